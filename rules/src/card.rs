@@ -0,0 +1,92 @@
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "weil", derive(weil_macros::WeilType))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Card {
+    ACE,
+    TWO,
+    THREE,
+    FOUR,
+    FIVE,
+    SIX,
+    SEVEN,
+    EIGHT,
+    NINE,
+    TEN,
+    JACK,
+    QUEEN,
+    KING,
+    JOKER
+}
+
+// how often a rank turns up in draws, the JOKER being the rarest
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "weil", derive(weil_macros::WeilType))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+}
+
+impl Card{
+    pub const ALL: [Card; 14] = [
+        Card::ACE,
+        Card::TWO,
+        Card::THREE,
+        Card::FOUR,
+        Card::FIVE,
+        Card::SIX,
+        Card::SEVEN,
+        Card::EIGHT,
+        Card::NINE,
+        Card::TEN,
+        Card::JACK,
+        Card::QUEEN,
+        Card::KING,
+        Card::JOKER,
+    ];
+
+    pub fn equivalent(card1: Card, card2: Card) -> bool {
+        card1 == Card::JOKER || card2 == Card::JOKER || card1 == card2
+    }
+
+    // rank order for scoring claims: TWO is the weakest and ACE the
+    // strongest natural rank, with the JOKER above every one of them
+    pub fn strength(&self) -> u32 {
+        match self {
+            Card::TWO => 2,
+            Card::THREE => 3,
+            Card::FOUR => 4,
+            Card::FIVE => 5,
+            Card::SIX => 6,
+            Card::SEVEN => 7,
+            Card::EIGHT => 8,
+            Card::NINE => 9,
+            Card::TEN => 10,
+            Card::JACK => 11,
+            Card::QUEEN => 12,
+            Card::KING => 13,
+            Card::ACE => 14,
+            Card::JOKER => 15,
+        }
+    }
+
+    pub fn rarity(&self) -> Rarity {
+        match self {
+            Card::JOKER => Rarity::Rare,
+            Card::ACE | Card::KING | Card::QUEEN | Card::JACK => Rarity::Uncommon,
+            _ => Rarity::Common,
+        }
+    }
+
+    // reference value of a rank in chips, rarer and stronger cards are worth more
+    pub fn reference_value(&self) -> u64 {
+        let units = match self {
+            Card::JOKER => 5,
+            Card::ACE => 3,
+            Card::KING | Card::QUEEN | Card::JACK => 2,
+            _ => 1,
+        };
+        units * crate::CHIPS_PER_UNIT
+    }
+}
@@ -0,0 +1,53 @@
+use crate::Card;
+
+pub const LISTING_FEE_PERCENT: u64 = 5;
+// share of the listing fee given back to the seller when an offer sells
+pub const LISTING_FEE_REFUND_PERCENT: u64 = 50;
+// sellers from this reputation on list at half the fee, and for free from
+// the veteran threshold on
+pub const TRUSTED_SELLER_REPUTATION: i64 = 20;
+pub const VETERAN_SELLER_REPUTATION: i64 = 100;
+// how much longer winners of a seller with negative reputation get to
+// confirm the purchase
+pub const RISKY_SELLER_CONFIRM_MULTIPLIER: u64 = 2;
+
+// fee charged at listing time, scaled by the reference value of the cards;
+// rounds down to whole chips
+pub fn listing_fee(cards: &[Card]) -> u64 {
+    listing_fee_on(cards.iter().map(|c| c.reference_value()).sum())
+}
+
+// fee for listing anything else worth `value` chips
+pub fn listing_fee_on(value: u64) -> u64 {
+    value * LISTING_FEE_PERCENT / 100
+}
+
+pub fn listing_fee_refund(fee: u64) -> u64 {
+    fee * LISTING_FEE_REFUND_PERCENT / 100
+}
+
+// the part of a listing `fee` a seller with `reputation` pays
+pub fn listing_fee_for_reputation(fee: u64, reputation: i64) -> u64 {
+    if reputation >= VETERAN_SELLER_REPUTATION {
+        0
+    } else if reputation >= TRUSTED_SELLER_REPUTATION {
+        fee / 2
+    } else {
+        fee
+    }
+}
+
+// sellers below zero reputation sell under full escrow: no installments,
+// and every open auction waits for the winner to confirm the purchase
+pub fn is_risky_seller(reputation: i64) -> bool {
+    reputation < 0
+}
+
+// blocks the winner of a seller with `reputation` has to confirm a purchase
+pub fn confirm_window_for_reputation(blocks: u64, reputation: i64) -> u64 {
+    if is_risky_seller(reputation) {
+        blocks.saturating_mul(RISKY_SELLER_CONFIRM_MULTIPLIER)
+    } else {
+        blocks
+    }
+}
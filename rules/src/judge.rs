@@ -0,0 +1,21 @@
+use crate::Card;
+
+// a stake claims that at least `claimed_count` of its cards are the claimed
+// card, it is a bluff when fewer of them are
+pub fn is_bluff(stake: &[Card], claimed: Card, claimed_count: u32) -> bool {
+    let matching = stake
+        .iter()
+        .filter(|card| Card::equivalent(**card, claimed))
+        .count();
+    matching < claimed_count as usize
+}
+
+// a stake can claim anything from a single matching card up to all of them
+pub fn is_valid_claim_count(stake: &[Card], claimed_count: u32) -> bool {
+    claimed_count >= 1 && claimed_count as usize <= stake.len()
+}
+
+// every stake has to match the previous one's size plus the hand's minimum raise
+pub fn is_valid_raise(previous: usize, next: usize, min_raise: u32) -> bool {
+    next >= previous.saturating_add(min_raise as usize)
+}
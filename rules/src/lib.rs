@@ -0,0 +1,32 @@
+//! The rules of Escalate with no runtime attached: card ranks and
+//! equivalence, bluff judging, payout math and the listing fee schedule.
+//!
+//! The contract judges every hand through this crate, so front-ends and
+//! bots that link it get exactly the same answers. It is `no_std` and has
+//! no required dependencies; enable `serde` to (de)serialize rule types.
+//!
+//! Every amount is a whole number of chips, the smallest unit the contract
+//! accounts in. One unit of currency is [`CHIPS_PER_UNIT`] chips, so a
+//! balance of `1.25` is stored and passed around as `125`.
+
+#![no_std]
+
+mod card;
+mod fees;
+mod judge;
+mod payout;
+
+// chips in one unit of currency
+pub const CHIPS_PER_UNIT: u64 = 100;
+
+pub use card::{Card, Rarity};
+pub use fees::{
+    LISTING_FEE_PERCENT, LISTING_FEE_REFUND_PERCENT, RISKY_SELLER_CONFIRM_MULTIPLIER,
+    TRUSTED_SELLER_REPUTATION, VETERAN_SELLER_REPUTATION, confirm_window_for_reputation,
+    is_risky_seller, listing_fee, listing_fee_for_reputation, listing_fee_on, listing_fee_refund,
+};
+pub use judge::{is_bluff, is_valid_claim_count, is_valid_raise};
+pub use payout::{
+    BLUFF_REWARD, EQUIVALENT_REWARD, card_reward, card_reward_with, checker_delta, stake_reward,
+    stake_reward_with,
+};
@@ -0,0 +1,32 @@
+use crate::{CHIPS_PER_UNIT, Card};
+
+pub const EQUIVALENT_REWARD: u64 = CHIPS_PER_UNIT;
+pub const BLUFF_REWARD: u64 = CHIPS_PER_UNIT * 6 / 5;
+
+// stakers earn more for the cards they dared to bluff with
+pub fn card_reward(card: Card, claimed: Card) -> u64 {
+    card_reward_with(card, claimed, BLUFF_REWARD)
+}
+
+// `card_reward` with a custom reward for bluffed cards
+pub fn card_reward_with(card: Card, claimed: Card, bluff_reward: u64) -> u64 {
+    if Card::equivalent(card, claimed) {
+        EQUIVALENT_REWARD
+    } else {
+        bluff_reward
+    }
+}
+
+pub fn stake_reward(stake: &[Card], claimed: Card) -> u64 {
+    stake_reward_with(stake, claimed, BLUFF_REWARD)
+}
+
+pub fn stake_reward_with(stake: &[Card], claimed: Card, bluff_reward: u64) -> u64 {
+    stake.iter().map(|card| card_reward_with(*card, claimed, bluff_reward)).sum()
+}
+
+// the checker wins the size of the last stake on a bluff and loses it otherwise
+pub fn checker_delta(last_stake: &[Card], bluff_detected: bool) -> i64 {
+    let size = (last_stake.len() as u64 * CHIPS_PER_UNIT) as i64;
+    if bluff_detected { size } else { -size }
+}
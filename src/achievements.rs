@@ -0,0 +1,63 @@
+use escalate_rules::CHIPS_PER_UNIT;
+
+use crate::elements::{Achievement, AchievementKind, Card, UnlockedAchievement, UserStats};
+
+impl AchievementKind {
+    pub const ALL: [AchievementKind; 5] = [
+        AchievementKind::FirstHandStarted,
+        AchievementKind::FirstBluffCaught,
+        AchievementKind::TenChecksWon,
+        AchievementKind::JokerSold,
+        AchievementKind::HundredHonestStakes,
+    ];
+
+    // the stat that counts towards the achievement
+    pub fn progress(&self, stats: &UserStats) -> u64 {
+        match self {
+            AchievementKind::FirstHandStarted => stats.hands_started,
+            AchievementKind::FirstBluffCaught => stats.bluffs_caught,
+            AchievementKind::TenChecksWon => stats.successful_checks,
+            AchievementKind::JokerSold => stats.jokers_sold,
+            AchievementKind::HundredHonestStakes => stats.honest_stakes,
+        }
+    }
+}
+
+impl Achievement {
+    // what `progress` has to reach and what unlocking it pays; the chips
+    // come from the treasury and the cards from the deck
+    pub fn for_kind(kind: AchievementKind) -> Self {
+        let (goal, reward_cards, reward_balance) = match kind {
+            AchievementKind::FirstHandStarted => (1, vec![Card::ACE], 0),
+            AchievementKind::FirstBluffCaught => (1, Vec::new(), CHIPS_PER_UNIT),
+            AchievementKind::TenChecksWon => (10, Vec::new(), 5 * CHIPS_PER_UNIT),
+            AchievementKind::JokerSold => (1, Vec::new(), 0),
+            AchievementKind::HundredHonestStakes => (100, vec![Card::JOKER], 0),
+        };
+        Achievement {
+            kind,
+            goal,
+            reward_cards,
+            reward_balance,
+        }
+    }
+}
+
+// JOKERs among `cards`, counted towards `JokerSold` when they are sold
+pub fn jokers_in(cards: &[Card]) -> u64 {
+    cards.iter().filter(|c| **c == Card::JOKER).count() as u64
+}
+
+pub fn achievements() -> Vec<Achievement> {
+    AchievementKind::ALL.iter().map(|kind| Achievement::for_kind(*kind)).collect()
+}
+
+// achievements `stats` reached that are not among `unlocked` yet
+pub fn newly_reached(stats: &UserStats, unlocked: &[UnlockedAchievement]) -> Vec<AchievementKind> {
+    AchievementKind::ALL
+        .iter()
+        .copied()
+        .filter(|kind| !unlocked.iter().any(|u| u.kind == *kind))
+        .filter(|kind| kind.progress(stats) >= Achievement::for_kind(*kind).goal)
+        .collect()
+}
@@ -0,0 +1,55 @@
+use crate::elements::{AutoBidRule, Card, Offer};
+
+impl AutoBidRule {
+    pub fn new(
+        rule_id: String,
+        owner_id: String,
+        card: Card,
+        max_bid: u64,
+        budget: u64,
+        height: u64,
+    ) -> Self {
+        AutoBidRule {
+            rule_id,
+            owner_id,
+            card,
+            max_bid,
+            budget,
+            is_active: true,
+            created_at_block: height,
+        }
+    }
+
+    // the most this rule can bid right now: its cap, or less once the
+    // budget runs low
+    pub fn limit(&self) -> u64 {
+        self.max_bid.min(self.budget)
+    }
+
+    // plain auctions of someone else's cards that hold the rule's rank
+    pub fn matches(&self, offer: &Offer) -> bool {
+        self.is_active
+            && self.owner_id != offer.creator_id
+            && offer.cards.contains(&self.card)
+            && offer.pack_id.is_none()
+            && offer.installments.is_none()
+            && !offer.is_sealed()
+            && offer.dutch.is_none()
+    }
+}
+
+// the rule that wins a new offer and what it bids: the highest limit above
+// the initial price wins, earliest rule first on ties, and it pays one chip
+// over the runner up's limit or the initial price, like an English auction
+// between the rules would end
+pub fn opening_bid(rules: &[AutoBidRule], offer: &Offer) -> Option<(String, u64)> {
+    let mut limits: Vec<(&AutoBidRule, u64)> = rules
+        .iter()
+        .filter(|r| r.matches(offer) && r.limit() > offer.initial_price)
+        .map(|r| (r, r.limit()))
+        .collect();
+    limits.sort_by_key(|l| std::cmp::Reverse(l.1));
+    let (winner, limit) = limits.first()?;
+    let floor = limits.get(1).map_or(offer.initial_price, |(_, l)| *l);
+    Some((winner.rule_id.clone(), (floor + 1).min(*limit)))
+}
@@ -0,0 +1,28 @@
+use crate::elements::{Card, CraftingRate, Rarity};
+
+impl CraftingRate {
+    // burning pays a fraction of what crafting the same card costs, so
+    // junk cards can be turned into specific ones but never at a profit
+    pub fn for_card(card: Card) -> Self {
+        let (burn_points, craft_cost) = match card.rarity() {
+            Rarity::Common => (1, 4),
+            Rarity::Uncommon => (2, 10),
+            Rarity::Rare => (5, 30),
+        };
+        CraftingRate {
+            card,
+            rarity: card.rarity(),
+            burn_points,
+            craft_cost,
+        }
+    }
+}
+
+// crafting points burning `cards` earns
+pub fn burn_value(cards: &[Card]) -> u64 {
+    cards.iter().map(|c| CraftingRate::for_card(*c).burn_points).sum()
+}
+
+pub fn crafting_rates() -> Vec<CraftingRate> {
+    Card::ALL.iter().map(|c| CraftingRate::for_card(*c)).collect()
+}
@@ -0,0 +1,119 @@
+//! The finite pool every drawn card is dealt from. It starts as a number
+//! of standard decks (four of each rank and two jokers per deck), shrinks
+//! as cards are dealt and grows back when checked hands return their
+//! stakes. Draws are reserved when they are committed, so a paid draw can
+//! always be dealt when it is revealed. Each copy left is drawn with the
+//! weight of its rank's rarity, so rare cards stay rare as the deck thins.
+
+use serde::{Deserialize, Serialize};
+use weil_macros::WeilType;
+
+use crate::elements::{Card, DropRate};
+use crate::randomness;
+use crate::token;
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Deck {
+    // cards of each rank left to deal, indexed by token id
+    pub remaining: Vec<u64>,
+    // cards promised to committed draws that have not been revealed yet
+    pub reserved: u64,
+}
+
+impl Deck {
+    pub fn standard(decks: u64) -> Self {
+        let remaining = Card::ALL
+            .iter()
+            .map(|card| if *card == Card::JOKER { 2 * decks } else { 4 * decks })
+            .collect();
+        Deck { remaining, reserved: 0 }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.remaining.iter().sum()
+    }
+
+    // cards that are neither dealt nor reserved
+    pub fn available(&self) -> u64 {
+        self.size() - self.reserved
+    }
+
+    pub fn reserve(&mut self, count: u64) -> Result<(), String> {
+        if count > self.available() {
+            return Err(format!("the deck has only {} cards left to deal", self.available()));
+        }
+        self.reserved += count;
+        Ok(())
+    }
+
+    // frees the cards of a draw that will never be dealt
+    pub fn release(&mut self, count: u64) {
+        self.reserved = self.reserved.saturating_sub(count);
+    }
+
+    // copies left of each rank times its drop weight
+    fn weighted(&self, weights: &[u64]) -> Vec<u64> {
+        self.remaining.iter().zip(weights).map(|(n, w)| n * w).collect()
+    }
+
+    // deals a reserved draw; every card is picked among the cards still in
+    // the deck by `weights`, one per rank, so the pool is reshuffled on each deal
+    pub fn deal(&mut self, seed: &[u8], count: u32, weights: &[u64]) -> Vec<Card> {
+        self.reserved = self.reserved.saturating_sub(count as u64);
+        (0..count)
+            .map(|i| {
+                let weighted = self.weighted(weights);
+                let mut pick = randomness::draw_word(seed, i) % weighted.iter().sum::<u64>();
+                let idx = weighted
+                    .iter()
+                    .position(|n| {
+                        let hit = pick < *n;
+                        pick = pick.saturating_sub(*n);
+                        hit
+                    })
+                    .expect("a reserved draw never exceeds the deck");
+                self.remaining[idx] -= 1;
+                Card::ALL[idx]
+            })
+            .collect()
+    }
+
+    pub fn drop_rates(&self, weights: &[u64]) -> Vec<DropRate> {
+        let weighted = self.weighted(weights);
+        let total: u64 = weighted.iter().sum();
+        Card::ALL
+            .iter()
+            .zip(weighted)
+            .zip(&self.remaining)
+            .map(|((card, weight), remaining)| DropRate {
+                card: *card,
+                rarity: card.rarity(),
+                remaining: *remaining,
+                per_million: (weight as u128 * 1_000_000 / total.max(1) as u128) as u64,
+            })
+            .collect()
+    }
+
+    // pulls specific cards out of the deck, leaving enough for every
+    // reserved draw
+    pub fn take(&mut self, cards: &[Card]) -> Result<(), String> {
+        if cards.len() as u64 > self.available() {
+            return Err(format!("the deck has only {} cards left to deal", self.available()));
+        }
+        for card in Card::ALL {
+            if token::balance_of(cards, card) > self.remaining[token::token_id(card)] {
+                return Err(format!("the deck has run out of {:?}", card));
+            }
+        }
+        for card in cards {
+            self.remaining[token::token_id(*card)] -= 1;
+        }
+        Ok(())
+    }
+
+    pub fn return_cards(&mut self, cards: &[Card]) {
+        for card in cards {
+            self.remaining[token::token_id(*card)] += 1;
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! A machine-readable catalog of the contract's entrypoints, read from the
+//! interface definition compiled into the contract. The WIDL file already
+//! changes with every entrypoint, so it doubles as the registry: every
+//! `mutate func` or `query func` line becomes one entry, its comment lines
+//! become the entry's doc, and a doc starting with "admin only" marks an
+//! entrypoint that rejects everyone but the admin. No entrypoint depends
+//! on a cargo feature.
+
+use crate::elements::{EntrypointRole, EntrypointSchema, ParamSchema};
+
+const INTERFACE: &str = include_str!("../escalate.widl");
+
+pub fn catalog() -> Vec<EntrypointSchema> {
+    let mut entries = Vec::new();
+    let mut doc: Vec<&str> = Vec::new();
+    for line in INTERFACE.lines().map(str::trim) {
+        if let Some(comment) = line.strip_prefix("//") {
+            doc.push(comment.trim());
+            continue;
+        }
+        let entry = match (line.strip_prefix("mutate func "), line.strip_prefix("query func ")) {
+            (Some(sig), _) => parse_signature(sig, true, &doc),
+            (_, Some(sig)) => parse_signature(sig, false, &doc),
+            _ => None,
+        };
+        entries.extend(entry);
+        doc.clear();
+    }
+    entries
+}
+
+// `name(a: T, b: U) -> R;` into its schema, the trailing semicolon is optional
+fn parse_signature(sig: &str, mutates: bool, doc: &[&str]) -> Option<EntrypointSchema> {
+    let (name, rest) = sig.split_once('(')?;
+    let (params, returns) = rest.split_once(')')?;
+    let returns = returns.trim().strip_prefix("->")?.trim().trim_end_matches(';');
+    let doc = doc.join(" ");
+    let role = if doc.starts_with("admin only") {
+        EntrypointRole::Admin
+    } else {
+        EntrypointRole::Anyone
+    };
+
+    Some(EntrypointSchema {
+        name: name.trim().to_string(),
+        mutates,
+        params: split_params(params)
+            .into_iter()
+            .filter_map(|p| {
+                let (name, ty) = p.split_once(':')?;
+                Some(ParamSchema {
+                    name: name.trim().to_string(),
+                    ty: ty.trim().to_string(),
+                })
+            })
+            .collect(),
+        returns: returns.trim().to_string(),
+        role,
+        doc,
+    })
+}
+
+// splits on the commas that are not nested inside a generic type
+fn split_params(params: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in params.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&params[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&params[start..]);
+    parts.into_iter().filter(|p| !p.trim().is_empty()).collect()
+}
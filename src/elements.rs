@@ -1,43 +1,91 @@
 use serde::{Deserialize, Serialize};
 use weil_macros::WeilType;
-use weil_rs::runtime::Runtime;
+
+pub use escalate_rules::{Card, Rarity};
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct User {
+    pub user_id: String,
+    pub bio: String,
+    pub balance: u64,
+    // escrowed for the user's open bids, auto-bid budgets and side bets, on
+    // top of the spendable `balance`
+    pub locked_balance: u64,
+    pub cards: Vec<Card>,
+    pub registered_at_block: u64,
+    // cold storage rejects all gameplay until a requested unlock takes effect
+    pub cold_storage: bool,
+    pub unlock_at_block: Option<u64>,
+    // opted out of the per-hand loss limit
+    pub high_roller: bool,
+    // rises with honest stakes and caught bluffs, falls when the user's own
+    // bluff is caught or they pull an offer nobody bid on
+    pub reputation: i64,
+    // set once the starter bundle was granted, so it is only handed out once
+    pub starter_granted: bool,
+    // block of the latest free daily draw, if the user ever claimed one
+    pub last_claim_block: Option<u64>,
+    // earned by burning cards, spent crafting specific ones
+    pub crafting_points: u64,
+    // who referred the user at registration, paid a cut of their fees for a while
+    pub referrer: Option<String>,
+}
+
+// what a referrer earned from one referee so far; the referee's card
+// purchases and rakes pay the referrer until `ends_at_block`
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct ReferralEarning {
+    pub referee_id: String,
+    pub earned: u64,
+    pub ends_at_block: u64,
+}
 
 #[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
-pub enum Card {
-    ACE,
-    TWO,
-    THREE,
-    FOUR,
-    FIVE,
-    SIX,
-    SEVEN,
-    EIGHT,
-    NINE,
-    TEN,
-    JACK,
-    QUEEN,
-    KING,
-    JOKER
+pub enum EscrowKind {
+    Offer,
+    Trade,
+    Hand,
+    Challenge,
 }
 
-impl Card{
-    pub fn equivalent(card1: Card, card2: Card) -> bool {
-        card1 == Card::JOKER || card2 == Card::JOKER || card1 == card2
-    }
+// cards out of a user's inventory while the offer, trade, hand or challenge
+// `subject_id` is open; they come back or change hands once it closes
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct EscrowedCards {
+    pub kind: EscrowKind,
+    pub subject_id: String,
+    pub cards: Vec<Card>,
 }
 
+// the chips a user can spend, those escrowed for them and what they owe
 #[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
-pub struct User {
-    pub user_id: String,
-    pub bio: String,
-    pub balance: f64,
-    pub cards: Vec<Card>
+pub struct BalanceBreakdown {
+    pub available: u64,
+    pub locked: u64,
+    pub debt: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
 pub struct Stake {
     pub user_id: String,
     pub cards: Vec<Card>,
+    pub claimed_count: u32,
+    // arrival order within the hand starting at 1, the stake a new one must
+    // raise is the one with the highest seq
+    pub seq: u32,
+    pub staked_at_block: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum HandStatus {
+    Open,
+    Checked,
+    // nobody checked before the deadline, every stake was returned
+    Expired,
+    // withdrawn by the creator before anyone else staked
+    Canceled,
+    // every player but one passed, every stake was returned
+    Folded,
 }
 
 #[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
@@ -45,8 +93,172 @@ pub struct Hand {
     pub hand_id: String,
     pub creator: String,
     pub claimed_card: Card,
-    pub is_resolved: bool,
+    pub status: HandStatus,
     pub stakes: Vec<Stake>,
+    pub region_tag: Option<String>,
+    pub expires_at_block: u64,
+    pub duration_blocks: u64,
+    pub min_raise: u32,
+    pub max_stakes: u32,
+    // no player may place two stakes in a row
+    pub alternate_stakers: bool,
+    // stakes each player may place, the opening one included; none for no limit
+    pub max_stakes_per_player: Option<u32>,
+    pub newcomers_only: bool,
+    // only users who staked on the hand may check it
+    pub participants_only: bool,
+    // reference value of every card staked so far, in chips
+    pub pot: u64,
+    // who checked the hand and what they found, once it is checked
+    pub resolution: Option<CheckResolution>,
+    // seats of a turn-based hand in the order they act, empty when anyone
+    // may stake or check at any time
+    pub turn_order: Vec<String>,
+    // index into `turn_order` of the player to act, equal to its length
+    // while the creator waits for someone to join
+    pub current_turn: u32,
+    // players who passed and sit out the rest of the hand
+    pub passed: Vec<String>,
+    // bumped every time the hand is stored, so observers can tell it changed
+    pub update_seq: u64,
+    // invitees of a private hand besides the creator, empty when anyone may play
+    pub allowed_stakers: Vec<String>,
+    // reputation everyone but the creator needs to stake on or check the hand
+    pub min_reputation: Option<i64>,
+    // the tournament the hand counts towards, only its players may play it
+    pub tournament_id: Option<String>,
+    // extra percent of the pot the treasury pays the creator once the hand
+    // is checked, for claiming a rank few recent hands claimed
+    pub claim_bonus_percent: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum HandActionKind {
+    Started,
+    Staked,
+    Joined,
+    Passed,
+    Checked,
+    Expired,
+    Canceled,
+    Folded,
+}
+
+// one step of a hand in the order it happened; stakes carry their cards
+// unmasked, checks whether a bluff was found
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct HandAction {
+    pub seq: u32,
+    pub user_id: String,
+    pub kind: HandActionKind,
+    pub cards: Vec<Card>,
+    pub claimed_count: u32,
+    pub bluff_detected: Option<bool>,
+    pub at_block: u64,
+}
+
+// everything that happened in a resolved hand, for reviews and disputes
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct HandReplay {
+    pub hand: Hand,
+    pub actions: Vec<HandAction>,
+    // what the check settled, empty for hands that ended without one
+    pub payouts: Vec<PayoutLine>,
+}
+
+// answer to a poll for changes; `hand` is only sent when it changed
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct HandUpdate {
+    pub hand_id: String,
+    pub update_seq: u64,
+    pub changed: bool,
+    pub hand: Option<Hand>,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct CheckResolution {
+    pub checker_id: String,
+    pub bluff_detected: bool,
+    pub checked_at_block: u64,
+}
+
+// table settings chosen by the creator when starting a hand
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct HandOptions {
+    pub region_tag: Option<String>,
+    pub duration_blocks: u64,
+    pub min_raise: u32,
+    pub max_stakes: u32,
+    pub alternate_stakers: bool,
+    pub max_stakes_per_player: Option<u32>,
+    // restricts the hand to the newcomer bracket
+    pub newcomers_only: bool,
+    pub participants_only: bool,
+    // players join and take turns staking, passing or checking
+    pub turn_based: bool,
+    // makes the hand private to these users and the creator
+    pub allowed_stakers: Vec<String>,
+    pub min_reputation: Option<i64>,
+    // plays the hand in this tournament, open to its players only
+    pub tournament_id: Option<String>,
+}
+
+// the two sides of a 2v2 team hand, the creator always plays on `team_a`.
+// Stakes alternate between the teams and the team that did not place the
+// last stake checks it once a majority of its members confirm
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct TeamHand {
+    pub hand_id: String,
+    pub team_a: Vec<String>,
+    pub team_b: Vec<String>,
+    pub confirm_window_blocks: u64,
+    pub pending_check: Option<TeamCheck>,
+}
+
+// confirmations gathered towards a team check, dropped once the window lapses
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct TeamCheck {
+    pub confirmations: Vec<String>,
+    pub opened_at_block: u64,
+}
+
+// team assignment chosen by the creator when starting a team hand
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct TeamOptions {
+    pub teammate: String,
+    pub opponents: Vec<String>,
+    pub confirm_window_blocks: u64,
+}
+
+// lobby filters and paging for `get_open_hands`, unset fields match every
+// open hand
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct HandFilter {
+    pub claimed_card: Option<Card>,
+    // minimum pot, in chips
+    pub min_pot: Option<u64>,
+    pub creator: Option<String>,
+    // skips expired hands that can no longer be staked on
+    pub unresolved_only: bool,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum HandSort {
+    Newest,
+    BiggestPot,
+    MostStakes,
+}
+
+// arguments of `search_hands`, unset filters match every hand
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct HandQuery {
+    pub status: Option<HandStatus>,
+    pub creator: Option<String>,
+    pub sort: HandSort,
+    pub offset: u64,
+    pub limit: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
@@ -54,52 +266,983 @@ pub struct Offer {
     pub offer_id: String,
     pub creator_id: String,
     pub cards: Vec<Card>,
-    pub initial_price: f64,
-    pub current_bid: Option<f64>,
+    pub initial_price: u64,
+    pub current_bid: Option<u64>,
     pub current_bidder_id: Option<String>,
     pub is_resolved: bool,
+    pub listing_fee: u64,
+    pub expires_at: u64,
+    pub installments: Option<InstallmentTerms>,
+    // set for sealed-bid auctions, bids are revealed in the blocks before it
+    pub reveal_deadline: Option<u64>,
+    // set when the offer sells a sealed pack instead of cards
+    pub pack_id: Option<String>,
+    // fixed price anyone can pay through `buy_now` to settle the sale at once
+    pub buyout_price: Option<u64>,
+    // the offer is listed ahead of unboosted ones until this height
+    pub boost_until: u64,
+    // everything the seller paid the treasury for boosts
+    pub boost_fees_paid: u64,
+    // the seller's split of the initial price across `cards`, in the same order
+    pub card_prices: Option<Vec<u64>>,
+    // set for descending-price auctions, sold through `accept_dutch`
+    pub dutch: Option<DutchTerms>,
+    // every bid after the first must beat the previous one by this much
+    pub min_increment: u64,
+    // the offer only sells for at least this much, otherwise resolving it
+    // hands the cards back and refunds the highest bidder
+    pub reserve_price: Option<u64>,
+    // where the seller and the current bidder stand on a counter price
+    pub negotiation_state: NegotiationState,
+    pub counter_price: Option<u64>,
+    // set for bundles: buyers take any of `cards` at this price each through
+    // `buy_from_bundle`, and the offer closes once none are left
+    pub price_per_card: Option<u64>,
+    // resolving only asks the current bidder to confirm the purchase through
+    // `confirm_purchase`, which they must do before `confirm_by`
+    pub requires_confirmation: bool,
+    pub confirm_by: Option<u64>,
 }
 
-pub fn get_random_cards(num: u32) -> Vec<Card> {
-    let deck = [
-        Card::ACE,
-        Card::TWO,
-        Card::THREE,
-        Card::FOUR,
-        Card::FIVE,
-        Card::SIX,
-        Card::SEVEN,
-        Card::EIGHT,
-        Card::NINE,
-        Card::TEN,
-        Card::JACK,
-        Card::QUEEN,
-        Card::KING,
-        Card::JOKER,
-    ];
-
-    let seed = Runtime::block_height();
-
-    (0..num)
-        .map(|i| {
-            let idx = ((seed + i as u64) as usize) % deck.len();
-            deck[idx]
-        })
-        .collect()
+// `Countered` waits for the current bidder to answer `counter_price`;
+// `Accepted` locks the offer at that price until the seller resolves it
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum NegotiationState {
+    None,
+    Countered,
+    Accepted,
+    Declined,
 }
 
-pub fn is_bluff(hand: &Hand) -> bool{
-    let claim_card = hand.claimed_card;
+// the initial price of a dutch auction drops by `decay_per_block` every
+// block after it started, never below `floor_price`
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct DutchTerms {
+    pub floor_price: u64,
+    pub decay_per_block: u64,
+    pub started_at_block: u64,
+}
+
+// the part of an offer's price attributed to one of its cards
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct CardValue {
+    pub card: Card,
+    pub value: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum PackKind {
+    Booster,
+    Box,
+}
+
+// an unopened pack, its cards are only drawn when the owner opens it
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Pack {
+    pub pack_id: String,
+    pub kind: PackKind,
+    pub owner_id: String,
+    pub is_listed: bool,
+    pub is_opened: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum TradeStatus {
+    Open,
+    Accepted,
+    // declined by the counterparty or withdrawn by the proposer
+    Rejected,
+}
+
+// bids on new offers holding `card` for `owner_id`, up to `max_bid` each.
+// `budget` is escrowed at creation and every bid is paid out of it; chips
+// refunded when the rule is outbid go back to the owner's balance
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct AutoBidRule {
+    pub rule_id: String,
+    pub owner_id: String,
+    pub card: Card,
+    pub max_bid: u64,
+    pub budget: u64,
+    pub is_active: bool,
+    pub created_at_block: u64,
+}
+
+// users who can jointly move the account to a new key, `threshold` of them
+// need to approve
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct RecoveryGuardians {
+    pub guardians: Vec<String>,
+    pub threshold: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryStatus {
+    Pending,
+    // stopped by the original key during the challenge window
+    Vetoed,
+    Completed,
+}
+
+// a guardian initiated move of `old_id` to `new_id`; it can be completed
+// from `executable_at_block`, which is set once enough guardians approved
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct RecoveryRequest {
+    pub old_id: String,
+    pub new_id: String,
+    pub approvals: Vec<String>,
+    pub started_at_block: u64,
+    pub executable_at_block: Option<u64>,
+    pub status: RecoveryStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeStatus {
+    Pending,
+    // the hand was opened, see `hand_id`
+    Accepted,
+    // turned down by the friend or withdrawn by the challenger
+    Declined,
+    // reclaimed by the challenger after nobody answered in time
+    Expired,
+}
+
+// a player waiting for an opponent who wants to play for the same number
+// of cards; nothing is escrowed until the two are matched
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct QueueEntry {
+    pub user_id: String,
+    pub stake_size: u32,
+    pub joined_at_block: u64,
+}
+
+// a private two-player hand offered to a friend; the opening stake is
+// escrowed until the friend accepts and the hand is opened with it
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Challenge {
+    pub challenge_id: String,
+    pub challenger_id: String,
+    pub friend_id: String,
+    pub claim: Card,
+    pub cards: Vec<Card>,
+    pub status: ChallengeStatus,
+    pub hand_id: Option<String>,
+    pub expires_at_block: u64,
+}
+
+// a season ranks players by `metric` over what they do while it runs; the
+// best players take `prizes` from the treasury, first place the first one
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct SeasonConfig {
+    pub name: String,
+    pub metric: StatsMetric,
+    pub prizes: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct SeasonPrize {
+    pub user_id: String,
+    pub rank: u32,
+    pub amount: u64,
+}
+
+// `players` lists everyone with season stats in the order they first
+// scored, `ended_at_block` is set once the prizes were paid
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Season {
+    pub season_id: String,
+    pub config: SeasonConfig,
+    pub started_at_block: u64,
+    pub ended_at_block: Option<u64>,
+    pub players: Vec<String>,
+    pub prizes_paid: Vec<SeasonPrize>,
+    // chips staked on the hands checked and paid for the cards sold while
+    // the season ran
+    pub volume: u64,
+    // the biggest pots checked during the season, largest first
+    pub notable_hands: Vec<NotableHand>,
+    // users who registered with a referrer while the season ran
+    pub referees: Vec<String>,
+    pub referral_rewards: Vec<SeasonPrize>,
+}
+
+// a referrer's place on the running season's referral leaderboard. Only
+// referees who played enough hands qualify, `reward` is what the referrer's
+// tier pays at season end before the budget runs out
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct ReferralStanding {
+    pub referrer_id: String,
+    pub qualified_referees: u32,
+    pub pending_referees: u32,
+    pub reward: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct NotableHand {
+    pub hand_id: String,
+    pub pot: u64,
+    pub checker_id: String,
+    pub bluff_detected: bool,
+}
+
+// what an ended season keeps once its players' season stats are dropped
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct SeasonArchive {
+    pub season_id: String,
+    pub name: String,
+    pub metric: StatsMetric,
+    pub started_at_block: u64,
+    pub ended_at_block: u64,
+    // the final standings, cut to the best players
+    pub standings: Vec<UserStats>,
+    pub prizes_paid: Vec<SeasonPrize>,
+    pub volume: u64,
+    pub notable_hands: Vec<NotableHand>,
+}
+
+// players register until `starts_at_block` and play tournament hands from
+// then until `ends_at_block`
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct TournamentConfig {
+    pub name: String,
+    pub entry_fee: u64,
+    pub max_players: u32,
+    pub starts_at_block: u64,
+    pub ends_at_block: u64,
+    // chips the creator puts up on top of the entry fees, locked in their
+    // balance from creation until the tournament is finalized or cancelled
+    pub prize_escrow: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum TournamentStatus {
+    Open,
+    Finalized,
+    Cancelled,
+}
+
+// entry fees and the creator's prize escrow make up `prize_pool` until the
+// tournament is finalized
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Tournament {
+    pub tournament_id: String,
+    pub creator_id: String,
+    pub config: TournamentConfig,
+    pub status: TournamentStatus,
+    // in the order they joined, which also breaks ties in the standings
+    pub players: Vec<String>,
+    pub hand_ids: Vec<String>,
+    pub prize_pool: u64,
+    pub created_at_block: u64,
+}
+
+// a player's chips won or lost across the tournament's checked hands
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Standing {
+    pub user_id: String,
+    pub net_winnings: i64,
+    pub hands_played: u64,
+}
+
+// a card-for-card swap, the offered cards are escrowed until it closes
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Trade {
+    pub trade_id: String,
+    pub proposer_id: String,
+    pub counterparty_id: String,
+    pub offered_cards: Vec<Card>,
+    pub requested_cards: Vec<Card>,
+    pub status: TradeStatus,
+    pub proposed_at_block: u64,
+}
+
+// a hidden bid on a sealed-bid offer, binding once revealed
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct SealedBid {
+    pub bidder_id: String,
+    pub commitment: String,
+    pub committed_at_block: u64,
+    pub revealed_amount: Option<u64>,
+    // locked until the bid is revealed, forfeited if it never is
+    pub deposit: u64,
+}
+
+// how a seller lets the winning bid be paid off; missing a due block
+// forfeits `forfeit_percent` of what was paid so far to the seller
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct InstallmentTerms {
+    pub count: u32,
+    pub interval_blocks: u64,
+    pub forfeit_percent: u64,
+}
+
+// a sold offer being paid off; payments and cards stay in escrow here
+// until the last installment lands or the buyer defaults
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct InstallmentPlan {
+    pub offer_id: String,
+    pub buyer_id: String,
+    pub seller_id: String,
+    pub cards: Vec<Card>,
+    pub total: u64,
+    pub paid: u64,
+    pub installments_paid: u32,
+    pub terms: InstallmentTerms,
+    pub next_due_block: u64,
+    pub is_settled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct SideBet {
+    pub bet_id: String,
+    pub hand_id: String,
+    pub proposer_id: String,
+    pub opponent_id: String,
+    pub predicts_bluff: bool,
+    pub amount: u64,
+    pub is_accepted: bool,
+    pub is_settled: bool,
+}
+
+// a spectator's share of the parimutuel pool on a hand
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct PoolWager {
+    pub user_id: String,
+    pub predicts_bluff: bool,
+    pub amount: u64,
+    pub placed_at_block: u64,
+}
+
+// table talk posted by one of a hand's players
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct HandMessage {
+    pub message_id: u64,
+    pub hand_id: String,
+    pub author_id: String,
+    pub text: String,
+    pub posted_at_block: u64,
+    // set by a moderator for profanity; clients decide whether to hide it
+    pub flagged: bool,
+}
+
+// wagers of non-players on a hand, settled when it is checked
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct SpectatorPool {
+    pub hand_id: String,
+    pub wagers: Vec<PoolWager>,
+    pub is_settled: bool,
+}
+
+// one slice of a list query along with the size of the whole list
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PageResult<T> {
+    pub items: Vec<T>,
+    pub total_count: u64,
+}
+
+pub type UserPage = PageResult<User>;
+pub type HandPage = PageResult<Hand>;
+pub type OfferPage = PageResult<Offer>;
+pub type EnrichedOfferPage = PageResult<EnrichedOffer>;
+pub type ActivityPage = PageResult<Activity>;
+pub type HandMessagePage = PageResult<HandMessage>;
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct CheckPreview {
+    pub hand_id: String,
+    pub if_bluff: i64,
+    pub if_not_bluff: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum PayoutReason {
+    CheckerReward,
+    CheckerPenalty,
+    StakeReward,
+    SideBetWon,
+    SideBetRefund,
+    NewcomerSubsidy,
+    ClaimDiversityBonus,
+    Rake,
+    PoolWon,
+    PoolRefund,
+}
+
+// a single balance movement made by a check, negative amounts are debits
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct PayoutLine {
+    pub user_id: String,
+    pub amount: i64,
+    pub reason: PayoutReason,
+}
 
+// everything a check settled, in the order it was applied
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct CheckOutcome {
+    pub hand_id: String,
+    pub bluff_detected: bool,
+    pub pot: u64,
+    pub payouts: Vec<PayoutLine>,
+}
+
+// values the admin can tune without redeploying the contract
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, PartialEq)]
+pub struct GameParams {
+    // balance every newly registered account starts with
+    pub starting_balance: u64,
+    // penalties never take a balance below this, the rest becomes debt; nor
+    // do withdrawals and gifts
+    pub balance_floor: u64,
+    // price of a single card drawn through `buy_cards`
+    pub card_price: u64,
+    // reward for a bluffed card, as a percent of the reward for an equivalent one
+    pub bluff_reward_percent: u64,
+    // most cards a single stake, including the opening one, may hold
+    pub max_stake_cards: u32,
+    // most a player may risk on a single hand, as a percent of their balance;
+    // high rollers are exempt
+    pub max_hand_loss_percent: u64,
+    // what catching a bluff on each claimed rank pays the checker, in Card
+    // order, as a percent of the base reward
+    pub checker_reward_percent: Vec<u64>,
+    // relative odds of drawing a card of each rarity, per copy left in the deck
+    pub drop_weights: DropWeights,
+    // cards dealt out of the deck to every newly registered account
+    pub starter_cards: Vec<Card>,
+    // cards a user may draw for free through `claim_daily_cards`, once per
+    // `daily_claim_blocks`; zero turns the claim off
+    pub daily_claim_cards: u32,
+    pub daily_claim_blocks: u64,
+    // the treasury's cut of every checked hand's pot, taken from its winners,
+    // and of every completed sale, taken from the seller
+    pub hand_rake_percent: u64,
+    pub sale_rake_percent: u64,
+    // the referrer's share of what a referee pays for cards and in rakes,
+    // paid out of the treasury's take for `referral_blocks` after they register
+    pub referral_percent: u64,
+    pub referral_blocks: u64,
+    // most cards one inventory may hold, counting draws still to be revealed;
+    // zero lifts the cap
+    pub max_inventory_cards: u32,
+    // a player who made no call of their own for `decay_blocks` loses
+    // `decay_percent` of their oldest cards back to the deck; zero blocks
+    // turns decay off
+    pub decay_blocks: u64,
+    pub decay_percent: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, PartialEq)]
+pub struct DropWeights {
+    pub common: u64,
+    pub uncommon: u64,
+    pub rare: u64,
+}
+
+// cards of a rank in existence: in inventories, escrowed or staked
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct CardSupply {
+    pub card: Card,
+    pub supply: u64,
+}
+
+// one completed sale of a rank, priced per card
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, PartialEq)]
+pub struct SalePrice {
+    pub price_per_card: u64,
+    pub sold_at_block: u64,
+}
+
+// recent sale prices of a rank, per card; the median is the lower middle
+// price when there is an even number of sales
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct CardPriceStats {
+    pub card: Card,
+    pub sales: u64,
+    pub min: u64,
+    pub max: u64,
+    pub average: u64,
+    pub median: u64,
+    pub last: Option<SalePrice>,
+}
+
+// the chance that the next card drawn is `card`, in parts per million
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct DropRate {
+    pub card: Card,
+    pub rarity: Rarity,
+    pub remaining: u64,
+    pub per_million: u64,
+}
+
+// crafting points one card of `card` yields when burned and costs to craft
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct CraftingRate {
+    pub card: Card,
+    pub rarity: Rarity,
+    pub burn_points: u64,
+    pub craft_cost: u64,
+}
+
+// blocks during which every non-admin mutation is rejected, from
+// `start_block` up to but excluding `end_block`
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct MaintenanceWindow {
+    pub start_block: u64,
+    pub end_block: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Health {
+    pub block_height: u64,
+    pub paused: bool,
+    // the scheduled or running maintenance window, if any
+    pub maintenance: Option<MaintenanceWindow>,
+    pub in_maintenance: bool,
+}
+
+// who may call an entrypoint; everything else is open to any registered
+// user, subject to pauses and maintenance
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum EntrypointRole {
+    Anyone,
+    Admin,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct ParamSchema {
+    pub name: String,
+    // the WIDL type, e.g. `option<list<Card>>`
+    pub ty: String,
+}
+
+// one entrypoint of the interface, types are spelled as in the WIDL
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct EntrypointSchema {
+    pub name: String,
+    pub mutates: bool,
+    pub params: Vec<ParamSchema>,
+    pub returns: String,
+    pub role: EntrypointRole,
+    pub doc: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Succession {
+    pub successor: String,
+    pub announced_at_block: u64,
+    pub creation_frozen: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct ExternalIdentity {
+    pub platform: String,
+    pub handle: String,
+    pub proof: String,
+    pub is_verified: bool,
+    pub linked_at_block: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct IdentityProfile {
+    pub is_public: bool,
+    pub identities: Vec<ExternalIdentity>,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct PendingDraw {
+    pub draw_id: String,
+    pub user_id: String,
+    pub count: u32,
+    pub commitment: String,
+    pub committed_at_block: u64,
+    // the entropy pool as the block after the commit left it, set by the
+    // first mix of any later block
+    pub seed_pool: Option<String>,
+    // unrevealed after this block the draw can only be forfeited
+    pub reveal_by_block: u64,
+}
+
+// cumulative counters over every checked hand, one snapshot is kept per
+// check so any trailing window can be read as the difference of two
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Default)]
+pub struct ResolutionTotals {
+    pub resolutions: u64,
+    pub bluffs: u64,
+    pub stakes: u64,
+    pub staked_cards: u64,
+    pub checker_wins: u64,
+}
+
+// cumulative claims of each rank in Card order, one snapshot is kept per
+// started hand so any trailing window can be read as the difference of two
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Default)]
+pub struct ClaimTotals {
+    pub hands: u64,
+    pub claims: Vec<u64>,
+}
+
+// how often `card` was claimed over the recent hands and the bonus a new
+// hand claiming it earns its creator
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct ClaimBonus {
+    pub card: Card,
+    pub recent_claims: u64,
+    pub bonus_percent: u64,
+}
+
+// aggregates over the most recent checked hands, sizes are in cards
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct ResolutionStats {
+    pub resolutions: u64,
+    pub bluff_rate: f64,
+    pub average_stake_size: f64,
+    pub average_pot: f64,
+    pub checker_win_rate: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum TransferStatus {
+    Pending,
+    Completed,
+    Failed,
+    // the escrowed amount went back to the user's balance
+    Reclaimed,
+}
+
+// everything the contract stores about one user. Hands show other
+// players' stakes masked, `events` is the user's full history
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct UserDataExport {
+    pub user: User,
+    pub debt: u64,
+    pub stats: Option<UserStats>,
+    pub identities: Option<IdentityProfile>,
+    pub hands: Vec<Hand>,
+    pub side_bets: Vec<SideBet>,
+    pub offers: Vec<Offer>,
+    pub sealed_bids: Vec<SealedBid>,
+    pub installment_plans: Vec<InstallmentPlan>,
+    pub trades: Vec<Trade>,
+    pub packs: Vec<Pack>,
+    pub pending_draws: Vec<PendingDraw>,
+    pub transfers: Vec<TransferIntent>,
+    pub events: Vec<Event>,
+}
+
+// a player's record across every hand and sale they took part in
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct UserStats {
+    pub user_id: String,
+    pub hands_started: u64,
+    // bluffs found while checking
+    pub bluffs_caught: u64,
+    // earlier stakes of checked hands that were bluffs but never got checked
+    pub bluffs_gotten_away_with: u64,
+    // checks that left the checker better off
+    pub successful_checks: u64,
+    // net of every check payout plus the price of every card sale, in chips
+    pub total_winnings: i64,
+    // stakes in checked hands, and how many of them matched their claim
+    pub checked_stakes: u64,
+    pub honest_stakes: u64,
+    // JOKERs the player sold through offers
+    pub jokers_sold: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum AchievementKind {
+    FirstHandStarted,
+    FirstBluffCaught,
+    TenChecksWon,
+    JokerSold,
+    HundredHonestStakes,
+}
+
+// a milestone a player unlocks once the stat behind it reaches `goal`, and
+// what they can claim for it
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Achievement {
+    pub kind: AchievementKind,
+    pub goal: u64,
+    pub reward_cards: Vec<Card>,
+    pub reward_balance: u64,
+}
+
+// a badge a player earned; the achievement's rewards wait until they claim them
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct UnlockedAchievement {
+    pub kind: AchievementKind,
+    pub unlocked_at_block: u64,
+    pub rewards_claimed: bool,
+}
+
+// where a player stands on one achievement, `progress` stops at the goal
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct AchievementProgress {
+    pub achievement: Achievement,
+    pub progress: u64,
+    pub unlocked: Option<UnlockedAchievement>,
+}
+
+// what buyers see about the user selling an offer
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct SellerProfile {
+    pub user_id: String,
+    pub bio: String,
+    pub reputation: i64,
+    // percent of the seller's checked stakes that were honest, none before
+    // any of their stakes was checked
+    pub honesty_index: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct EnrichedOffer {
+    pub offer: Offer,
+    pub seller: Option<SellerProfile>,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum StatsMetric {
+    HandsStarted,
+    BluffsCaught,
+    BluffsGottenAwayWith,
+    SuccessfulChecks,
+    TotalWinnings,
+}
+
+// balance taken out of the contract and held in escrow until the external
+// transfer is reported as completed or failed
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct TransferIntent {
+    pub intent_id: String,
+    pub user_id: String,
+    pub amount: u64,
+    pub status: TransferStatus,
+    pub created_at_block: u64,
+    // a transfer still pending at this height may be reclaimed
+    pub expires_at_block: u64,
+}
+
+// stable codes for the validation errors queries can return, each with a
+// translatable template whose `{0}`, `{1}`... are filled with arguments
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum MessageCode {
+    UserNotRegistered,
+    HandNotFound,
+    HandAlreadyResolved,
+    HandExpired,
+    NoStakesToCheck,
+    NewcomersOnly,
+    NewcomerStakeCap,
+    OwnStakeCheck,
+    ParticipantsOnly,
+    AlreadyResolvedBy,
+    ReputationTooLow,
+    MaintenanceWindow,
+    TournamentPlayersOnly,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    UserRegistered,
+    ProfileUpdated,
+    ReferralPaid,
+    HandStarted,
+    Staked,
+    Checked,
+    TeamCheckConfirmed,
+    HandExpired,
+    HandCanceled,
+    HandJoined,
+    TurnPassed,
+    HandFolded,
+    RematchStarted,
+    CardsBought,
+    CardsRevealed,
+    DailyCardsClaimed,
+    DrawForfeited,
+    CardsBurned,
+    CardCrafted,
+    AchievementUnlocked,
+    AchievementRewardsClaimed,
+    TreasuryWithdrawn,
+    EntropyContributed,
+    OfferCreated,
+    BidPlaced,
+    SealedBidPlaced,
+    BidRevealed,
+    SealedBidForfeited,
+    PackBought,
+    PackGifted,
+    PackOpened,
+    BidWithdrawn,
+    TradeProposed,
+    TradeAccepted,
+    TradeRejected,
+    OfferResolved,
+    OfferBoughtOut,
+    BundlePurchased,
+    AutoBidCreated,
+    AutoBidPlaced,
+    AutoBidCanceled,
+    OfferReserveNotMet,
+    OfferAwaitingConfirmation,
+    PurchaseConfirmationLapsed,
+    OfferBuyerInventoryFull,
+    OfferCountered,
+    CounterAccepted,
+    CounterDeclined,
+    HandMessagePosted,
+    HandPlayerMuted,
+    HandMessageFlagged,
+    ModeratorSet,
+    FriendAdded,
+    FriendRemoved,
+    ChallengeIssued,
+    ChallengeAccepted,
+    ChallengeDeclined,
+    QueueJoined,
+    QueueLeft,
+    PlayersMatched,
+    RecoveryGuardiansSet,
+    RecoveryApproved,
+    RecoveryVetoed,
+    AccountRecovered,
+    TournamentCreated,
+    TournamentJoined,
+    TournamentFinalized,
+    TournamentCancelled,
+    SeasonStarted,
+    SeasonEnded,
+    SeasonReferralPaid,
+    InventoryDecayed,
+    DutchOfferAccepted,
+    OfferBoosted,
+    OfferExpired,
+    Deposited,
+    Withdrawn,
+    TransferCompleted,
+    TransferFailed,
+    TransferReclaimed,
+    ColdStorageEnabled,
+    HighRollerChanged,
+    UnlockRequested,
+    SideBetProposed,
+    SideBetAccepted,
+    SpectatorBetPlaced,
+    SuccessorAnnounced,
+    IdentityLinked,
+    IdentityVerified,
+    IdentityUnlinked,
+    IdentityVisibilityChanged,
+    RegionTagAdded,
+    RegionTagRemoved,
+    MessageTemplateSet,
+    MessageTemplateRemoved,
+    CardsTransferred,
+    CardsGifted,
+    BalanceGifted,
+    OperatorApprovalChanged,
+    Paused,
+    Unpaused,
+    MaintenanceScheduled,
+    MaintenanceCanceled,
+    ParamsUpdated,
+    InstallmentPaid,
+    InstallmentPlanCompleted,
+    InstallmentPlanDefaulted,
+}
+
+// one entry of the append-only event log. `subject_id` is the hand, offer,
+// side bet, draw, platform or tag the event is about, `users` everyone it
+// concerns (always including the actor) and `amount` the chips moved, if any
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Event {
+    pub seq: u64,
+    pub block_height: u64,
+    pub kind: EventKind,
+    pub actor: String,
+    pub subject_id: String,
+    pub users: Vec<String>,
+    pub amount: Option<u64>,
+}
+
+// one entry of a user's history, built from an event they were part of.
+// `net_change` is set on checks to what the check did to the user's balance
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct Activity {
+    pub seq: u64,
+    pub block_height: u64,
+    pub kind: EventKind,
+    pub subject_id: String,
+    pub amount: Option<u64>,
+    pub net_change: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct StartHandArgs {
+    pub claim: Card,
+    pub claimed_count: u32,
+    pub cards: Vec<Card>,
+    pub options: HandOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct StakeArgs {
+    pub hand_id: String,
+    pub cards: Vec<Card>,
+    pub claimed_count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct OfferArgs {
+    pub cards: Vec<Card>,
+    pub amount: u64,
+    pub duration_blocks: u64,
+    pub buyout_price: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct BidArgs {
+    pub offer_id: String,
+    pub amount: u64,
+}
+
+// one call made through `batch`, with the arguments of the entrypoint of the
+// same name; `Check`, `BuyNow` and `Resolve` carry the hand or offer id
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub enum BatchAction {
+    StartHand(StartHandArgs),
+    Stake(StakeArgs),
+    Check(String),
+    Offer(OfferArgs),
+    Bid(BidArgs),
+    BuyNow(String),
+    Resolve(String),
+}
+
+// what the entrypoint behind a batch action returned, `Done` for the ones
+// that return nothing
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub enum BatchResult {
+    Hand(Hand),
+    Checked(CheckOutcome),
+    Offer(Offer),
+    Done,
+}
+
+// the outcome of a call made through `with_idempotency`, kept so a retry
+// with the same `request_id` answers with it instead of running again
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone)]
+pub struct IdempotentCall {
+    pub request_id: String,
+    pub result: BatchResult,
+}
+
+pub fn is_bluff(hand: &Hand) -> bool{
     // SAFETY: when u created a hand, you would have immutably 
     // put atleast one initial stake
-    let last_stake = hand.stakes.last().unwrap();
+    let last_stake = hand.last_stake().unwrap();
 
-    for card in last_stake.cards.iter(){
-        if !Card::equivalent(*card, claim_card){
-            return true;
-        }
-    }
-    false
+    escalate_rules::is_bluff(&last_stake.cards, hand.claimed_card, last_stake.claimed_count)
 }
-
@@ -0,0 +1,22 @@
+// thin indirection over the runtime so the `testing` feature can
+// swap in a mock caller and block height
+use weil_rs::runtime::Runtime;
+
+#[cfg(feature = "testing")]
+use crate::testing::MockEnv;
+
+pub fn sender() -> String {
+    #[cfg(feature = "testing")]
+    if let Some(sender) = MockEnv::sender() {
+        return sender;
+    }
+    Runtime::sender()
+}
+
+pub fn block_height() -> u64 {
+    #[cfg(feature = "testing")]
+    if let Some(height) = MockEnv::block_height() {
+        return height;
+    }
+    Runtime::block_height()
+}
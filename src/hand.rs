@@ -0,0 +1,148 @@
+use crate::elements::{
+    Card, Hand, HandFilter, HandOptions, HandQuery, HandSort, HandStatus, Stake,
+};
+
+impl Hand {
+    // checked, expired, canceled or folded, either way no longer playable
+    pub fn is_resolved(&self) -> bool {
+        self.status != HandStatus::Open
+    }
+
+    pub fn is_expired(&self, height: u64) -> bool {
+        height >= self.expires_at_block
+    }
+
+    // cards staked across every stake so far
+    pub fn staked_card_count(&self) -> u32 {
+        self.stakes.iter().map(|s| s.cards.len() as u32).sum()
+    }
+
+    // numbers the stake after every stake placed before it
+    pub fn add_stake(&mut self, mut stake: Stake) {
+        stake.seq = self.last_stake().map_or(0, |s| s.seq) + 1;
+        self.pot += stake.value();
+        self.stakes.push(stake);
+    }
+
+    // stakes `user_id` has placed, the opening one included
+    pub fn stakes_by(&self, user_id: &str) -> u32 {
+        self.stakes.iter().filter(|s| s.user_id == user_id).count() as u32
+    }
+
+    // the most recent stake, the one a check reveals and a raise must beat
+    pub fn last_stake(&self) -> Option<&Stake> {
+        self.stakes.iter().max_by_key(|s| s.seq)
+    }
+
+    // the table settings the hand was started with
+    pub fn options(&self) -> HandOptions {
+        HandOptions {
+            region_tag: self.region_tag.clone(),
+            duration_blocks: self.duration_blocks,
+            min_raise: self.min_raise,
+            max_stakes: self.max_stakes,
+            alternate_stakers: self.alternate_stakers,
+            max_stakes_per_player: self.max_stakes_per_player,
+            newcomers_only: self.newcomers_only,
+            participants_only: self.participants_only,
+            turn_based: self.is_turn_based(),
+            allowed_stakers: self.allowed_stakers.clone(),
+            min_reputation: self.min_reputation,
+            tournament_id: self.tournament_id.clone(),
+        }
+    }
+
+    // private hands only admit the creator and their invitees
+    pub fn admits(&self, user_id: &str) -> bool {
+        self.allowed_stakers.is_empty()
+            || self.creator == user_id
+            || self.allowed_stakers.iter().any(|u| u == user_id)
+    }
+
+    pub fn is_turn_based(&self) -> bool {
+        !self.turn_order.is_empty()
+    }
+
+    // the player to act, none while the table waits for a second player
+    pub fn turn_player(&self) -> Option<&String> {
+        self.turn_order.get(self.current_turn as usize)
+    }
+
+    // seated players who have not passed yet
+    pub fn active_players(&self) -> usize {
+        self.turn_order.iter().filter(|p| !self.passed.contains(p)).count()
+    }
+
+    // hands the turn to the next seated player who has not passed
+    pub fn advance_turn(&mut self) {
+        let seats = self.turn_order.len();
+        let next = (1..=seats)
+            .map(|step| (self.current_turn as usize + step) % seats)
+            .find(|&idx| !self.passed.contains(&self.turn_order[idx]));
+        if let Some(idx) = next {
+            self.current_turn = idx as u32;
+        }
+    }
+}
+
+impl HandFilter {
+    pub fn matches(&self, hand: &Hand, height: u64) -> bool {
+        if self.unresolved_only && (hand.is_resolved() || hand.is_expired(height)) {
+            return false;
+        }
+        if self.claimed_card.is_some_and(|c| c != hand.claimed_card) {
+            return false;
+        }
+        if self.creator.as_ref().is_some_and(|c| *c != hand.creator) {
+            return false;
+        }
+        self.min_pot.is_none_or(|min| hand.pot >= min)
+    }
+}
+
+impl HandQuery {
+    pub fn matches(&self, hand: &Hand) -> bool {
+        self.status.is_none_or(|s| s == hand.status)
+            && self.creator.as_ref().is_none_or(|c| *c == hand.creator)
+    }
+
+    // orders hands listed oldest first; ties keep the newer hand first
+    pub fn sort(&self, hands: &mut [Hand]) {
+        hands.reverse();
+        match self.sort {
+            HandSort::Newest => {}
+            HandSort::BiggestPot => hands.sort_by_key(|h| std::cmp::Reverse(h.pot)),
+            HandSort::MostStakes => hands.sort_by_key(|h| std::cmp::Reverse(h.stakes.len())),
+        }
+    }
+}
+
+impl Stake {
+    // numbered once it is added to a hand
+    pub fn new(
+        user_id: String,
+        cards: Vec<Card>,
+        claimed_count: u32,
+        height: u64,
+    ) -> Result<Self, String> {
+        if !escalate_rules::is_valid_claim_count(&cards, claimed_count) {
+            return Err(format!(
+                "claimed count must be between 1 and the {} staked cards",
+                cards.len()
+            ));
+        }
+
+        Ok(Stake {
+            user_id,
+            cards,
+            claimed_count,
+            seq: 0,
+            staked_at_block: height,
+        })
+    }
+
+    // reference value of the staked cards, in chips
+    pub fn value(&self) -> u64 {
+        self.cards.iter().map(|c| c.reference_value()).sum()
+    }
+}
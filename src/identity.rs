@@ -0,0 +1,40 @@
+use crate::elements::{ExternalIdentity, IdentityProfile};
+
+impl IdentityProfile {
+    pub fn new() -> Self {
+        IdentityProfile {
+            is_public: true,
+            identities: Vec::new(),
+        }
+    }
+
+    // relinking a platform replaces the old handle and needs fresh verification
+    pub fn link(&mut self, identity: ExternalIdentity) {
+        self.identities.retain(|i| i.platform != identity.platform);
+        self.identities.push(identity);
+    }
+
+    pub fn unlink(&mut self, platform: &str) -> bool {
+        let before = self.identities.len();
+        self.identities.retain(|i| i.platform != platform);
+        self.identities.len() != before
+    }
+
+    pub fn verify(&mut self, platform: &str) -> Option<ExternalIdentity> {
+        let identity = self.identities.iter_mut().find(|i| i.platform == platform)?;
+        identity.is_verified = true;
+        Some(identity.clone())
+    }
+
+    // what other users get to see on the profile
+    pub fn public_view(&self) -> Vec<ExternalIdentity> {
+        if !self.is_public {
+            return Vec::new();
+        }
+        self.identities
+            .iter()
+            .filter(|i| i.is_verified)
+            .cloned()
+            .collect()
+    }
+}
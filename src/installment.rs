@@ -0,0 +1,62 @@
+use crate::elements::{Card, InstallmentPlan, InstallmentTerms};
+
+impl InstallmentTerms {
+    // every installment but the last, which also covers the rounding remainder
+    pub fn installment_for(&self, total: u64) -> u64 {
+        total / self.count as u64
+    }
+}
+
+impl InstallmentPlan {
+    // opens the plan at resolve time, the first installment was escrowed with the bid
+    pub fn new(
+        offer_id: String,
+        buyer_id: String,
+        seller_id: String,
+        cards: Vec<Card>,
+        total: u64,
+        terms: InstallmentTerms,
+        resolved_at: u64,
+    ) -> Self {
+        InstallmentPlan {
+            offer_id,
+            buyer_id,
+            seller_id,
+            cards,
+            total,
+            paid: terms.installment_for(total),
+            installments_paid: 1,
+            next_due_block: resolved_at.saturating_add(terms.interval_blocks),
+            terms,
+            is_settled: false,
+        }
+    }
+
+    // the next installment pays the plan off and delivers the cards
+    pub fn is_last_installment_due(&self) -> bool {
+        self.installments_paid + 1 >= self.terms.count
+    }
+
+    pub fn next_installment(&self) -> u64 {
+        if self.is_last_installment_due() {
+            self.total - self.paid
+        } else {
+            self.terms.installment_for(self.total)
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.installments_paid >= self.terms.count
+    }
+
+    pub fn is_overdue(&self, height: u64) -> bool {
+        !self.is_complete() && height > self.next_due_block
+    }
+
+    // due blocks stay on the original schedule, paying early does not shift them
+    pub fn record_payment(&mut self, amount: u64) {
+        self.paid += amount;
+        self.installments_paid += 1;
+        self.next_due_block = self.next_due_block.saturating_add(self.terms.interval_blocks);
+    }
+}
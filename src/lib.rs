@@ -3,34 +3,456 @@ use serde::{Deserialize, Serialize};
 use weil_macros::{constructor, mutate, query, smart_contract, WeilType};
 use weil_rs::collections::WeilIdGenerator;
 use weil_rs::collections::{WeilId, map::WeilMap, vec::WeilVec};
-use weil_rs::runtime::Runtime;
 use weil_rs::webserver::WebServer;
 
+use escalate_rules::CHIPS_PER_UNIT;
+
 mod elements;
-use elements::{Card, Hand, Offer, Stake, User, get_random_cards, is_bluff};
+mod env;
+use elements::{
+    Achievement, AchievementProgress, Activity, ActivityPage, AutoBidRule, BalanceBreakdown,
+    BatchAction, BatchResult, Card, CardPriceStats, CardSupply, CardValue, Challenge,
+    ChallengeStatus, CheckOutcome, CheckPreview, CheckResolution, ClaimBonus, ClaimTotals,
+    CraftingRate, DropRate, DutchTerms, EnrichedOffer, EnrichedOfferPage, EntrypointSchema,
+    EscrowKind, EscrowedCards, Event, EventKind, ExternalIdentity, GameParams, Hand, HandAction,
+    HandActionKind, HandFilter, HandMessage, HandMessagePage, HandOptions, HandPage, HandQuery,
+    HandReplay, HandStatus, HandUpdate, Health, IdempotentCall, IdentityProfile, InstallmentPlan,
+    InstallmentTerms, MaintenanceWindow, MessageCode, NegotiationState, NotableHand, Offer,
+    OfferPage, Pack, PackKind, PageResult, PayoutLine, PayoutReason, PendingDraw, PoolWager,
+    QueueEntry, RecoveryGuardians, RecoveryRequest, RecoveryStatus, ReferralEarning,
+    ReferralStanding, ResolutionStats, ResolutionTotals, SalePrice, SealedBid, Season,
+    SeasonArchive, SeasonConfig, SeasonPrize, SellerProfile, SideBet, SpectatorPool, Stake,
+    Standing, StatsMetric, Succession, TeamHand, TeamOptions, Tournament, TournamentConfig,
+    TournamentStatus, Trade, TradeStatus, TransferIntent, TransferStatus, UnlockedAchievement, User,
+    UserDataExport, UserPage, UserStats, is_bluff,
+};
 
 mod user;
 mod offer;
+mod pack;
+mod crafting;
+mod achievements;
+mod sealed_bid;
+mod hand;
+mod team;
+mod side_bet;
+mod trade;
+mod social;
+mod matchmaking;
+mod auto_bid;
+mod tournament;
+mod season;
+mod installment;
+mod transfer;
+mod identity;
+mod recovery;
+mod messages;
+mod randomness;
+mod deck;
+mod payouts;
+mod params;
+mod stats;
+mod token;
+mod market;
+mod ticker;
+mod describe;
+mod utils;
+use messages::Message;
+use offer::OfferTerms;
+use randomness::EntropyPool;
+use deck::Deck;
+use ticker::RecentEvents;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
 trait Escalate {
     fn new() -> Result<Self, String>
     where
         Self: Sized;
     async fn register_user(&mut self, bio: String) -> Result<User, String>;
+    async fn register_user_with_referrer(
+        &mut self,
+        bio: String,
+        referrer_id: String,
+    ) -> Result<User, String>;
+    async fn get_referral_earnings(&self) -> Vec<ReferralEarning>;
+    async fn get_referral_standings(&self) -> Vec<ReferralStanding>;
     async fn get_users(&self) -> Vec<User>;
+    async fn get_users_page(&self, offset: u64, limit: u64) -> UserPage;
     async fn get_user(&self, id: String) -> Option<User>;
-    async fn get_my_cards(&self) -> Result<Vec<Card>, String>;
-    async fn start_hand(&mut self, claim: Card, cards: Vec<Card>) -> Result<Hand, String>;
+    async fn get_my_cards(&self, locale: Option<String>) -> Result<Vec<Card>, String>;
+    async fn get_my_escrow(&self) -> Vec<EscrowedCards>;
+    async fn get_my_debt(&self, locale: Option<String>) -> Result<u64, String>;
+    async fn get_balance_breakdown(
+        &self,
+        locale: Option<String>,
+    ) -> Result<BalanceBreakdown, String>;
+    async fn start_hand(
+        &mut self,
+        claim: Card,
+        claimed_count: u32,
+        cards: Vec<Card>,
+        options: HandOptions,
+    ) -> Result<Hand, String>;
+    async fn start_team_hand(
+        &mut self,
+        claim: Card,
+        claimed_count: u32,
+        cards: Vec<Card>,
+        options: HandOptions,
+        teams: TeamOptions,
+    ) -> Result<Hand, String>;
+    async fn get_team_hand(&self, hand_id: String) -> Option<TeamHand>;
+    async fn rematch(
+        &mut self,
+        hand_id: String,
+        claim: Card,
+        claimed_count: u32,
+        cards: Vec<Card>,
+    ) -> Result<Hand, String>;
     async fn get_hands(&self) -> Vec<Hand>;
+    async fn get_hands_page(&self, offset: u64, limit: u64) -> HandPage;
+    async fn get_hands_by_region(&self, region_tag: String) -> Vec<Hand>;
+    async fn get_open_hands(&self, filter: HandFilter) -> HandPage;
+    async fn search_hands(&self, query: HandQuery) -> HandPage;
     async fn get_hand(&self, id: String) -> Option<Hand>;
-    async fn buy_cards(&mut self, amount: f64) -> Result<Vec<Card>, String>;
-    async fn stake(&mut self, hand_id: String, cards: Vec<Card>) -> Result<Hand, String>;
-    async fn check(&mut self, hand_id: String) -> Result<bool, String>;
-    async fn offer(&mut self, cards: Vec<Card>, amount: f64) -> Result<Offer, String>;
+    async fn wait_changed(&self, hand_id: String, known_seq: u64) -> Result<HandUpdate, String>;
+    async fn get_hands_by_participant(
+        &self,
+        user_id: String,
+        status_filter: Option<HandStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> HandPage;
+    async fn buy_cards(&mut self, amount: u64, commitment: String) -> Result<PendingDraw, String>;
+    async fn reveal_cards(&mut self, draw_id: String, salt: String) -> Result<Vec<Card>, String>;
+    async fn forfeit_expired_draws(&mut self, user_id: String) -> Result<u32, String>;
+    async fn claim_daily_cards(&mut self, commitment: String) -> Result<PendingDraw, String>;
+    async fn get_my_pending_draws(&self) -> Vec<PendingDraw>;
+    async fn get_deck(&self) -> Deck;
+    async fn get_drop_rates(&self) -> Vec<DropRate>;
+    async fn burn_cards(&mut self, cards: Vec<Card>) -> Result<u64, String>;
+    async fn decay_inventory(&mut self, user_id: String) -> Result<Vec<Card>, String>;
+    async fn craft_card(&mut self, target: Card, points: u64) -> Result<User, String>;
+    async fn get_crafting_rates(&self) -> Vec<CraftingRate>;
+    async fn contribute_entropy(&mut self, entropy: String) -> Result<(), String>;
+    async fn buy_sealed_pack(&mut self, kind: PackKind) -> Result<Pack, String>;
+    async fn gift_pack(&mut self, pack_id: String, recipient: String) -> Result<Pack, String>;
+    async fn offer_pack(
+        &mut self,
+        pack_id: String,
+        amount: u64,
+        duration_blocks: u64,
+    ) -> Result<Offer, String>;
+    async fn open_pack(
+        &mut self,
+        pack_id: String,
+        commitment: String,
+    ) -> Result<PendingDraw, String>;
+    async fn get_my_packs(&self) -> Vec<Pack>;
+    async fn get_pack(&self, pack_id: String) -> Option<Pack>;
+    async fn stake(
+        &mut self,
+        hand_id: String,
+        cards: Vec<Card>,
+        claimed_count: u32,
+    ) -> Result<Hand, String>;
+    async fn join_hand(&mut self, hand_id: String) -> Result<Hand, String>;
+    async fn pass_turn(&mut self, hand_id: String) -> Result<Hand, String>;
+    async fn get_hand_replay(&self, hand_id: String) -> Result<HandReplay, String>;
+    async fn check(&mut self, hand_id: String) -> Result<CheckOutcome, String>;
+    async fn confirm_team_check(
+        &mut self,
+        hand_id: String,
+    ) -> Result<Option<CheckOutcome>, String>;
+    async fn claim_expired_hand(&mut self, hand_id: String) -> Result<Hand, String>;
+    async fn cancel_hand(&mut self, hand_id: String) -> Result<Hand, String>;
+    async fn preview_check(
+        &self,
+        hand_id: String,
+        locale: Option<String>,
+    ) -> Result<CheckPreview, String>;
+    async fn get_resolution_stats(&self, window: u64) -> ResolutionStats;
+    async fn get_claim_bonuses(&self) -> Vec<ClaimBonus>;
+    async fn get_user_stats(&self, user_id: String) -> Option<UserStats>;
+    async fn get_reputation(&self, user_id: String) -> Option<i64>;
+    async fn get_leaderboard(&self, metric: StatsMetric, limit: u64) -> Vec<UserStats>;
+    async fn get_my_achievements(&self) -> Vec<AchievementProgress>;
+    async fn get_user_badges(&self, user_id: String) -> Vec<UnlockedAchievement>;
+    async fn claim_achievement_rewards(&mut self) -> Result<User, String>;
+    async fn get_total_supply(&self) -> u64;
+    async fn get_card_supply(&self) -> Vec<CardSupply>;
+    async fn get_card_price_stats(&self, card: Card) -> CardPriceStats;
+    async fn get_treasury_balance(&self) -> u64;
+    async fn withdraw_treasury(&mut self, to: String, amount: u64) -> Result<u64, String>;
+    async fn offer(
+        &mut self,
+        cards: Vec<Card>,
+        amount: u64,
+        duration_blocks: u64,
+        installments: Option<InstallmentTerms>,
+        buyout_price: Option<u64>,
+        card_prices: Option<Vec<u64>>,
+    ) -> Result<Offer, String>;
+    async fn offer_sealed(
+        &mut self,
+        cards: Vec<Card>,
+        reserve: u64,
+        reveal_deadline: u64,
+    ) -> Result<Offer, String>;
+    async fn bid_sealed(
+        &mut self,
+        offer_id: String,
+        commitment: String,
+    ) -> Result<SealedBid, String>;
+    async fn reveal_bid(
+        &mut self,
+        offer_id: String,
+        amount: u64,
+        salt: String,
+    ) -> Result<SealedBid, String>;
+    async fn get_sealed_bids(&self, offer_id: String) -> Vec<SealedBid>;
+    async fn reclaim_expired_offer(&mut self, offer_id: String) -> Result<Offer, String>;
     async fn get_offers(&self) -> Vec<Offer>;
-    async fn bid(&mut self, offer_id: String, bid_amout: f64) -> Result<(), String>;
+    async fn get_offers_page(&self, offset: u64, limit: u64) -> OfferPage;
+    async fn get_offers_enriched(&self, offset: u64, limit: u64) -> EnrichedOfferPage;
+    async fn get_offer_card_values(&self, offer_id: String) -> Result<Vec<CardValue>, String>;
+    async fn bid(&mut self, offer_id: String, bid_amout: u64) -> Result<(), String>;
+    async fn set_offer_bid_rules(
+        &mut self,
+        offer_id: String,
+        min_increment: u64,
+        reserve_price: Option<u64>,
+    ) -> Result<Offer, String>;
+    async fn require_bidder_confirmation(&mut self, offer_id: String) -> Result<Offer, String>;
+    async fn confirm_purchase(&mut self, offer_id: String) -> Result<(), String>;
+    async fn counter(&mut self, offer_id: String, price: u64) -> Result<Offer, String>;
+    async fn accept_counter(&mut self, offer_id: String) -> Result<Offer, String>;
+    async fn decline_counter(&mut self, offer_id: String) -> Result<Offer, String>;
+    async fn buy_now(&mut self, offer_id: String) -> Result<Offer, String>;
+    async fn offer_dutch(
+        &mut self,
+        cards: Vec<Card>,
+        start_price: u64,
+        floor_price: u64,
+        decay_per_block: u64,
+    ) -> Result<Offer, String>;
+    async fn get_offer_price(&self, offer_id: String) -> Result<u64, String>;
+    async fn accept_dutch(&mut self, offer_id: String) -> Result<Offer, String>;
+    async fn offer_bundle(
+        &mut self,
+        cards: Vec<Card>,
+        price_per_card: u64,
+        duration_blocks: u64,
+    ) -> Result<Offer, String>;
+    async fn buy_from_bundle(
+        &mut self,
+        offer_id: String,
+        cards: Vec<Card>,
+    ) -> Result<Offer, String>;
+    async fn boost_offer(&mut self, offer_id: String, blocks: u64) -> Result<Offer, String>;
     async fn resolve(&mut self, offer_id: String) -> Result<(), String>;
     async fn withdraw_bid(&mut self, offer_id: String) -> Result<(), String>;
-    async fn deposit(&mut self, amount: f64) -> Result<(), String>;
+    async fn pay_installment(&mut self, offer_id: String) -> Result<InstallmentPlan, String>;
+    async fn forfeit_installment_plan(
+        &mut self,
+        offer_id: String,
+    ) -> Result<InstallmentPlan, String>;
+    async fn get_installment_plan(&self, offer_id: String) -> Option<InstallmentPlan>;
+    async fn propose_trade(
+        &mut self,
+        counterparty: String,
+        offered_cards: Vec<Card>,
+        requested_cards: Vec<Card>,
+    ) -> Result<Trade, String>;
+    async fn accept_trade(&mut self, trade_id: String) -> Result<Trade, String>;
+    async fn reject_trade(&mut self, trade_id: String) -> Result<Trade, String>;
+    async fn get_my_trades(&self) -> Vec<Trade>;
+    async fn create_auto_bid(
+        &mut self,
+        card: Card,
+        max_bid: u64,
+        budget: u64,
+    ) -> Result<AutoBidRule, String>;
+    async fn cancel_auto_bid(&mut self, rule_id: String) -> Result<AutoBidRule, String>;
+    async fn get_my_auto_bids(&self) -> Vec<AutoBidRule>;
+    async fn create_tournament(&mut self, config: TournamentConfig) -> Result<Tournament, String>;
+    async fn join_tournament(&mut self, tournament_id: String) -> Result<Tournament, String>;
+    async fn finalize_tournament(&mut self, tournament_id: String) -> Result<Tournament, String>;
+    async fn cancel_tournament(&mut self, tournament_id: String) -> Result<Tournament, String>;
+    async fn get_tournament(&self, tournament_id: String) -> Option<Tournament>;
+    async fn get_tournament_standings(
+        &self,
+        tournament_id: String,
+    ) -> Result<Vec<Standing>, String>;
+    async fn start_season(&mut self, config: SeasonConfig) -> Result<Season, String>;
+    async fn end_season(&mut self) -> Result<Season, String>;
+    async fn get_current_season(&self) -> Option<Season>;
+    async fn get_seasons(&self) -> Vec<Season>;
+    async fn get_season_standings(
+        &self,
+        season_id: String,
+        limit: u64,
+    ) -> Result<Vec<UserStats>, String>;
+    async fn get_season_archive(&self, season_id: String) -> Option<SeasonArchive>;
+    async fn add_friend(&mut self, friend_id: String) -> Result<Vec<String>, String>;
+    async fn remove_friend(&mut self, friend_id: String) -> Result<Vec<String>, String>;
+    async fn get_friends(&self) -> Vec<String>;
+    async fn challenge(
+        &mut self,
+        friend_id: String,
+        claim: Card,
+        cards: Vec<Card>,
+    ) -> Result<Challenge, String>;
+    async fn accept_challenge(&mut self, challenge_id: String) -> Result<Hand, String>;
+    async fn decline_challenge(&mut self, challenge_id: String) -> Result<Challenge, String>;
+    async fn get_my_challenges(&self) -> Vec<Challenge>;
+    async fn join_queue(&mut self, stake_size: u32) -> Result<Option<Hand>, String>;
+    async fn leave_queue(&mut self) -> Result<(), String>;
+    async fn match_players(&mut self) -> Result<Vec<Hand>, String>;
+    async fn get_match_queue(&self) -> Vec<QueueEntry>;
+    async fn deposit(&mut self, amount: u64) -> Result<(), String>;
+    async fn withdraw(&mut self, amount: u64) -> Result<TransferIntent, String>;
+    async fn settle_transfer(
+        &mut self,
+        intent_id: String,
+        succeeded: bool,
+    ) -> Result<TransferIntent, String>;
+    async fn reclaim_failed_transfer(
+        &mut self,
+        intent_id: String,
+    ) -> Result<TransferIntent, String>;
+    async fn get_my_transfers(&self) -> Vec<TransferIntent>;
+    async fn get_transfer(&self, intent_id: String) -> Option<TransferIntent>;
+    async fn enable_cold_storage(&mut self) -> Result<User, String>;
+    async fn request_unlock(&mut self) -> Result<User, String>;
+    async fn set_high_roller(&mut self, enabled: bool) -> Result<User, String>;
+    async fn set_recovery_guardians(
+        &mut self,
+        guardians: Vec<String>,
+        threshold: u32,
+    ) -> Result<RecoveryGuardians, String>;
+    async fn recover_account(
+        &mut self,
+        old_id: String,
+        new_id: String,
+    ) -> Result<RecoveryRequest, String>;
+    async fn veto_recovery(&mut self) -> Result<RecoveryRequest, String>;
+    async fn complete_recovery(&mut self, old_id: String) -> Result<User, String>;
+    async fn get_recovery_guardians(&self, user_id: String) -> Option<RecoveryGuardians>;
+    async fn get_recovery(&self, user_id: String) -> Option<RecoveryRequest>;
+    async fn propose_side_bet(
+        &mut self,
+        opponent: String,
+        hand_id: String,
+        predicts_bluff: bool,
+        amount: u64,
+    ) -> Result<SideBet, String>;
+    async fn accept_side_bet(&mut self, bet_id: String) -> Result<SideBet, String>;
+    async fn get_side_bets(&self, hand_id: String) -> Vec<SideBet>;
+    async fn place_side_bet(
+        &mut self,
+        hand_id: String,
+        predicts_bluff: bool,
+        amount: u64,
+    ) -> Result<PoolWager, String>;
+    async fn get_spectator_pool(&self, hand_id: String) -> Option<SpectatorPool>;
+    async fn post_hand_message(
+        &mut self,
+        hand_id: String,
+        text: String,
+    ) -> Result<HandMessage, String>;
+    async fn get_hand_messages(
+        &self,
+        hand_id: String,
+        offset: u64,
+        limit: u64,
+    ) -> Result<HandMessagePage, String>;
+    async fn mute_in_hand(&mut self, hand_id: String, user_id: String) -> Result<(), String>;
+    async fn flag_hand_message(
+        &mut self,
+        message_id: u64,
+        flagged: bool,
+    ) -> Result<HandMessage, String>;
+    async fn set_moderator(&mut self, user_id: String, is_moderator: bool) -> Result<(), String>;
+    async fn get_moderators(&self) -> Vec<String>;
+    async fn set_successor(
+        &mut self,
+        successor: String,
+        freeze_creation: bool,
+    ) -> Result<Succession, String>;
+    async fn get_successor(&self) -> Option<Succession>;
+    async fn pause(&mut self) -> Result<(), String>;
+    async fn unpause(&mut self) -> Result<(), String>;
+    async fn is_paused(&self) -> bool;
+    async fn schedule_maintenance(
+        &mut self,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<MaintenanceWindow, String>;
+    async fn cancel_maintenance(&mut self) -> Result<(), String>;
+    async fn health(&self) -> Health;
+    async fn describe(&self) -> Vec<EntrypointSchema>;
+    async fn set_params(&mut self, params: GameParams) -> Result<GameParams, String>;
+    async fn get_params(&self) -> GameParams;
+    async fn link_external_identity(
+        &mut self,
+        platform: String,
+        handle: String,
+        proof: String,
+    ) -> Result<ExternalIdentity, String>;
+    async fn verify_external_identity(
+        &mut self,
+        user_id: String,
+        platform: String,
+    ) -> Result<ExternalIdentity, String>;
+    async fn unlink_external_identity(&mut self, platform: String) -> Result<(), String>;
+    async fn set_identities_public(&mut self, is_public: bool) -> Result<(), String>;
+    async fn get_external_identities(&self, user_id: String) -> Vec<ExternalIdentity>;
+    async fn add_region_tag(&mut self, region_tag: String) -> Result<(), String>;
+    async fn remove_region_tag(&mut self, region_tag: String) -> Result<(), String>;
+    async fn get_region_tags(&self) -> Vec<String>;
+    async fn set_message_template(
+        &mut self,
+        code: MessageCode,
+        locale: String,
+        template: String,
+    ) -> Result<(), String>;
+    async fn remove_message_template(
+        &mut self,
+        code: MessageCode,
+        locale: String,
+    ) -> Result<(), String>;
+    async fn get_message_template(&self, code: MessageCode, locale: Option<String>) -> String;
+    async fn get_events_since(&self, seq: u64) -> Vec<Event>;
+    async fn get_recent_activity(&self) -> Vec<Event>;
+    async fn get_user_events_since(&self, user_id: String, seq: u64) -> Vec<Event>;
+    async fn export_my_data(&self) -> Result<UserDataExport, String>;
+    async fn get_my_activity(&self, offset: u64, limit: u64) -> ActivityPage;
+    async fn balance_of(&self, owner: String, card: Card) -> u64;
+    async fn card_total_supply(&self, card: Card) -> u64;
+    async fn set_approval_for_all(
+        &mut self,
+        operator: String,
+        approved: bool,
+    ) -> Result<(), String>;
+    async fn is_approved_for_all(&self, owner: String, operator: String) -> bool;
+    async fn gift_cards(&mut self, to_user_id: String, cards: Vec<Card>) -> Result<(), String>;
+    async fn gift_balance(&mut self, to_user_id: String, amount: u64) -> Result<(), String>;
+    async fn transfer_from(
+        &mut self,
+        from: String,
+        to: String,
+        card: Card,
+        amount: u64,
+    ) -> Result<(), String>;
+    async fn with_idempotency(
+        &mut self,
+        request_id: String,
+        action: BatchAction,
+    ) -> Result<BatchResult, String>;
+    async fn batch(&mut self, actions: Vec<BatchAction>) -> Result<Vec<BatchResult>, String>;
 
     // webserver specific functions
     fn start_file_upload(&mut self, path: String, total_chunks: u32) -> Result<(), String>;
@@ -52,8 +474,78 @@ trait Escalate {
     fn get_chunk_size(&self) -> u32;
 }
 
-const EQUIVALENT_REWARD: f64 = 1.0;
-const BLUFF_REWARD: f64 = 1.2;
+// list queries without explicit paging return at most this many items
+const DEFAULT_PAGE_LIMIT: u64 = 50;
+const MAX_PAGE_LIMIT: u64 = 200;
+const MAX_BATCH_ACTIONS: usize = 20;
+// successful idempotent calls remembered per user, the oldest is forgotten
+// first, and the longest request id accepted
+const MAX_IDEMPOTENT_CALLS: usize = 100;
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+// accounts younger than this play in the newcomer bracket, where
+// stakes are capped and part of a lost check is paid by the treasury
+const NEWCOMER_AGE_BLOCKS: u64 = 50_000;
+const NEWCOMER_MAX_STAKE_CARDS: usize = 3;
+const NEWCOMER_SUBSIDY_PERCENT: u64 = 50;
+// delay between requesting a cold storage unlock and being able to play again
+const COLD_STORAGE_UNLOCK_BLOCKS: u64 = 1_000;
+// only offers priced at least this high may be settled in installments
+const MIN_INSTALLMENT_PRICE: u64 = 100 * CHIPS_PER_UNIT;
+const MAX_INSTALLMENTS: u32 = 12;
+// reputation changes: every honest stake of a checked hand, a check that
+// catches a bluff, the caught bluff itself and an offer resolved unsold
+const REPUTATION_HONEST_STAKE: i64 = 1;
+const REPUTATION_SUCCESSFUL_CHECK: i64 = 2;
+const REPUTATION_CAUGHT_BLUFF: i64 = -3;
+const REPUTATION_ABANDONED_OFFER: i64 = -1;
+// sealed-bid offers take commitments until this many blocks before their
+// reveal deadline, then only reveals, and can be resolved for a while after
+const SEALED_REVEAL_BLOCKS: u64 = 100;
+const SEALED_SETTLE_BLOCKS: u64 = 1_000;
+// a sealed bid locks the reserve, and at least this much, until it is
+// revealed; bids still hidden after the reveal window lose it
+const SEALED_BID_MIN_DEPOSIT: u64 = CHIPS_PER_UNIT;
+// dutch auctions stay open this long after their price reaches the floor
+const DUTCH_FLOOR_BLOCKS: u64 = 1_000;
+// how long the winner of an offer requiring confirmation has to confirm it
+const CONFIRM_PURCHASE_BLOCKS: u64 = 500;
+// standard decks shuffled together into the pool all cards are dealt from
+const STANDARD_DECKS: u64 = 1_000;
+// blocks a committed draw has to be revealed in before it can be forfeited
+const DRAW_REVEAL_BLOCKS: u64 = 1_000;
+// events kept for the live activity ticker
+const RECENT_EVENTS: usize = 32;
+// event seqs stored per page of a user's history; recording an event
+// rewrites only the last page
+const USER_EVENT_PAGE_SIZE: u64 = 100;
+// table talk: characters per message, messages per player and hand, and
+// the blocks a player waits between two messages at the same table
+const MAX_HAND_MESSAGE_LENGTH: usize = 280;
+const MAX_HAND_MESSAGES_PER_USER: usize = 50;
+const HAND_MESSAGE_COOLDOWN_BLOCKS: u64 = 3;
+const MAX_FRIENDS: usize = 200;
+// a challenge waits this long for the friend to answer, and the hand it
+// opens stays open this long
+const CHALLENGE_EXPIRY_BLOCKS: u64 = 500;
+const CHALLENGE_HAND_BLOCKS: u64 = 1_000;
+// hands opened for players matched through the queue stay open this long
+const MATCH_HAND_BLOCKS: u64 = 1_000;
+// guardians an account can name, and how long the original key has to veto
+// a recovery once enough of them approved it
+const MAX_RECOVERY_GUARDIANS: usize = 10;
+const RECOVERY_CHALLENGE_BLOCKS: u64 = 5_000;
+// paid to the treasury for every block an offer is listed ahead of the rest
+const BOOST_FEE_PER_BLOCK: u64 = CHIPS_PER_UNIT / 10;
+// withdrawals nobody reported on within this many blocks can be reclaimed
+const TRANSFER_TIMEOUT_BLOCKS: u64 = 500;
+// percent of a tournament's prize pool paid to first, second and third place
+const TOURNAMENT_PRIZE_SPLIT: [u64; 3] = [50, 30, 20];
+// hands started with a rank claimed less than its fair share over the last
+// window of started hands earn their creator up to this much of the pot
+// once checked; smaller windows pay no bonus
+const CLAIM_DIVERSITY_WINDOW: u64 = 500;
+const CLAIM_DIVERSITY_MIN_HANDS: u64 = 50;
+const MAX_CLAIM_DIVERSITY_BONUS_PERCENT: u64 = 10;
 
 impl EscalateContractState {
     fn remove_cards_from_inventory(
@@ -70,503 +562,8727 @@ impl EscalateContractState {
         Ok(())
     }
 
-    fn reward_stakers(&mut self, stakes: &[Stake], include_last: bool, claimed: Card) {
-        if stakes.is_empty() {
+    // passes the referrer their cut of a fee `referee_id` paid the treasury
+    // while the referral runs; callers write the referee and any
+    // counterparty back first, since the referrer may be one of them
+    fn pay_referral(&mut self, referee_id: &String, fee: u64) {
+        let Some(referrer_id) = self.users.get(referee_id).and_then(|u| u.referrer) else {
+            return;
+        };
+        if self.users.get(&referrer_id).is_none() {
             return;
         }
-
-        let upto = if include_last {
-            stakes.len()
-        } else {
-            stakes.len().saturating_sub(1)
+        let mut referees = self.referral_earnings.get(&referrer_id).unwrap_or_default();
+        let Some(referral) = referees.iter_mut().find(|r| r.referee_id == *referee_id) else {
+            return;
         };
+        if env::block_height() >= referral.ends_at_block {
+            return;
+        }
+        let cut = (fee * self.params.referral_percent / 100).min(self.treasury);
+        if cut == 0 {
+            return;
+        }
 
-        for stake in stakes.iter().take(upto) {
-            if let Some(mut staker) = self.users.get(&stake.user_id) {
-                for card in &stake.cards {
-                    let reward = if Card::equivalent(*card, claimed) {
-                        EQUIVALENT_REWARD
-                    } else {
-                        BLUFF_REWARD
-                    };
-                    staker.balance += reward;
-                }
-                self.users.insert(stake.user_id.clone(), staker);
+        referral.earned += cut;
+        self.referral_earnings.insert(referrer_id.clone(), referees);
+        self.treasury -= cut;
+        self.apply_payouts(vec![(referrer_id.clone(), cut as i64)]);
+        self.emit(EventKind::ReferralPaid, referee_id, &[&referrer_id], Some(cut));
+    }
+
+    // penalties and debt repayments are owed to the treasury, so whatever
+    // part of them is actually collected moves there
+    fn apply_payouts(&mut self, payouts: Vec<(String, i64)>) {
+        for (user_id, amount) in payouts {
+            if let Some(mut user) = self.users.get(&user_id) {
+                let mut debt = self.debts.get(&user_id).unwrap_or(0);
+                let (balance_before, debt_before) = (user.balance, debt);
+                user.apply_delta(amount, &mut debt, self.params.balance_floor);
+                self.treasury += if amount < 0 {
+                    balance_before - user.balance
+                } else {
+                    debt_before - debt
+                };
+                self.debts.insert(user_id.clone(), debt);
+                self.users.insert(user_id, user);
             }
         }
     }
 
-    fn next_hand_id(&mut self) -> String {
-        self.hand_counter += 1;
-        self.hand_counter.to_string()
-    }
+    fn checkable_hand(&self, hand_id: &String) -> Result<Hand, Message> {
+        let hand = self
+            .hands
+            .get(hand_id)
+            .ok_or_else(|| Message::new(MessageCode::HandNotFound))?;
+        // calls are applied one at a time in submission order, so of two
+        // checks racing in the same block the first wins and the second is
+        // told who beat it and what they found
+        if let Some(resolution) = &hand.resolution {
+            return Err(Message::new(MessageCode::AlreadyResolvedBy)
+                .with_arg(&resolution.checker_id)
+                .with_arg(resolution.bluff_detected));
+        }
+        if hand.is_resolved() {
+            return Err(Message::new(MessageCode::HandAlreadyResolved));
+        }
 
-    fn next_offer_id(&mut self) -> String {
-        self.offer_counter += 1;
-        self.offer_counter.to_string()
-    }
+        if hand.is_expired(env::block_height()) {
+            return Err(Message::new(MessageCode::HandExpired));
+        }
 
-    fn mask_hand_for_view(hand: &Hand) -> Hand {
-        let mut masked = hand.clone();
-        masked.stakes = hand
-            .stakes
-            .iter()
-            .map(|s| Stake {
-                user_id: s.user_id.clone(),
-                // preserve count and order, hide card identities
-                cards: vec![Card::JOKER; s.cards.len()],
-            })
-            .collect();
-        masked
-    }
-}
+        if hand.stakes.is_empty() {
+            return Err(Message::new(MessageCode::NoStakesToCheck));
+        }
 
-#[derive(Serialize, Deserialize, WeilType)]
-pub struct EscalateContractState {
-    users: WeilMap<String, User>,
-    user_ids: WeilVec<String>,
-    hands: WeilMap<String, Hand>,
-    hand_ids: WeilVec<String>,
-    offers: WeilMap<String, Offer>,
-    offer_ids: WeilVec<String>,
-    hand_counter: u64,
-    offer_counter: u64,
-    server: WebServer,
-    weil_id_generator: WeilIdGenerator
-}
+        Ok(hand)
+    }
 
-#[smart_contract]
-impl Escalate for EscalateContractState {
-    #[constructor]
-    fn new() -> Result<Self, String>
-    where
-        Self: Sized,
-    {
-        Ok(
-            Self {
-                users: WeilMap::new(WeilId(1)),
-                user_ids: WeilVec::new(WeilId(2)),
-                hands: WeilMap::new(WeilId(3)),
-                hand_ids: WeilVec::new(WeilId(4)),
-                offers: WeilMap::new(WeilId(5)),
-                offer_ids: WeilVec::new(WeilId(6)),
-                hand_counter: 0,
-                offer_counter: 0,
-                server: WebServer::new(WeilId(7), None),
-                weil_id_generator: WeilIdGenerator::new(WeilId(8))
-            }
-        )
+    // newcomer bracket hands only admit newcomers, with capped stakes
+    fn ensure_bracket_entry(
+        &self,
+        hand: &Hand,
+        user: &User,
+        cards: &[Card],
+    ) -> Result<(), Message> {
+        if let Some(min) = hand.min_reputation
+            && hand.creator != user.user_id
+            && user.reputation < min
+        {
+            return Err(Message::new(MessageCode::ReputationTooLow).with_arg(min));
+        }
+        if let Some(id) = &hand.tournament_id
+            && !self.tournaments.get(id).is_some_and(|t| t.has_player(&user.user_id))
+        {
+            return Err(Message::new(MessageCode::TournamentPlayersOnly).with_arg(id));
+        }
+        if !hand.newcomers_only {
+            return Ok(());
+        }
+        if !user.is_newcomer(env::block_height(), NEWCOMER_AGE_BLOCKS) {
+            return Err(Message::new(MessageCode::NewcomersOnly));
+        }
+        if cards.len() > NEWCOMER_MAX_STAKE_CARDS {
+            let message = Message::new(MessageCode::NewcomerStakeCap);
+            return Err(message.with_arg(NEWCOMER_MAX_STAKE_CARDS));
+        }
+        Ok(())
     }
 
+    // the catalog template for `code`, falling back from a regional locale to
+    // its language, then to the default locale and the built in default
+    fn message_template(&self, code: MessageCode, locale: Option<&str>) -> String {
+        messages::locale_fallbacks(locale.unwrap_or(messages::DEFAULT_LOCALE))
+            .into_iter()
+            .find_map(|l| self.message_templates.get(&code.catalog_key(l)))
+            .unwrap_or_else(|| code.default_template().to_string())
+    }
 
-    #[mutate]
-    async fn register_user(&mut self, bio: String) -> Result<User, String> {
-        let sender = Runtime::sender();
+    fn localize(&self, message: Message, locale: &Option<String>) -> String {
+        message.render(&self.message_template(message.code, locale.as_deref()))
+    }
 
-        if let Some(mut existing) = self.users.get(&sender) {
-            existing.bio = bio;
-            self.users.insert(sender.clone(), existing.clone());
-            return Ok(existing);
+    // nobody checks their own stake, which also keeps the creator from
+    // checking a hand nobody else has staked on yet
+    fn ensure_checker_role(hand: &Hand, checker_id: &str) -> Result<(), Message> {
+        if hand.last_stake().is_some_and(|s| s.user_id == checker_id) {
+            return Err(Message::new(MessageCode::OwnStakeCheck));
         }
-
-        let user = User::new(sender.clone(), bio);
-        self.users.insert(sender.clone(), user.clone());
-        self.user_ids.push(sender);
-        Ok(user)
+        if hand.participants_only && !hand.stakes.iter().any(|s| s.user_id == checker_id) {
+            return Err(Message::new(MessageCode::ParticipantsOnly));
+        }
+        Ok(())
     }
 
-    #[query]
-    async fn get_users(&self) -> Vec<User> {
-        self.user_ids
+    // check payouts, including the subsidy a newcomer bracket checker gets
+    // on a loss, with the rewards capped to what the treasury can pay once
+    // the checker's penalty was collected
+    fn settle_check(
+        &self,
+        hand: &Hand,
+        bets: &[SideBet],
+        checker_id: &str,
+        bluff_detected: bool,
+    ) -> Vec<PayoutLine> {
+        let payouts = self.uncapped_check_payouts(hand, bets, checker_id, bluff_detected);
+        let collected: u64 = payouts
             .iter()
-            .filter_map(|id| self.users.get(&id))
-            .collect()
+            .filter(|l| l.amount < 0)
+            .map(|l| {
+                let balance = self.users.get(&l.user_id).map_or(0, |u| u.balance);
+                l.amount.unsigned_abs().min(balance.saturating_sub(self.params.balance_floor))
+            })
+            .sum();
+        let mut payouts = payouts::fund_from_treasury(payouts, self.treasury + collected);
+        let rake = payouts::rake_lines(&payouts, hand.pot, self.params.hand_rake_percent);
+        payouts.extend(rake);
+        if let Some(pool) = self.spectator_pools.get(&hand.hand_id) {
+            payouts.extend(payouts::spectator_pool_lines(&pool, bluff_detected));
+        }
+        payouts
     }
 
-    #[query]
-    async fn get_user(&self, id: String) -> Option<User> {
-        self.users.get(&id)
+    fn uncapped_check_payouts(
+        &self,
+        hand: &Hand,
+        bets: &[SideBet],
+        checker_id: &str,
+        bluff_detected: bool,
+    ) -> Vec<PayoutLine> {
+        if let Some(team) = self.team_hands.get(&hand.hand_id) {
+            let checking_team = EscalateContractState::checking_team(hand, &team);
+            let payouts = payouts::team_check_payouts(
+                hand,
+                &team,
+                bets,
+                checking_team,
+                bluff_detected,
+                &self.params,
+            );
+            return payouts;
+        }
+        let mut payouts =
+            payouts::check_payouts(hand, bets, checker_id, bluff_detected, &self.params);
+        let bonus = payouts::claim_diversity_bonus(hand, self.treasury);
+        if bonus > 0 {
+            let bonus_line = (hand.creator.clone(), bonus as i64);
+            payouts.push(PayoutLine::new(bonus_line, PayoutReason::ClaimDiversityBonus));
+        }
+        if !hand.newcomers_only {
+            return payouts;
+        }
+        let subsidy = payouts::newcomer_subsidy(
+            payouts::checker_delta(hand, bluff_detected),
+            NEWCOMER_SUBSIDY_PERCENT,
+            self.treasury,
+        );
+        let subsidy_line = (checker_id.to_string(), subsidy as i64);
+        payouts.push(PayoutLine::new(subsidy_line, PayoutReason::NewcomerSubsidy));
+        payouts
     }
 
-    #[query]
-    async fn get_my_cards(&self) -> Result<Vec<Card>, String> {
-        let sender = Runtime::sender();
-        match self.users.get(&sender) {
-            Some(u) => Ok(u.cards.clone()),
-            None => Err("user not registered".to_string()),
+    // the team that did not place the last stake
+    fn checking_team(hand: &Hand, team: &TeamHand) -> usize {
+        let last = hand.last_stake().map_or(&hand.creator, |s| &s.user_id);
+        match team.team_of(last) {
+            Some(0) => 1,
+            _ => 0,
         }
     }
 
-    #[mutate]
-    async fn start_hand(&mut self, claim: Card, cards: Vec<Card>) -> Result<Hand, String> {
-        let sender = Runtime::sender();
-        let mut user = self
-            .users
-            .get(&sender)
-            .ok_or_else(|| "user must register before starting a hand".to_string())?;
-
-        EscalateContractState::remove_cards_from_inventory(&mut user.cards, &cards)
-            .map_err(|e| e.to_string())?;
-
-        let hand_id = self.next_hand_id();
-        let stake = Stake {
-            user_id: sender.clone(),
-            cards: cards.clone(),
-        };
-        let hand = Hand {
-            hand_id: hand_id.clone(),
-            creator: sender.clone(),
-            claimed_card: claim,
-            is_resolved: false,
-            stakes: vec![stake],
+    // closes a hand that ends without a check: every stake goes back to its
+    // owner, side bets are refunded and the new status is stored
+    fn unwind_hand(&mut self, hand: &mut Hand, kind: EventKind) {
+        let closed = match hand.status {
+            HandStatus::Expired => HandActionKind::Expired,
+            HandStatus::Canceled => HandActionKind::Canceled,
+            _ => HandActionKind::Folded,
         };
+        self.log_hand_action(&hand.hand_id, &env::sender(), closed, None, None);
+        for stake in &hand.stakes {
+            if let Some(mut staker) = self.users.get(&stake.user_id) {
+                staker.add_cards(stake.cards.clone());
+                self.users.insert(stake.user_id.clone(), staker);
+            }
+        }
 
-        self.users.insert(sender.clone(), user);
-        self.hands.insert(hand_id.clone(), hand.clone());
-        self.hand_ids.push(hand_id);
+        let bets = self.side_bets.get(&hand.hand_id).unwrap_or_default();
+        let participants = self.hand_participants(hand);
+        self.apply_payouts(payouts::side_bet_refunds(&bets));
+        if let Some(pool) = self.spectator_pools.get(&hand.hand_id) {
+            self.apply_payouts(payouts::spectator_pool_refunds(&pool));
+        }
+        self.close_side_bets(&hand.hand_id);
 
-        Ok(hand)
+        self.store_hand(hand);
+        let participants: Vec<&str> = participants.iter().map(String::as_str).collect();
+        self.emit(kind, &hand.hand_id, &participants, None);
     }
 
-    #[query]
-    async fn get_hands(&self) -> Vec<Hand> {
-        self.hand_ids
-            .iter()
-            .filter_map(|id| self.hands.get(&id).map(|h| EscalateContractState::mask_hand_for_view(&h)))
-            .collect()
+    fn store_hand(&mut self, hand: &mut Hand) {
+        hand.update_seq += 1;
+        match (hand.is_resolved(), self.open_hand_slots.get(&hand.hand_id)) {
+            (true, Some(slot)) => self.unlist_open_hand(&hand.hand_id, slot),
+            (false, None) => self.list_open_hand(&hand.hand_id),
+            _ => {}
+        }
+        self.hands.insert(hand.hand_id.clone(), hand.clone());
     }
 
-    #[query]
-    async fn get_hand(&self, id: String) -> Option<Hand> {
-        self.hands
-            .get(&id)
-            .map(|h| EscalateContractState::mask_hand_for_view(&h))
+    // takes the first free slot past the live ones, reusing slots left
+    // behind by closed hands before growing the vector
+    fn list_open_hand(&mut self, hand_id: &String) {
+        let slot = self.open_hand_count;
+        if (slot as usize) < self.open_hand_ids.len() {
+            self.open_hand_ids.set(slot as usize, hand_id.clone());
+        } else {
+            self.open_hand_ids.push(hand_id.clone());
+        }
+        self.open_hand_slots.insert(hand_id.clone(), slot);
+        self.open_hand_count += 1;
     }
 
-    #[mutate]
-    async fn buy_cards(&mut self, amount: f64) -> Result<Vec<Card>, String> {
-        let sender = Runtime::sender();
-        let mut user = self
-            .users
-            .get(&sender)
-            .ok_or_else(|| "user must register before buying cards".to_string())?;
-
-        let spend = amount.floor();
-        if spend <= 0.0 {
-            return Ok(Vec::new());
-        }
-        if user.balance < spend {
-            return Err("insufficient balance".to_string());
+    // moves the last live id into the closed hand's slot
+    fn unlist_open_hand(&mut self, hand_id: &String, slot: u64) {
+        self.open_hand_slots.remove(hand_id);
+        self.open_hand_count -= 1;
+        let last = self.open_hand_count;
+        if slot != last
+            && let Some(moved) = self.open_hand_ids.get(last as usize)
+        {
+            self.open_hand_ids.set(slot as usize, moved.clone());
+            self.open_hand_slots.insert(moved, slot);
         }
-
-        let count = spend as u32;
-        let new_cards = get_random_cards(count);
-        user.balance -= spend;
-        user.cards.extend(new_cards.clone());
-
-        self.users.insert(sender, user);
-        Ok(new_cards)
     }
 
-    #[mutate]
-    async fn stake(&mut self, hand_id: String, cards: Vec<Card>) -> Result<Hand, String> {
-        let sender = Runtime::sender();
-        let mut user = self
-            .users
-            .get(&sender)
-            .ok_or_else(|| "user must register before staking".to_string())?;
+    // the ids of the open hands in the order they were started; hand ids
+    // count up, so sorting them restores the order slot moves mixed up
+    fn open_hands_in_start_order(&self) -> Vec<String> {
+        let mut ids: Vec<String> = (0..self.open_hand_count as usize)
+            .filter_map(|slot| self.open_hand_ids.get(slot))
+            .collect();
+        ids.sort_by_key(|id| id.parse::<u64>().unwrap_or_default());
+        ids
+    }
 
-        let mut hand = self
-            .hands
-            .get(&hand_id)
-            .ok_or_else(|| "hand not found for staking".to_string())?;
+    // appends to the hand's action log, copying the cards of `stake` if any
+    fn log_hand_action(
+        &mut self,
+        hand_id: &String,
+        user_id: &str,
+        kind: HandActionKind,
+        stake: Option<&Stake>,
+        bluff_detected: Option<bool>,
+    ) {
+        let mut actions = self.hand_actions.get(hand_id).unwrap_or_default();
+        actions.push(HandAction {
+            seq: actions.len() as u32 + 1,
+            user_id: user_id.to_string(),
+            kind,
+            cards: stake.map(|s| s.cards.clone()).unwrap_or_default(),
+            claimed_count: stake.map_or(0, |s| s.claimed_count),
+            bluff_detected,
+            at_block: env::block_height(),
+        });
+        self.hand_actions.insert(hand_id.clone(), actions);
+    }
 
-        if hand.is_resolved {
-            return Err("cannot stake on a resolved hand".to_string());
+    // pays out a checked hand, closes its side bets and records it as resolved
+    fn finish_check(&mut self, mut hand: Hand, bluff_detected: bool) -> CheckOutcome {
+        let hand_id = hand.hand_id.clone();
+        let bets = self.side_bets.get(&hand_id).unwrap_or_default();
+        let participants = self.hand_participants(&hand);
+        let payouts = self.settle_check(&hand, &bets, &env::sender(), bluff_detected);
+        self.check_payouts.insert(hand_id.clone(), payouts.clone());
+        self.apply_payouts(payouts.iter().cloned().map(payouts::Payout::from).collect());
+        self.treasury -= payouts::treasury_funded(&payouts);
+        for line in payouts.iter().filter(|l| matches!(l.reason, PayoutReason::Rake)) {
+            self.pay_referral(&line.user_id, line.amount.unsigned_abs());
         }
+        self.close_side_bets(&hand_id);
+        let burned: Vec<Card> = hand.stakes.iter().flat_map(|s| s.cards.clone()).collect();
+        self.deck.return_cards(&burned);
+        token::record_burned(&mut self.card_supply, &burned);
 
-        EscalateContractState::remove_cards_from_inventory(&mut user.cards, &cards)
-            .map_err(|e| e.to_string())?;
-
-        hand.stakes.push(Stake {
-            user_id: sender.clone(),
-            cards: cards.clone(),
+        hand.status = HandStatus::Checked;
+        hand.resolution = Some(CheckResolution {
+            checker_id: env::sender(),
+            bluff_detected,
+            checked_at_block: env::block_height(),
         });
+        let totals = self.latest_resolution_totals().record(&hand, bluff_detected);
+        self.resolution_totals.push(totals);
+        self.record_check_stats(&hand, &payouts, bluff_detected);
+        self.record_check_reputation(&hand, bluff_detected);
+        let notable = NotableHand {
+            hand_id: hand_id.clone(),
+            pot: hand.pot,
+            checker_id: env::sender(),
+            bluff_detected,
+        };
+        self.update_current_season(|s| s.record_checked_hand(notable));
+        let pot = hand.pot;
+        self.store_hand(&mut hand);
+        self.record_participation(&env::sender(), &hand_id);
+        let checked = HandActionKind::Checked;
+        self.log_hand_action(&hand_id, &env::sender(), checked, None, Some(bluff_detected));
+        let participants: Vec<&str> = participants.iter().map(String::as_str).collect();
+        self.emit(EventKind::Checked, &hand_id, &participants, None);
 
-        self.users.insert(sender.clone(), user);
-        self.hands.insert(hand_id.clone(), hand.clone());
-        Ok(hand)
+        CheckOutcome {
+            hand_id,
+            bluff_detected,
+            pot,
+            payouts,
+        }
     }
 
-    #[mutate]
-    async fn check(&mut self, hand_id: String) -> Result<bool, String> {
-        let checker_id = Runtime::sender();
-        let mut checker = self
-            .users
-            .get(&checker_id)
-            .ok_or_else(|| "user must register before checking".to_string())?;
+    fn update_stats(&mut self, user_id: &String, update: impl Fn(&mut UserStats)) {
+        let mut stats = self
+            .user_stats
+            .get(user_id)
+            .unwrap_or_else(|| UserStats::new(user_id.clone()));
+        update(&mut stats);
+        self.unlock_achievements(&stats);
+        self.user_stats.insert(user_id.clone(), stats);
+        self.update_season_stats(user_id, update);
+    }
 
-        let mut hand = self
-            .hands
-            .get(&hand_id)
-            .ok_or_else(|| "hand not found for check".to_string())?;
-        if hand.is_resolved {
-            return Err("hand already resolved".to_string());
+    // the running season keeps its own copy of every stat, counted from zero
+    fn update_season_stats(&mut self, user_id: &str, update: impl Fn(&mut UserStats)) {
+        let Some(season_id) = self.current_season.clone() else {
+            return;
+        };
+        let key = season::stats_key(&season_id, user_id);
+        let mut stats = self
+            .season_stats
+            .get(&key)
+            .unwrap_or_else(|| UserStats::new(user_id.to_string()));
+        update(&mut stats);
+        self.season_stats.insert(key, stats);
+
+        if let Some(mut season) = self.seasons.get(&season_id)
+            && !season.has_player(user_id)
+        {
+            season.players.push(user_id.to_string());
+            self.seasons.insert(season_id, season);
         }
+    }
 
-        if hand.stakes.is_empty() {
-            return Err("no stakes to check".to_string());
+    fn update_current_season(&mut self, update: impl FnOnce(&mut Season)) {
+        let Some(season_id) = self.current_season.clone() else {
+            return;
+        };
+        if let Some(mut season) = self.seasons.get(&season_id) {
+            update(&mut season);
+            self.seasons.insert(season_id, season);
         }
+    }
 
-        let last_stake_len = hand.stakes.last().unwrap().cards.len() as f64;
-        let bluff_detected = is_bluff(&hand);
+    // a referee qualifies once they played enough hands, wherever they ended up
+    fn referral_standings(&self, season: &Season) -> Vec<ReferralStanding> {
+        let referrals = season
+            .referees
+            .iter()
+            .filter_map(|referee_id| {
+                let referrer_id = self.users.get(referee_id)?.referrer?;
+                let hands = self.user_hands.get(referee_id).map_or(0, |h| h.len());
+                Some((referrer_id, hands >= season::REFERRAL_MIN_HANDS))
+            })
+            .collect();
+        season::referral_standings(referrals)
+    }
+
+    // tiered rewards for the season's best referrers, paid from the treasury
+    // until the season's referral budget is spent
+    fn pay_referral_rewards(&mut self, season: &mut Season) {
+        let mut budget = season::REFERRAL_BUDGET.min(self.treasury);
+        for (rank, standing) in self.referral_standings(season).into_iter().enumerate() {
+            let amount = standing.reward.min(budget);
+            if amount == 0 {
+                continue;
+            }
+            budget -= amount;
+            self.treasury -= amount;
+            self.apply_payouts(vec![(standing.referrer_id.clone(), amount as i64)]);
+            let others = [standing.referrer_id.as_str()];
+            self.emit(EventKind::SeasonReferralPaid, &season.season_id, &others, Some(amount));
+            season.referral_rewards.push(SeasonPrize {
+                user_id: standing.referrer_id,
+                rank: rank as u32 + 1,
+                amount,
+            });
+        }
+    }
+
+    fn season_standings(&self, season: &Season) -> Vec<UserStats> {
+        let stats = season
+            .players
+            .iter()
+            .filter_map(|p| self.season_stats.get(&season::stats_key(&season.season_id, p)))
+            .collect();
+        season::standings(season, stats)
+    }
+
+    // records every achievement `stats` reached for the first time, their
+    // rewards wait for `claim_achievement_rewards`
+    fn unlock_achievements(&mut self, stats: &UserStats) {
+        let mut unlocked = self.user_achievements.get(&stats.user_id).unwrap_or_default();
+        let reached = achievements::newly_reached(stats, &unlocked);
+        if reached.is_empty() {
+            return;
+        }
 
+        for kind in reached {
+            unlocked.push(UnlockedAchievement {
+                kind,
+                unlocked_at_block: env::block_height(),
+                rewards_claimed: false,
+            });
+            let goal = Some(Achievement::for_kind(kind).goal);
+            let user_id = stats.user_id.as_str();
+            self.emit(EventKind::AchievementUnlocked, user_id, &[user_id], goal);
+        }
+        self.user_achievements.insert(stats.user_id.clone(), unlocked);
+    }
+
+    // on team hands the whole checking team shares the check
+    fn checkers(&self, hand: &Hand) -> Vec<String> {
+        match self.team_hands.get(&hand.hand_id) {
+            Some(team) => {
+                let side = EscalateContractState::checking_team(hand, &team);
+                team.members(side).to_vec()
+            }
+            None => vec![env::sender()],
+        }
+    }
+
+    fn adjust_reputation(&mut self, user_id: &String, delta: i64) {
+        if let Some(mut user) = self.users.get(user_id) {
+            user.reputation = user.reputation.saturating_add(delta);
+            self.users.insert(user_id.clone(), user);
+        }
+    }
+
+    // only the last stake is revealed by a check, bluffs before it go unpunished
+    fn record_check_reputation(&mut self, hand: &Hand, bluff_detected: bool) {
         if bluff_detected {
-            checker.balance += last_stake_len;
-            self.reward_stakers(&hand.stakes, false, hand.claimed_card);
-        } else {
-            checker.balance -= last_stake_len;
-            self.reward_stakers(&hand.stakes, true, hand.claimed_card);
+            for checker in self.checkers(hand) {
+                self.adjust_reputation(&checker, REPUTATION_SUCCESSFUL_CHECK);
+            }
+        }
+        let last = hand.stakes.len() - 1;
+        for (i, stake) in hand.stakes.iter().enumerate() {
+            let bluffed =
+                escalate_rules::is_bluff(&stake.cards, hand.claimed_card, stake.claimed_count);
+            match (bluffed, i == last) {
+                (false, _) => self.adjust_reputation(&stake.user_id, REPUTATION_HONEST_STAKE),
+                (true, true) => self.adjust_reputation(&stake.user_id, REPUTATION_CAUGHT_BLUFF),
+                (true, false) => {}
+            }
+        }
+    }
+
+    fn record_check_stats(&mut self, hand: &Hand, payouts: &[PayoutLine], bluff_detected: bool) {
+        for line in payouts {
+            self.update_stats(&line.user_id, |s| s.total_winnings += line.amount);
         }
 
-        hand.is_resolved = true;
+        for checker in &self.checkers(hand) {
+            let net: i64 = payouts
+                .iter()
+                .filter(|l| l.user_id == *checker)
+                .filter(|l| {
+                    matches!(
+                        l.reason,
+                        PayoutReason::CheckerReward
+                            | PayoutReason::CheckerPenalty
+                            | PayoutReason::NewcomerSubsidy
+                    )
+                })
+                .map(|l| l.amount)
+                .sum();
+            self.update_stats(checker, |s| {
+                s.bluffs_caught += bluff_detected as u64;
+                s.successful_checks += (net > 0) as u64;
+            });
+        }
 
-        self.users.insert(checker_id, checker);
-        self.hands.insert(hand_id, hand);
+        let unchecked = hand.stakes.len().saturating_sub(1);
+        for (i, stake) in hand.stakes.iter().enumerate() {
+            let bluffed =
+                escalate_rules::is_bluff(&stake.cards, hand.claimed_card, stake.claimed_count);
+            self.update_stats(&stake.user_id, |s| {
+                s.checked_stakes += 1;
+                s.honest_stakes += !bluffed as u64;
+                s.bluffs_gotten_away_with += (bluffed && i < unchecked) as u64;
+            });
+        }
+    }
 
-        Ok(bluff_detected)
+    // opens a hand with the sender's first stake, shared by solo and team hands
+    fn open_hand(
+        &mut self,
+        claim: Card,
+        claimed_count: u32,
+        cards: Vec<Card>,
+        options: HandOptions,
+    ) -> Result<Hand, String> {
+        self.open_hand_as(env::sender(), claim, claimed_count, cards, options)
     }
 
-    #[mutate]
-    async fn offer(&mut self, cards: Vec<Card>, amount: f64) -> Result<Offer, String> {
-        let sender = Runtime::sender();
+    // opens a hand with `sender`'s first stake out of their inventory, which
+    // is someone other than the caller when an accepted challenge starts it
+    fn open_hand_as(
+        &mut self,
+        sender: String,
+        claim: Card,
+        claimed_count: u32,
+        cards: Vec<Card>,
+        options: HandOptions,
+    ) -> Result<Hand, String> {
+        self.ensure_creation_allowed()?;
+        self.ensure_unlocked(&sender)?;
         let mut user = self
             .users
             .get(&sender)
-            .ok_or_else(|| "user must register before offering cards".to_string())?;
+            .ok_or_else(|| "user must register before starting a hand".to_string())?;
+
+        if let Some(tag) = &options.region_tag
+            && !self.region_tags.contains(tag)
+        {
+            return Err(format!("region tag {} is not allowed", tag));
+        }
+
+        if options.duration_blocks == 0 {
+            return Err("hand duration must be at least one block".to_string());
+        }
+
+        if options.max_stakes == 0 {
+            return Err("a hand must allow at least its opening stake".to_string());
+        }
+        if options.max_stakes_per_player == Some(0) {
+            return Err("players must be allowed at least one stake each".to_string());
+        }
+
+        self.ensure_stake_size(&cards)?;
+        self.ensure_stake_loss_limit(&user, &cards)?;
+        let mut tournament = match &options.tournament_id {
+            Some(id) => Some(self.running_tournament(id)?),
+            None => None,
+        };
+
+        // the creator holds the first seat and the second player to join acts next
+        let turn_order = if options.turn_based { vec![sender.clone()] } else { Vec::new() };
+        let height = env::block_height();
+        let stake = Stake::new(sender.clone(), cards.clone(), claimed_count, height)?;
+        let mut hand = Hand {
+            hand_id: String::new(),
+            creator: sender.clone(),
+            claimed_card: claim,
+            status: HandStatus::Open,
+            pot: 0,
+            resolution: None,
+            stakes: Vec::new(),
+            region_tag: options.region_tag,
+            expires_at_block: height.saturating_add(options.duration_blocks),
+            duration_blocks: options.duration_blocks,
+            min_raise: options.min_raise,
+            max_stakes: options.max_stakes,
+            alternate_stakers: options.alternate_stakers,
+            max_stakes_per_player: options.max_stakes_per_player,
+            newcomers_only: options.newcomers_only,
+            participants_only: options.participants_only,
+            current_turn: turn_order.len() as u32,
+            turn_order,
+            passed: Vec::new(),
+            update_seq: 0,
+            allowed_stakers: options.allowed_stakers,
+            min_reputation: options.min_reputation,
+            tournament_id: options.tournament_id,
+            claim_bonus_percent: self.claim_bonus_percent(claim),
+        };
+        hand.add_stake(stake);
+        self.ensure_bracket_entry(&hand, &user, &cards)?;
 
         EscalateContractState::remove_cards_from_inventory(&mut user.cards, &cards)
             .map_err(|e| e.to_string())?;
 
-        let offer_id = self.next_offer_id();
-        let offer = Offer {
-            offer_id: offer_id.clone(),
-            creator_id: sender.clone(),
-            cards,
-            initial_price: amount,
-            current_bid: None,
-            current_bidder_id: None,
-            is_resolved: false,
-        };
+        let hand_id = self.next_hand_id();
+        hand.hand_id = hand_id.clone();
 
-        self.users.insert(sender, user);
-        self.offers.insert(offer_id.clone(), offer.clone());
-        self.offer_ids.push(offer_id);
+        self.users.insert(sender.clone(), user);
+        if let Some(t) = tournament.as_mut() {
+            t.hand_ids.push(hand_id.clone());
+            self.tournaments.insert(t.tournament_id.clone(), t.clone());
+        }
+        self.store_hand(&mut hand);
+        self.hand_ids.push(hand_id.clone());
+        let mut created = self.hands_by_creator.get(&sender).unwrap_or_default();
+        created.push(hand_id.clone());
+        self.hands_by_creator.insert(sender.clone(), created);
+        let claims = self.latest_claim_totals().record(claim);
+        self.claim_totals.push(claims);
+        self.record_participation(&sender, &hand_id);
+        self.update_stats(&sender, |s| s.hands_started += 1);
+        let opening = hand.last_stake().cloned();
+        self.log_hand_action(&hand_id, &sender, HandActionKind::Started, opening.as_ref(), None);
+        self.emit(EventKind::HandStarted, &hand_id, &[&sender], None);
 
-        Ok(offer)
+        Ok(hand)
     }
 
-    #[query]
-    async fn get_offers(&self) -> Vec<Offer> {
-        self.offer_ids
-            .iter()
-            .filter_map(|id| self.offers.get(&id))
-            .collect()
+    // deals the configured starter bundle to `user` once; when the deck can
+    // no longer supply it the account simply starts without cards
+    fn grant_starter_cards(&mut self, user: &mut User) {
+        let cards = self.params.starter_cards.clone();
+        if user.starter_granted
+            || cards.is_empty()
+            || self.ensure_inventory_room(user, cards.len()).is_err()
+            || self.deck.take(&cards).is_err()
+        {
+            return;
+        }
+        token::record_minted(&mut self.card_supply, &cards);
+        user.add_cards(cards);
+        user.starter_granted = true;
     }
 
-    #[mutate]
-    async fn bid(&mut self, offer_id: String, bid_amout: f64) -> Result<(), String> {
-        let bidder_id = Runtime::sender();
-        let mut bidder = self
-            .users
-            .get(&bidder_id)
-            .ok_or_else(|| "user must register before bidding".to_string())?;
-
-        let mut offer = self
-            .offers
-            .get(&offer_id)
-            .ok_or_else(|| "offer not found".to_string())?;
+    fn pending_recovery(&self, old_id: &String) -> Option<RecoveryRequest> {
+        self.recoveries
+            .get(old_id)
+            .filter(|r| r.status == RecoveryStatus::Pending)
+    }
 
-        if offer.is_resolved {
-            return Err("cannot bid on resolved offer".to_string());
+    // moves the account record, its stats, history, debt, identities,
+    // friends, guardians, packs, achievements, positions, referrals and
+    // season records from `old_id` to `new_id`. Open positions are keyed by
+    // the user id all over the state, so recovery waits until
+    // `open_position` finds none
+    fn migrate_account(&mut self, old_id: &String, new_id: &str) -> Option<User> {
+        let mut user = self.users.remove(old_id)?;
+        user.user_id = new_id.to_string();
+        self.users.insert(new_id.to_string(), user.clone());
+        if let Some(i) = self.user_ids.iter().position(|id| &id == old_id) {
+            self.user_ids.set(i, new_id.to_string());
         }
 
-        if offer.creator_id == bidder_id {
-            return Err("creator cannot bid on own offer".to_string());
+        if let Some(mut stats) = self.user_stats.remove(old_id) {
+            stats.user_id = new_id.to_string();
+            self.user_stats.insert(new_id.to_string(), stats);
+        }
+        if let Some(count) = self.user_event_counts.remove(old_id) {
+            for page in 0..count.div_ceil(USER_EVENT_PAGE_SIZE) {
+                let old_key = EscalateContractState::user_event_page_key(old_id, page);
+                if let Some(seqs) = self.user_event_pages.remove(&old_key) {
+                    let key = EscalateContractState::user_event_page_key(new_id, page);
+                    self.user_event_pages.insert(key, seqs);
+                }
+            }
+            self.user_event_counts.insert(new_id.to_string(), count);
+        }
+        if let Some(height) = self.last_active_blocks.remove(old_id) {
+            self.last_active_blocks.insert(new_id.to_string(), height);
+        }
+        if let Some(hands) = self.user_hands.remove(old_id) {
+            self.user_hands.insert(new_id.to_string(), hands);
+        }
+        if let Some(debt) = self.debts.remove(old_id) {
+            self.debts.insert(new_id.to_string(), debt);
+        }
+        if let Some(profile) = self.identities.remove(old_id) {
+            self.identities.insert(new_id.to_string(), profile);
+        }
+        if let Some(friends) = self.friends.remove(old_id) {
+            self.friends.insert(new_id.to_string(), friends);
+        }
+        if let Some(guardians) = self.recovery_guardians.remove(old_id) {
+            self.recovery_guardians.insert(new_id.to_string(), guardians);
         }
+        if let Some(pack_ids) = self.user_packs.remove(old_id) {
+            for pack_id in &pack_ids {
+                if let Some(mut pack) = self.packs.get(pack_id) {
+                    pack.owner_id = new_id.to_string();
+                    self.packs.insert(pack_id.clone(), pack);
+                }
+            }
+            self.user_packs.insert(new_id.to_string(), pack_ids);
+        }
+        if let Some(unlocked) = self.user_achievements.remove(old_id) {
+            self.user_achievements.insert(new_id.to_string(), unlocked);
+        }
+        self.migrate_positions(old_id, new_id);
+        self.migrate_referrals(old_id, new_id, user.referrer.as_ref());
+        self.migrate_season_records(old_id, new_id);
+        Some(user)
+    }
 
-        let min_bid = offer.current_bid.unwrap_or(offer.initial_price);
-        if bid_amout <= min_bid {
-            return Err("bid must be higher than current bid or initial price".to_string());
+    // moves the per-user offer, trade, transfer, challenge, auto-bid, hand,
+    // operator, installment plan and tournament indexes, renaming the
+    // account on the records they list so exports and searches by the new id
+    // find them
+    fn migrate_positions(&mut self, old_id: &String, new_id: &str) {
+        let rename = |id: &mut String| {
+            if id == old_id {
+                *id = new_id.to_string();
+            }
+        };
+        if let Some(offer_ids) = self.user_offers.remove(old_id) {
+            for offer_id in &offer_ids {
+                if let Some(mut offer) = self.offers.get(offer_id) {
+                    rename(&mut offer.creator_id);
+                    if let Some(bidder_id) = offer.current_bidder_id.as_mut() {
+                        rename(bidder_id);
+                    }
+                    self.offers.insert(offer_id.clone(), offer);
+                }
+            }
+            self.user_offers.insert(new_id.to_string(), offer_ids);
+        }
+        if let Some(trade_ids) = self.user_trades.remove(old_id) {
+            for trade_id in &trade_ids {
+                if let Some(mut trade) = self.trades.get(trade_id) {
+                    rename(&mut trade.proposer_id);
+                    rename(&mut trade.counterparty_id);
+                    self.trades.insert(trade_id.clone(), trade);
+                }
+            }
+            self.user_trades.insert(new_id.to_string(), trade_ids);
+        }
+        if let Some(intent_ids) = self.user_transfers.remove(old_id) {
+            for intent_id in &intent_ids {
+                if let Some(mut intent) = self.transfer_intents.get(intent_id) {
+                    intent.user_id = new_id.to_string();
+                    self.transfer_intents.insert(intent_id.clone(), intent);
+                }
+            }
+            self.user_transfers.insert(new_id.to_string(), intent_ids);
+        }
+        if let Some(challenge_ids) = self.user_challenges.remove(old_id) {
+            for challenge_id in &challenge_ids {
+                if let Some(mut challenge) = self.challenges.get(challenge_id) {
+                    rename(&mut challenge.challenger_id);
+                    rename(&mut challenge.friend_id);
+                    self.challenges.insert(challenge_id.clone(), challenge);
+                }
+            }
+            self.user_challenges.insert(new_id.to_string(), challenge_ids);
+        }
+        if let Some(rule_ids) = self.user_auto_bids.remove(old_id) {
+            for rule_id in &rule_ids {
+                if let Some(mut rule) = self.auto_bid_rules.get(rule_id) {
+                    rule.owner_id = new_id.to_string();
+                    self.auto_bid_rules.insert(rule_id.clone(), rule);
+                }
+            }
+            self.user_auto_bids.insert(new_id.to_string(), rule_ids);
+        }
+        if let Some(hand_ids) = self.hands_by_creator.remove(old_id) {
+            for hand_id in &hand_ids {
+                if let Some(mut hand) = self.hands.get(hand_id) {
+                    hand.creator = new_id.to_string();
+                    self.hands.insert(hand_id.clone(), hand);
+                }
+            }
+            self.hands_by_creator.insert(new_id.to_string(), hand_ids);
         }
+        if let Some(operators) = self.card_operators.remove(old_id) {
+            self.card_operators.insert(new_id.to_string(), operators);
+        }
+        if let Some(plan_ids) = self.user_installment_plans.remove(old_id) {
+            for plan_id in &plan_ids {
+                if let Some(mut plan) = self.installment_plans.get(plan_id) {
+                    rename(&mut plan.buyer_id);
+                    rename(&mut plan.seller_id);
+                    self.installment_plans.insert(plan_id.clone(), plan);
+                }
+            }
+            self.user_installment_plans.insert(new_id.to_string(), plan_ids);
+        }
+        if let Some(tournament_ids) = self.user_tournaments.remove(old_id) {
+            self.user_tournaments.insert(new_id.to_string(), tournament_ids);
+        }
+    }
 
-        // refund previous highest bidder, if any
-        if let (Some(prev_amount), Some(prev_bidder_id)) =
-            (offer.current_bid, offer.current_bidder_id.clone())
+    // the referees keep paying the recovered account, and its own referrer
+    // keeps earning from it
+    fn migrate_referrals(&mut self, old_id: &String, new_id: &str, referrer: Option<&String>) {
+        if let Some(referees) = self.referral_earnings.remove(old_id) {
+            for referral in &referees {
+                if let Some(mut referee) = self.users.get(&referral.referee_id) {
+                    referee.referrer = Some(new_id.to_string());
+                    self.users.insert(referral.referee_id.clone(), referee);
+                }
+            }
+            self.referral_earnings.insert(new_id.to_string(), referees);
+        }
+        if let Some(referrer) = referrer
+            && let Some(mut referees) = self.referral_earnings.get(referrer)
         {
-            if prev_bidder_id == bidder_id {
-                bidder.balance += prev_amount;
-            } else if let Some(mut prev_bidder) = self.users.get(&prev_bidder_id) {
-                prev_bidder.balance += prev_amount;
-                self.users.insert(prev_bidder_id, prev_bidder);
+            for referral in referees.iter_mut().filter(|r| r.referee_id == *old_id) {
+                referral.referee_id = new_id.to_string();
             }
+            self.referral_earnings.insert(referrer.clone(), referees);
         }
+    }
 
-        if bidder.balance < bid_amout {
-            return Err("insufficient balance for bid".to_string());
+    fn migrate_season_records(&mut self, old_id: &String, new_id: &str) {
+        for season_id in self.season_ids.clone() {
+            let Some(mut season) = self.seasons.get(&season_id) else {
+                continue;
+            };
+            if !season.has_player(old_id) && !season.has_referral(old_id) {
+                continue;
+            }
+            for player in season.players.iter_mut().filter(|p| *p == old_id) {
+                *player = new_id.to_string();
+            }
+            for referee in season.referees.iter_mut().filter(|r| *r == old_id) {
+                *referee = new_id.to_string();
+            }
+            for reward in season.referral_rewards.iter_mut().filter(|r| r.user_id == *old_id) {
+                reward.user_id = new_id.to_string();
+            }
+            for prize in season.prizes_paid.iter_mut().filter(|p| p.user_id == *old_id) {
+                prize.user_id = new_id.to_string();
+            }
+            for hand in season.notable_hands.iter_mut().filter(|h| h.checker_id == *old_id) {
+                hand.checker_id = new_id.to_string();
+            }
+            if let Some(mut archive) = self.season_archives.get(&season_id) {
+                archive.rename_player(old_id, new_id);
+                self.season_archives.insert(season_id.clone(), archive);
+            }
+            let key = season::stats_key(&season_id, old_id);
+            if let Some(mut stats) = self.season_stats.remove(&key) {
+                stats.user_id = new_id.to_string();
+                self.season_stats.insert(season::stats_key(&season_id, new_id), stats);
+            }
+            self.seasons.insert(season_id, season);
         }
+    }
 
-        bidder.balance -= bid_amout;
+    // the first kind of position `user` still has open. Escrowed chips cover
+    // bids, auto-bid budgets, side bets, wagers and prize escrows; the rest
+    // is looked up through the per-user indexes
+    fn open_position(&self, user: &User) -> Option<&'static str> {
+        let user_id = &user.user_id;
+        let ids_in = |index: &WeilMap<String, Vec<String>>| index.get(user_id).unwrap_or_default();
+        let open_hands = ids_in(&self.user_hands)
+            .iter()
+            .filter_map(|id| self.hands.get(id))
+            .any(|h| h.status == HandStatus::Open);
+        let open_offers = ids_in(&self.user_offers)
+            .iter()
+            .filter_map(|id| self.offers.get(id))
+            .any(|o| !o.is_resolved);
+        let unpaid_plans = ids_in(&self.user_installment_plans)
+            .iter()
+            .filter_map(|id| self.installment_plans.get(id))
+            .any(|p| !p.is_settled);
+        let open_trades = ids_in(&self.user_trades)
+            .iter()
+            .filter_map(|id| self.trades.get(id))
+            .any(|t| t.status == TradeStatus::Open);
+        let transferring = ids_in(&self.user_transfers)
+            .iter()
+            .filter_map(|id| self.transfer_intents.get(id))
+            .any(|t| matches!(t.status, TransferStatus::Pending | TransferStatus::Failed));
+        let in_tournament = ids_in(&self.user_tournaments)
+            .iter()
+            .filter_map(|id| self.tournaments.get(id))
+            .any(|t| t.status == TournamentStatus::Open);
+        let draws = self.pending_draws.get(user_id).unwrap_or_default();
+        [
+            (user.locked_balance > 0, "escrowed chips"),
+            (!self.escrowed_cards(user_id).is_empty(), "escrowed cards"),
+            (open_hands, "open hands"),
+            (open_offers, "open offers"),
+            (unpaid_plans, "unpaid installment plans"),
+            (open_trades, "open trades"),
+            (!draws.is_empty(), "unrevealed draws"),
+            (transferring, "pending transfers"),
+            (in_tournament, "running tournaments"),
+        ]
+        .into_iter()
+        .find_map(|(open, kind)| open.then_some(kind))
+    }
 
-        offer.current_bid = Some(bid_amout);
-        offer.current_bidder_id = Some(bidder_id.clone());
+    // the cards `user_id` has tied up in open offers, trades, hands and
+    // challenges, looked up through the per-user indexes
+    fn escrowed_cards(&self, user_id: &String) -> Vec<EscrowedCards> {
+        let held = |kind, subject_id: &String, cards: Vec<Card>| {
+            (!cards.is_empty()).then(|| EscrowedCards {
+                kind,
+                subject_id: subject_id.clone(),
+                cards,
+            })
+        };
 
-        self.users.insert(bidder_id, bidder);
-        self.offers.insert(offer_id, offer);
-        Ok(())
-    }
+        let offers = self.user_offers.get(user_id).unwrap_or_default();
+        let offers = offers
+            .iter()
+            .filter_map(|id| self.offers.get(id))
+            .filter(|o| !o.is_resolved)
+            .filter_map(|o| held(EscrowKind::Offer, &o.offer_id, o.cards));
+        let trades = self.user_trades.get(user_id).unwrap_or_default();
+        let trades = trades
+            .iter()
+            .filter_map(|id| self.trades.get(id))
+            .filter(|t| t.status == TradeStatus::Open && t.proposer_id == *user_id)
+            .filter_map(|t| held(EscrowKind::Trade, &t.trade_id, t.offered_cards));
+        let hands = self.user_hands.get(user_id).unwrap_or_default();
+        let hands = hands
+            .iter()
+            .filter_map(|id| self.hands.get(id))
+            .filter(|h| h.status == HandStatus::Open)
+            .filter_map(|h| {
+                let stakes = h.stakes.iter().filter(|s| s.user_id == *user_id);
+                let cards = stakes.flat_map(|s| s.cards.clone()).collect();
+                held(EscrowKind::Hand, &h.hand_id, cards)
+            });
+        let challenges = self.user_challenges.get(user_id).unwrap_or_default();
+        let challenges = challenges
+            .iter()
+            .filter_map(|id| self.challenges.get(id))
+            .filter(|c| c.status == ChallengeStatus::Pending && c.challenger_id == *user_id)
+            .filter_map(|c| held(EscrowKind::Challenge, &c.challenge_id, c.cards));
 
-    #[mutate]
-    async fn resolve(&mut self, offer_id: String) -> Result<(), String> {
-        let sender = Runtime::sender();
-        let mut offer = self
-            .offers
-            .get(&offer_id)
-            .ok_or_else(|| "offer not found".to_string())?;
+        offers.chain(trades).chain(hands).chain(challenges).collect()
+    }
 
-        if offer.creator_id != sender {
-            return Err("only creator can resolve offer".to_string());
-        }
+    fn seller_profile(&self, user_id: &String) -> Option<SellerProfile> {
+        let user = self.users.get(user_id)?;
+        let stats = self.user_stats.get(user_id);
+        Some(SellerProfile {
+            user_id: user.user_id,
+            bio: user.bio,
+            reputation: user.reputation,
+            honesty_index: stats.and_then(|s| s.honesty_index()),
+        })
+    }
 
-        if offer.is_resolved {
-            return Ok(());
+    // tournament hands are started between the start and end blocks
+    fn running_tournament(&self, tournament_id: &String) -> Result<Tournament, String> {
+        let tournament = self
+            .tournaments
+            .get(tournament_id)
+            .ok_or_else(|| "tournament not found".to_string())?;
+        if !tournament.is_running(env::block_height()) {
+            return Err("tournament is not running".to_string());
         }
+        Ok(tournament)
+    }
 
-        if let (Some(bid_amount), Some(bidder_id)) =
-            (offer.current_bid, offer.current_bidder_id.clone())
-        {
-            let mut bidder = self
-                .users
-                .get(&bidder_id)
-                .ok_or_else(|| "bidder not registered anymore".to_string())?;
-            let mut creator = self
-                .users
-                .get(&sender)
-                .ok_or_else(|| "creator not registered anymore".to_string())?;
-
-            creator.balance += bid_amount;
-            bidder.cards.extend(offer.cards.clone());
+    // credits tournament prizes or refunds to the players, repaying their
+    // debt first
+    fn pay_tournament(&mut self, payouts: &[(String, u64)]) {
+        let lines = payouts.iter().map(|(id, amount)| (id.clone(), *amount as i64)).collect();
+        self.apply_payouts(lines);
+    }
 
-            offer.is_resolved = true;
+    fn tournament_standings(&self, tournament: &Tournament) -> Vec<Standing> {
+        let payouts: Vec<Vec<PayoutLine>> = tournament
+            .hand_ids
+            .iter()
+            .filter_map(|id| self.check_payouts.get(id))
+            .collect();
+        tournament::standings(tournament, &payouts)
+    }
 
-            self.users.insert(bidder_id, bidder);
-            self.users.insert(sender, creator);
-        } else {
-            // no bids: return cards to creator
-            if let Some(mut creator) = self.users.get(&sender) {
-                creator.cards.extend(offer.cards.clone());
-                self.users.insert(sender, creator);
+    // accounts in cold storage cannot play until their unlock takes effect
+    fn ensure_unlocked(&self, user_id: &String) -> Result<(), String> {
+        match self.users.get(user_id) {
+            Some(user) if user.is_locked(env::block_height()) => {
+                Err("account is in cold storage, request an unlock first".to_string())
             }
-            offer.is_resolved = true;
+            _ => Ok(()),
         }
+    }
 
-        self.offers.insert(offer_id, offer);
-        Ok(())
+    // notes what every card of a sale went for, once per rank it held
+    fn record_sale_prices(&mut self, cards: &[Card], price_per_card: u64) {
+        let sample = SalePrice {
+            price_per_card,
+            sold_at_block: env::block_height(),
+        };
+        for card in Card::ALL {
+            if cards.contains(&card) {
+                market::record_sale(&mut self.sale_prices[token::token_id(card)], sample.clone());
+            }
+        }
     }
 
-    #[mutate]
-    async fn withdraw_bid(&mut self, offer_id: String) -> Result<(), String> {
-        let sender = Runtime::sender();
-        let mut offer = self
-            .offers
-            .get(&offer_id)
-            .ok_or_else(|| "offer not found".to_string())?;
+    // a lot sold whole for `price`, priced only when it holds a single rank.
+    // Every sale counts towards the running season's volume
+    fn record_lot_sale(&mut self, cards: &[Card], price: u64) {
+        if let Some(price_per_card) = market::lot_price_per_card(cards, price) {
+            self.record_sale_prices(cards, price_per_card);
+        }
+        self.update_current_season(|s| s.volume += price);
+    }
 
-        if offer.current_bidder_id.as_deref() != Some(&sender) {
-            return Err("only current bidder can withdraw bid".to_string());
+    // `incoming` more cards must fit under the inventory cap. Only cards new
+    // to the user are checked: cards returning to whoever escrowed them, from
+    // unwound hands, unsold offers, rejected trades or declined challenges,
+    // counted against the cap before they left and always come back
+    fn ensure_inventory_room(&self, user: &User, incoming: usize) -> Result<(), String> {
+        let cap = self.params.max_inventory_cards as usize;
+        if cap > 0 && user.cards.len() + incoming > cap {
+            return Err(format!("{} can hold at most {} cards", user.user_id, cap));
         }
+        Ok(())
+    }
 
-        if let Some(amount) = offer.current_bid {
-            if let Some(mut bidder) = self.users.get(&sender) {
-                bidder.balance += amount;
-                self.users.insert(sender.clone(), bidder);
-            }
+    // a bidder must have room for the cards the offer would hand them;
+    // installment buyers are checked when the plan is paid off instead
+    fn ensure_room_for_offer(&self, user: &User, offer: &Offer) -> Result<(), String> {
+        if offer.installments.is_some() {
+            return Ok(());
         }
+        self.ensure_inventory_room(user, offer.cards.len())
+    }
 
-        offer.current_bid = None;
-        offer.current_bidder_id = None;
+    // the block of the user's latest own call, or of their registration
+    fn last_activity_block(&self, user: &User) -> u64 {
+        self.last_active_blocks.get(&user.user_id).unwrap_or(user.registered_at_block)
+    }
 
-        self.offers.insert(offer_id, offer);
+    fn ensure_admin(&self, caller: &str) -> Result<(), String> {
+        if self.admin != caller {
+            return Err("only admin can perform this action".to_string());
+        }
         Ok(())
     }
 
-    #[mutate]
-    async fn deposit(&mut self, amount: f64) -> Result<(), String> {
-        // TODO: add the ability for users to deposit
-        // some amount
+    // while paused only the admin entrypoints keep working
+    fn ensure_not_paused(&self) -> Result<(), String> {
+        if self.paused {
+            return Err("contract is paused".to_string());
+        }
+        let height = env::block_height();
+        if let Some(window) = self.maintenance.as_ref().filter(|w| w.is_active(height)) {
+            let message = Message::new(MessageCode::MaintenanceWindow);
+            return Err(message.with_arg(window.end_block).into());
+        }
+        Ok(())
+    }
 
-        Err("Deposit is not allowed yet".into())
-        // let sender = Runtime::sender();
-        // let mut user = self
-        //     .users
-        //     .get(&sender)
-        //     .unwrap_or_else(|| User::new(sender.clone(), "".to_string()));
+    // turn-based hands only let the player to act stake, pass or check
+    fn ensure_turn(hand: &Hand, user_id: &String) -> Result<(), String> {
+        if !hand.is_turn_based() {
+            return Ok(());
+        }
+        match hand.turn_player() {
+            Some(player) if player == user_id => Ok(()),
+            Some(player) => Err(format!("it is {}'s turn", player)),
+            None => Err("waiting for another player to join the hand".to_string()),
+        }
+    }
 
-        // if amount <= 0.0 {
-        //     return Err("deposit amount must be positive".to_string());
-        // }
+    fn ensure_loss_limit(&self, user: &User, loss: u64) -> Result<(), String> {
+        if !self.params.allows_loss(user, loss) {
+            return Err(format!(
+                "a hand may risk at most {}% of the balance, opt in as a high roller to exceed it",
+                self.params.max_hand_loss_percent
+            ));
+        }
+        Ok(())
+    }
 
-        // user.balance += amount;
-        // if self.users.get(&sender).is_none() {
-        //     self.user_ids.push(sender.clone());
-        // }
-        // self.users.insert(sender, user);
-        // Ok(())
+    // staked cards are valued at the card price
+    fn ensure_stake_loss_limit(&self, user: &User, cards: &[Card]) -> Result<(), String> {
+        self.ensure_loss_limit(user, cards.len() as u64 * self.params.card_price)
     }
 
-    #[mutate]
-    fn start_file_upload(&mut self, path: String, total_chunks: u32) -> Result<(), String> {
-        self.server.start_file_upload(self.weil_id_generator.next_id(), path, total_chunks)
+    fn ensure_stake_size(&self, cards: &[Card]) -> Result<(), String> {
+        let max = self.params.max_stake_cards;
+        if cards.len() > max as usize {
+            return Err(format!("a stake may hold at most {} cards", max));
+        }
+        Ok(())
     }
 
-    #[query]
-    fn total_chunks(&self, path: String) -> Result<u32, String> {
-        self.server.total_chunks(path)
+    // once a successor froze creation, only settlement of existing
+    // hands, offers and bets is allowed here
+    fn ensure_creation_allowed(&self) -> Result<(), String> {
+        match &self.successor {
+            Some(s) if s.creation_frozen => Err(format!(
+                "contract is deprecated, create new objects on {}",
+                s.successor
+            )),
+            _ => Ok(()),
+        }
     }
 
-    #[mutate]
-    fn add_path_content(
+    fn next_hand_id(&mut self) -> String {
+        self.hand_counter += 1;
+        self.hand_counter.to_string()
+    }
+
+    fn next_offer_id(&mut self) -> String {
+        self.offer_counter += 1;
+        self.offer_counter.to_string()
+    }
+
+    fn next_auto_bid_id(&mut self) -> String {
+        self.auto_bid_counter += 1;
+        self.auto_bid_counter.to_string()
+    }
+
+    fn next_season_id(&mut self) -> String {
+        self.season_counter += 1;
+        self.season_counter.to_string()
+    }
+
+    fn next_tournament_id(&mut self) -> String {
+        self.tournament_counter += 1;
+        self.tournament_counter.to_string()
+    }
+
+    fn next_draw_id(&mut self) -> String {
+        self.draw_counter += 1;
+        self.draw_counter.to_string()
+    }
+
+    fn next_trade_id(&mut self) -> String {
+        self.trade_counter += 1;
+        self.trade_counter.to_string()
+    }
+
+    fn open_trade(&self, trade_id: &String) -> Result<Trade, String> {
+        let trade = self
+            .trades
+            .get(trade_id)
+            .ok_or_else(|| "trade not found".to_string())?;
+        if trade.status != TradeStatus::Open {
+            return Err("trade is already closed".to_string());
+        }
+        Ok(trade)
+    }
+
+    fn next_challenge_id(&mut self) -> String {
+        self.challenge_counter += 1;
+        self.challenge_counter.to_string()
+    }
+
+    fn pending_challenge(&self, challenge_id: &String) -> Result<Challenge, String> {
+        let challenge = self
+            .challenges
+            .get(challenge_id)
+            .ok_or_else(|| "challenge not found".to_string())?;
+        if challenge.status != ChallengeStatus::Pending {
+            return Err("challenge was already answered".to_string());
+        }
+        Ok(challenge)
+    }
+
+    fn next_transfer_id(&mut self) -> String {
+        self.transfer_counter += 1;
+        self.transfer_counter.to_string()
+    }
+
+    fn pending_transfer(&self, intent_id: &String) -> Result<TransferIntent, String> {
+        let intent = self
+            .transfer_intents
+            .get(intent_id)
+            .ok_or_else(|| "transfer not found".to_string())?;
+        if intent.status != TransferStatus::Pending {
+            return Err("transfer is no longer pending".to_string());
+        }
+        Ok(intent)
+    }
+
+    fn next_pack_id(&mut self) -> String {
+        self.pack_counter += 1;
+        self.pack_counter.to_string()
+    }
+
+    fn next_side_bet_id(&mut self) -> String {
+        self.side_bet_counter += 1;
+        self.side_bet_counter.to_string()
+    }
+
+    // settles the side bets and the spectator pool of a finished hand,
+    // dropping their escrow from the ledger once the payouts are applied
+    fn close_side_bets(&mut self, hand_id: &String) {
+        if let Some(mut bets) = self.side_bets.get(hand_id) {
+            for bet in bets.iter_mut().filter(|b| !b.is_settled) {
+                self.settle_escrow(&bet.proposer_id, bet.amount);
+                if bet.is_accepted {
+                    self.settle_escrow(&bet.opponent_id, bet.amount);
+                }
+                bet.is_settled = true;
+            }
+            self.side_bets.insert(hand_id.clone(), bets);
+        }
+        if let Some(mut pool) = self.spectator_pools.get(hand_id)
+            && !pool.is_settled
+        {
+            for wager in &pool.wagers {
+                self.settle_escrow(&wager.user_id, wager.amount);
+            }
+            pool.is_settled = true;
+            self.spectator_pools.insert(hand_id.clone(), pool);
+        }
+    }
+
+    fn release_escrow(&mut self, user_id: &String, amount: u64) {
+        if let Some(mut user) = self.users.get(user_id) {
+            user.unlock(amount);
+            self.users.insert(user_id.clone(), user);
+        }
+    }
+
+    fn settle_escrow(&mut self, user_id: &String, amount: u64) {
+        if let Some(mut user) = self.users.get(user_id) {
+            user.settle(amount);
+            self.users.insert(user_id.clone(), user);
+        }
+    }
+
+    // assembled from the per-user indexes; offers and side bets have none,
+    // so they are found through the subjects of the user's events
+    fn export_data(&self, user: User) -> UserDataExport {
+        let user_id = user.user_id.clone();
+        let events: Vec<Event> = self
+            .user_event_seqs(&user_id, 0, u64::MAX)
+            .into_iter()
+            .filter_map(|s| self.events.get(s as usize - 1))
+            .collect();
+        let mut offer_ids: Vec<String> = Vec::new();
+        let mut bet_ids: Vec<String> = Vec::new();
+        for event in &events {
+            let ids = match event.kind {
+                EventKind::OfferCreated
+                | EventKind::BidPlaced
+                | EventKind::SealedBidPlaced
+                | EventKind::BidRevealed
+                | EventKind::BidWithdrawn
+                | EventKind::OfferResolved
+                | EventKind::OfferBoughtOut
+                | EventKind::BundlePurchased
+                | EventKind::OfferBoosted
+                | EventKind::OfferExpired
+                | EventKind::InstallmentPaid
+                | EventKind::InstallmentPlanCompleted
+                | EventKind::InstallmentPlanDefaulted => &mut offer_ids,
+                EventKind::SideBetProposed | EventKind::SideBetAccepted => &mut bet_ids,
+                _ => continue,
+            };
+            if !ids.contains(&event.subject_id) {
+                ids.push(event.subject_id.clone());
+            }
+        }
+
+        let hands = self
+            .user_hands
+            .get(&user_id)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.hands.get(id))
+            .map(|hand| {
+                let mut masked = EscalateContractState::mask_hand_for_view(&hand);
+                for (own, stake) in masked.stakes.iter_mut().zip(&hand.stakes) {
+                    if stake.user_id == user_id {
+                        own.cards = stake.cards.clone();
+                    }
+                }
+                masked
+            })
+            .collect();
+        let side_bets = bet_ids
+            .iter()
+            .filter_map(|bet_id| {
+                let hand_id = self.side_bet_hand_ids.get(bet_id)?;
+                let bets = self.side_bets.get(&hand_id)?;
+                bets.into_iter().find(|b| b.bet_id == *bet_id)
+            })
+            .collect();
+        let sealed_bids = offer_ids
+            .iter()
+            .flat_map(|id| self.sealed_bids.get(id).unwrap_or_default())
+            .filter(|b| b.bidder_id == user_id)
+            .collect();
+        let ids_in = |index: &WeilMap<String, Vec<String>>| index.get(&user_id).unwrap_or_default();
+        let installment_plans = ids_in(&self.user_installment_plans)
+            .iter()
+            .filter_map(|id| self.installment_plans.get(id))
+            .collect();
+
+        UserDataExport {
+            debt: self.debts.get(&user_id).unwrap_or(0),
+            stats: self.user_stats.get(&user_id),
+            identities: self.identities.get(&user_id),
+            hands,
+            side_bets,
+            offers: offer_ids.iter().filter_map(|id| self.offers.get(id)).collect(),
+            sealed_bids,
+            installment_plans,
+            trades: ids_in(&self.user_trades).iter().filter_map(|id| self.trades.get(id)).collect(),
+            packs: ids_in(&self.user_packs).iter().filter_map(|id| self.packs.get(id)).collect(),
+            pending_draws: self.pending_draws.get(&user_id).unwrap_or_default(),
+            transfers: ids_in(&self.user_transfers)
+                .iter()
+                .filter_map(|id| self.transfer_intents.get(id))
+                .collect(),
+            events,
+            user,
+        }
+    }
+
+    // the ids on one page of an id list, plus the length of the whole list
+    fn page_ids(ids: &WeilVec<String>, offset: u64, limit: u64) -> (Vec<String>, u64) {
+        let total = ids.len() as u64;
+        let end = offset.saturating_add(limit.min(MAX_PAGE_LIMIT)).min(total);
+        let page = (offset..end).filter_map(|i| ids.get(i as usize)).collect();
+        (page, total)
+    }
+
+    fn users_page(&self, offset: u64, limit: u64) -> UserPage {
+        let (ids, total_count) = EscalateContractState::page_ids(&self.user_ids, offset, limit);
+        PageResult {
+            items: ids.iter().filter_map(|id| self.users.get(id)).collect(),
+            total_count,
+        }
+    }
+
+    fn hands_page(&self, offset: u64, limit: u64) -> HandPage {
+        let (ids, total_count) = EscalateContractState::page_ids(&self.hand_ids, offset, limit);
+        PageResult {
+            items: ids
+                .iter()
+                .filter_map(|id| self.hands.get(id))
+                .map(|h| EscalateContractState::mask_hand_for_view(&h))
+                .collect(),
+            total_count,
+        }
+    }
+
+    // hands `user_id` started, staked on, checked or was assigned to, oldest first
+    fn participant_hands_page(
+        &self,
+        user_id: &String,
+        status_filter: Option<HandStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> HandPage {
+        let hands: Vec<Hand> = self
+            .user_hands
+            .get(user_id)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.hands.get(id))
+            .filter(|h| status_filter.is_none_or(|s| h.status == s))
+            .collect();
+        PageResult {
+            total_count: hands.len() as u64,
+            items: hands
+                .iter()
+                .skip(offset as usize)
+                .take(limit.min(MAX_PAGE_LIMIT) as usize)
+                .map(EscalateContractState::mask_hand_for_view)
+                .collect(),
+        }
+    }
+
+    // the user's events oldest first, checks annotated with their net result
+    fn activity_page(&self, user_id: &String, offset: u64, limit: u64) -> ActivityPage {
+        let items = self
+            .user_event_seqs(user_id, offset, limit.min(MAX_PAGE_LIMIT))
+            .iter()
+            .filter_map(|s| self.events.get(*s as usize - 1))
+            .map(|event| {
+                let net_change = match event.kind {
+                    EventKind::Checked => self.check_payouts.get(&event.subject_id).map(|lines| {
+                        lines.iter().filter(|l| l.user_id == *user_id).map(|l| l.amount).sum()
+                    }),
+                    _ => None,
+                };
+                Activity {
+                    seq: event.seq,
+                    block_height: event.block_height,
+                    kind: event.kind,
+                    subject_id: event.subject_id,
+                    amount: event.amount,
+                    net_change,
+                }
+            })
+            .collect();
+        let total_count = self.user_event_counts.get(user_id).unwrap_or(0);
+        PageResult { items, total_count }
+    }
+
+    fn record_tournament(&mut self, user_id: &String, tournament_id: &String) {
+        let mut ids = self.user_tournaments.get(user_id).unwrap_or_default();
+        ids.push(tournament_id.clone());
+        self.user_tournaments.insert(user_id.clone(), ids);
+    }
+
+    fn user_event_page_key(user_id: &str, page: u64) -> String {
+        format!("{}:{}", user_id, page)
+    }
+
+    fn record_user_event(&mut self, user_id: &String, seq: u64) {
+        let count = self.user_event_counts.get(user_id).unwrap_or(0);
+        let page = count / USER_EVENT_PAGE_SIZE;
+        let key = EscalateContractState::user_event_page_key(user_id, page);
+        let mut seqs = self.user_event_pages.get(&key).unwrap_or_default();
+        seqs.push(seq);
+        self.user_event_pages.insert(key, seqs);
+        self.user_event_counts.insert(user_id.clone(), count + 1);
+    }
+
+    // the seqs of the user's events from the `offset`th on, oldest first,
+    // reading only the pages that hold them
+    fn user_event_seqs(&self, user_id: &str, offset: u64, limit: u64) -> Vec<u64> {
+        let count = self.user_event_counts.get(&user_id.to_string()).unwrap_or(0);
+        let end = offset.saturating_add(limit).min(count);
+        if offset >= end {
+            return Vec::new();
+        }
+        let first = offset / USER_EVENT_PAGE_SIZE;
+        (first..end.div_ceil(USER_EVENT_PAGE_SIZE))
+            .flat_map(|page| {
+                let key = EscalateContractState::user_event_page_key(user_id, page);
+                self.user_event_pages.get(&key).unwrap_or_default()
+            })
+            .skip((offset - first * USER_EVENT_PAGE_SIZE) as usize)
+            .take((end - offset) as usize)
+            .collect()
+    }
+
+    // the seqs of the user's events after `seq`, oldest first; seqs grow
+    // with every event, so a binary search over the pages finds the first
+    fn user_event_seqs_after(&self, user_id: &str, seq: u64, limit: u64) -> Vec<u64> {
+        let count = self.user_event_counts.get(&user_id.to_string()).unwrap_or(0);
+        let first_seq = |page: u64| {
+            let key = EscalateContractState::user_event_page_key(user_id, page);
+            self.user_event_pages.get(&key).unwrap_or_default().first().copied()
+        };
+        let (mut low, mut high) = (0, count.div_ceil(USER_EVENT_PAGE_SIZE));
+        while low < high {
+            let mid = (low + high) / 2;
+            if first_seq(mid).is_some_and(|s| s <= seq) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        // the first page read may still hold up to a page of older seqs
+        let offset = low.saturating_sub(1) * USER_EVENT_PAGE_SIZE;
+        self.user_event_seqs(user_id, offset, USER_EVENT_PAGE_SIZE.saturating_add(limit))
+            .into_iter()
+            .filter(|s| *s > seq)
+            .take(limit as usize)
+            .collect()
+    }
+
+    fn record_participation(&mut self, user_id: &String, hand_id: &String) {
+        let mut ids = self.user_hands.get(user_id).unwrap_or_default();
+        if !ids.contains(hand_id) {
+            ids.push(hand_id.clone());
+            self.user_hands.insert(user_id.clone(), ids);
+        }
+    }
+
+    // boosted offers come first, most recently boosted last, then every
+    // other offer in listing order
+    fn offers_page(&self, offset: u64, limit: u64) -> OfferPage {
+        let height = env::block_height();
+        let boosted: Vec<Offer> = self
+            .boosted_offer_ids
+            .iter()
+            .filter_map(|id| self.offers.get(id))
+            .filter(|o| o.is_boosted(height))
+            .collect();
+        let rest = (0..self.offer_ids.len())
+            .filter_map(|i| self.offer_ids.get(i))
+            .filter(|id| !boosted.iter().any(|o| o.offer_id == *id))
+            .filter_map(|id| self.offers.get(&id));
+        PageResult {
+            items: boosted
+                .iter()
+                .cloned()
+                .chain(rest)
+                .skip(offset as usize)
+                .take(limit.min(MAX_PAGE_LIMIT) as usize)
+                .collect(),
+            total_count: self.offer_ids.len() as u64,
+        }
+    }
+
+    // charges the listing fee, moves the cards (or the pack) out of the
+    // caller's hands and opens the offer; callers validate their own
+    // auction terms first. The seller's reputation scales the fee down, and
+    // below zero forces full escrow on the offer
+    fn list_offer(
         &mut self,
-        path: String,
-        chunk: Vec<u8>,
-        index: u32,
-    ) -> Result<(), String> {
-        self.server.add_path_content(path, chunk, index)
+        cards: Vec<Card>,
+        pack_id: Option<String>,
+        terms: OfferTerms,
+    ) -> Result<Offer, String> {
+        self.ensure_creation_allowed()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before offering cards".to_string())?;
+
+        let listed_pack = match &pack_id {
+            Some(pack_id) => Some(self.owned_pack(pack_id, &sender)?),
+            None => None,
+        };
+        let risky = escalate_rules::is_risky_seller(user.reputation);
+        if risky && terms.installments.is_some() {
+            return Err("sellers with negative reputation must be paid in full".to_string());
+        }
+        let listing_fee = match &listed_pack {
+            Some(pack) => pack.listing_fee(),
+            None => Offer::listing_fee_for(&cards),
+        };
+        let listing_fee = escalate_rules::listing_fee_for_reputation(listing_fee, user.reputation);
+        if user.balance < listing_fee {
+            return Err("insufficient balance for listing fee".to_string());
+        }
+
+        EscalateContractState::remove_cards_from_inventory(&mut user.cards, &cards)
+            .map_err(|e| e.to_string())?;
+
+        if let Some(mut pack) = listed_pack {
+            pack.is_listed = true;
+            self.packs.insert(pack.pack_id.clone(), pack);
+        }
+
+        user.balance -= listing_fee;
+        self.treasury += listing_fee;
+
+        let offer_id = self.next_offer_id();
+        let mut offer = Offer::new(
+            offer_id.clone(),
+            sender.clone(),
+            cards,
+            terms.amount,
+            listing_fee,
+            terms.expires_at,
+            terms.installments,
+        );
+        offer.reveal_deadline = terms.reveal_deadline;
+        offer.buyout_price = terms.buyout_price;
+        offer.card_prices = terms.card_prices;
+        offer.dutch = terms.dutch;
+        offer.price_per_card = terms.price_per_card;
+        offer.pack_id = pack_id;
+        offer.requires_confirmation = risky && offer.can_require_confirmation();
+
+        self.users.insert(sender.clone(), user);
+        self.offer_ids.push(offer_id.clone());
+        let mut listed = self.user_offers.get(&sender).unwrap_or_default();
+        listed.push(offer_id.clone());
+        self.user_offers.insert(sender, listed);
+        self.emit(EventKind::OfferCreated, &offer_id, &[], Some(terms.amount));
+        self.place_auto_bid(&mut offer);
+        self.offers.insert(offer_id, offer.clone());
+
+        Ok(offer)
     }
 
-    #[mutate]
-    fn finish_upload(&mut self, path: String, size_bytes: u32) -> Result<(), String> {
-        self.server.finish_upload(path, size_bytes)
+    // lets the best matching auto-bid rule open the bidding on a new offer
+    fn place_auto_bid(&mut self, offer: &mut Offer) {
+        // bundles sell card by card and never take bids
+        if offer.price_per_card.is_some() {
+            return;
+        }
+        let mut rule_ids: Vec<String> = Vec::new();
+        for card in &offer.cards {
+            for id in self.card_auto_bids.get(&format!("{:?}", card)).unwrap_or_default() {
+                if !rule_ids.contains(&id) {
+                    rule_ids.push(id);
+                }
+            }
+        }
+        let mut rules: Vec<AutoBidRule> =
+            rule_ids.iter().filter_map(|id| self.auto_bid_rules.get(id)).collect();
+        // owners without room for the cards sit this offer out
+        rules.retain(|r| {
+            self.users
+                .get(&r.owner_id)
+                .is_some_and(|owner| self.ensure_room_for_offer(&owner, offer).is_ok())
+        });
+        // oldest rules first, they win ties
+        rules.sort_by_key(|r| r.rule_id.parse::<u64>().unwrap_or(u64::MAX));
+        let Some((rule_id, amount)) = auto_bid::opening_bid(&rules, offer) else {
+            return;
+        };
+
+        let mut rule = self.auto_bid_rules.get(&rule_id).expect("picked from the stored rules");
+        rule.budget -= amount;
+        offer.current_bid = Some(amount);
+        offer.current_bidder_id = Some(rule.owner_id.clone());
+        let owner_id = rule.owner_id.clone();
+        self.auto_bid_rules.insert(rule_id, rule);
+        self.emit(EventKind::AutoBidPlaced, &offer.offer_id, &[&owner_id], Some(amount));
     }
 
-    #[query]
-    fn http_content(
+    // an open offer whose counter `bidder_id`, its current bidder, has yet
+    // to answer
+    fn countered_offer(&self, offer_id: &String, bidder_id: &str) -> Result<Offer, String> {
+        let offer = self
+            .offers
+            .get(offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        if offer.is_resolved || offer.is_expired(env::block_height()) {
+            return Err("offer is no longer open".to_string());
+        }
+        if offer.current_bidder_id.as_deref() != Some(bidder_id) {
+            return Err("only the current bidder can answer a counter".to_string());
+        }
+        if offer.negotiation_state != NegotiationState::Countered {
+            return Err("offer has no pending counter".to_string());
+        }
+        if offer.awaiting_confirmation() {
+            return Err("offer is waiting for the bidder to confirm the purchase".to_string());
+        }
+        Ok(offer)
+    }
+
+    // settles a resolved offer: the highest bid buys the cards, or opens its
+    // installment plan, and without bids they go back to the seller
+    fn complete_offer_sale(&mut self, mut offer: Offer) -> Result<(), String> {
+        let offer_id = offer.offer_id.clone();
+        let creator_id = offer.creator_id.clone();
+        if let (Some(bid_amount), Some(bidder_id)) =
+            (offer.current_bid, offer.current_bidder_id.clone())
+        {
+            let mut bidder = self
+                .users
+                .get(&bidder_id)
+                .ok_or_else(|| "bidder not registered anymore".to_string())?;
+            if self.users.get(&creator_id).is_none() {
+                return Err("creator not registered anymore".to_string());
+            }
+            // room was checked when the bid was placed, but cards received
+            // since can fill the inventory; the sale then falls through
+            if self.ensure_room_for_offer(&bidder, &offer).is_err() {
+                return self.unwind_unsold_offer(offer, EventKind::OfferBuyerInventoryFull);
+            }
+
+            // the escrowed bid pays the seller, or opens the installment plan
+            bidder.settle(offer.bid_escrow(bid_amount));
+            let refund = payouts::listing_refund(&offer, self.treasury);
+            // installment sales are raked when the last installment settles
+            let rake = match offer.installments {
+                Some(_) => 0,
+                None => payouts::sale_rake(bid_amount, self.params.sale_rake_percent),
+            };
+            let proceeds = payouts::sale_proceeds(&offer, bid_amount, refund) - rake;
+            self.treasury = self.treasury - refund + rake;
+            match offer.installments.clone() {
+                // cards wait in the plan until the last installment is paid
+                Some(terms) => {
+                    let plan = InstallmentPlan::new(
+                        offer_id.clone(),
+                        bidder_id.clone(),
+                        creator_id.clone(),
+                        offer.cards.clone(),
+                        bid_amount,
+                        terms,
+                        env::block_height(),
+                    );
+                    self.installment_plans.insert(offer_id.clone(), plan);
+                    for user_id in [&bidder_id, &creator_id] {
+                        let mut ids = self.user_installment_plans.get(user_id).unwrap_or_default();
+                        ids.push(offer_id.clone());
+                        self.user_installment_plans.insert(user_id.clone(), ids);
+                    }
+                }
+                None => {
+                    bidder.add_cards(offer.cards.clone());
+                    self.record_lot_sale(&offer.cards, bid_amount);
+                    let net = (bid_amount - rake) as i64;
+                    let jokers = achievements::jokers_in(&offer.cards);
+                    self.update_stats(&creator_id, |s| {
+                        s.total_winnings += net;
+                        s.jokers_sold += jokers;
+                    });
+                }
+            }
+            self.settle_offer_pack(&offer, &bidder_id);
+
+            offer.is_resolved = true;
+
+            self.users.insert(bidder_id, bidder);
+            self.apply_payouts(vec![(creator_id.clone(), proceeds as i64)]);
+            self.pay_referral(&creator_id, rake);
+        } else {
+            // no bids: return cards to creator
+            if let Some(mut creator) = self.users.get(&creator_id) {
+                creator.add_cards(offer.cards.clone());
+                self.users.insert(creator_id.clone(), creator);
+            }
+            self.adjust_reputation(&creator_id, REPUTATION_ABANDONED_OFFER);
+            self.settle_offer_pack(&offer, &creator_id);
+            offer.is_resolved = true;
+        }
+
+        let buyer_id = offer.current_bidder_id.clone().unwrap_or_default();
+        let price = offer.current_bid;
+        self.offers.insert(offer_id.clone(), offer);
+        self.emit(EventKind::OfferResolved, &offer_id, &[&creator_id, &buyer_id], price);
+        Ok(())
+    }
+
+    // resolves an offer that does not sell, its highest bid short of the
+    // reserve, never confirmed or its bidder out of room: the bidder is
+    // refunded and the cards (or the pack) go back to the seller, `kind`
+    // naming the reason
+    fn unwind_unsold_offer(&mut self, mut offer: Offer, kind: EventKind) -> Result<(), String> {
+        let bidder_id = offer.current_bidder_id.clone().unwrap_or_default();
+        if let Some((bidder_id, amount)) = payouts::outbid_refund(&offer)
+            && let Some(mut bidder) = self.users.get(&bidder_id)
+        {
+            bidder.unlock(amount);
+            self.users.insert(bidder_id, bidder);
+        }
+        if let Some(mut creator) = self.users.get(&offer.creator_id) {
+            creator.add_cards(offer.cards.clone());
+            self.users.insert(offer.creator_id.clone(), creator);
+        }
+        self.settle_offer_pack(&offer, &offer.creator_id);
+
+        let highest = offer.current_bid.take();
+        offer.current_bidder_id = None;
+        offer.is_resolved = true;
+        let offer_id = offer.offer_id.clone();
+        self.offers.insert(offer_id.clone(), offer);
+        self.emit(kind, &offer_id, &[&bidder_id], highest);
+        Ok(())
+    }
+
+    // settles an installment plan whose buyer has no room for the cards at
+    // the last installment: the payments so far go back to the buyer and the
+    // cards to the seller, as when a buyer is out of room at resolve time
+    fn unwind_installment_plan(&mut self, mut plan: InstallmentPlan) -> InstallmentPlan {
+        if let Some(mut seller) = self.users.get(&plan.seller_id) {
+            seller.add_cards(plan.cards.clone());
+            self.users.insert(plan.seller_id.clone(), seller);
+        }
+        self.apply_payouts(vec![(plan.buyer_id.clone(), plan.paid as i64)]);
+        plan.is_settled = true;
+        self.installment_plans.insert(plan.offer_id.clone(), plan.clone());
+        self.emit(
+            EventKind::OfferBuyerInventoryFull,
+            &plan.offer_id,
+            &[&plan.seller_id],
+            Some(plan.paid),
+        );
+        plan
+    }
+
+    // a live offer `buyer_id` may buy outright
+    fn offer_for_instant_sale(
         &self,
-        path: String,
-        index: u32,
-        method: String,
-    ) -> (u16, std::collections::HashMap<String, String>, Vec<u8>) {
-        self.server.http_content(path, index, method)
+        offer_id: &String,
+        buyer_id: &String,
+    ) -> Result<Offer, String> {
+        self.ensure_unlocked(buyer_id)?;
+        let offer = self
+            .offers
+            .get(offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        if offer.is_resolved {
+            return Err("offer already resolved".to_string());
+        }
+
+        if offer.is_expired(env::block_height()) {
+            return Err("cannot buy an expired offer".to_string());
+        }
+
+        if offer.creator_id == *buyer_id {
+            return Err("creator cannot buy own offer".to_string());
+        }
+
+        if offer.counter_accepted() {
+            return Err("offer is settling at an accepted counter price".to_string());
+        }
+
+        if offer.awaiting_confirmation() {
+            return Err("offer is waiting for the bidder to confirm the purchase".to_string());
+        }
+        Ok(offer)
     }
 
-    #[query]
-    fn size_bytes(&self, path: String) -> Result<u32, String> {
-        self.server.size_bytes(path)
+    // sells the offer to `buyer_id` at `price` right away, refunding the
+    // highest bid
+    fn complete_instant_sale(
+        &mut self,
+        mut offer: Offer,
+        buyer_id: String,
+        price: u64,
+        kind: EventKind,
+    ) -> Result<Offer, String> {
+        let mut buyer = self
+            .users
+            .get(&buyer_id)
+            .ok_or_else(|| "user must register before buying".to_string())?;
+
+        // a buyer holding the highest bid can put its escrow towards the price
+        let refund = payouts::outbid_refund(&offer);
+        let own_escrow = refund
+            .as_ref()
+            .filter(|(bidder_id, _)| *bidder_id == buyer_id)
+            .map_or(0, |(_, amount)| *amount);
+        if buyer.balance + own_escrow < price {
+            return Err("insufficient balance for buyout".to_string());
+        }
+        self.ensure_inventory_room(&buyer, offer.cards.len())?;
+        if self.users.get(&offer.creator_id).is_none() {
+            return Err("creator not registered anymore".to_string());
+        }
+
+        let outbid_id = offer.current_bidder_id.clone().unwrap_or_default();
+        if let Some((prev_bidder_id, prev_amount)) = refund {
+            if prev_bidder_id == buyer_id {
+                buyer.unlock(prev_amount);
+            } else if let Some(mut prev_bidder) = self.users.get(&prev_bidder_id) {
+                prev_bidder.unlock(prev_amount);
+                self.users.insert(prev_bidder_id, prev_bidder);
+            }
+        }
+
+        buyer.balance -= price;
+        buyer.add_cards(offer.cards.clone());
+        let refund = payouts::listing_refund(&offer, self.treasury);
+        let rake = payouts::sale_rake(price, self.params.sale_rake_percent);
+        let proceeds = payouts::sale_proceeds(&offer, price, refund) - rake;
+        self.treasury = self.treasury - refund + rake;
+        self.settle_offer_pack(&offer, &buyer_id);
+        self.record_lot_sale(&offer.cards, price);
+        let jokers = achievements::jokers_in(&offer.cards);
+        self.update_stats(&offer.creator_id, |s| {
+            s.total_winnings += (price - rake) as i64;
+            s.jokers_sold += jokers;
+        });
+
+        offer.current_bid = Some(price);
+        offer.current_bidder_id = Some(buyer_id.clone());
+        offer.is_resolved = true;
+
+        let offer_id = offer.offer_id.clone();
+        self.users.insert(buyer_id, buyer);
+        self.apply_payouts(vec![(offer.creator_id.clone(), proceeds as i64)]);
+        self.offers.insert(offer_id.clone(), offer.clone());
+        self.emit(kind, &offer_id, &[&offer.creator_id, &outbid_id], Some(price));
+        self.pay_referral(&offer.creator_id, rake);
+        Ok(offer)
     }
 
-    #[query]
-    fn get_chunk_size(&self) -> u32 {
-        self.server.get_chunk_size()
+    // pairs the longest waiting players of each stake size into private
+    // hands; an opener who can no longer cover the stake leaves the queue
+    // and their opponent waits on
+    fn pair_queued_players(&mut self) -> Vec<Hand> {
+        let mut waiting: Vec<QueueEntry> = Vec::new();
+        let mut hands = Vec::new();
+        for entry in std::mem::take(&mut self.match_queue) {
+            let Some(pos) = waiting.iter().position(|w| w.stake_size == entry.stake_size) else {
+                waiting.push(entry);
+                continue;
+            };
+            let opener = waiting.remove(pos);
+            match self.open_matched_hand(&opener, &entry.user_id) {
+                Ok(hand) => hands.push(hand),
+                Err(_) => waiting.push(entry),
+            }
+        }
+        self.match_queue = waiting;
+        hands
+    }
+
+    fn open_matched_hand(
+        &mut self,
+        opener: &QueueEntry,
+        opponent_id: &str,
+    ) -> Result<Hand, String> {
+        let user = self
+            .users
+            .get(&opener.user_id)
+            .ok_or_else(|| "user not registered anymore".to_string())?;
+        let (claim, count, cards) = matchmaking::opening_stake(&user.cards, opener.stake_size)
+            .ok_or_else(|| "not enough cards for the opening stake".to_string())?;
+        let options = opener.hand_options(opponent_id, MATCH_HAND_BLOCKS);
+        let hand = self.open_hand_as(opener.user_id.clone(), claim, count, cards, options)?;
+        let players = [opener.user_id.as_str(), opponent_id];
+        self.emit(EventKind::PlayersMatched, &hand.hand_id, &players, None);
+        Ok(hand)
     }
-}
 
+    // runs one batched call as the caller
+    async fn run_batch_action(&mut self, action: BatchAction) -> Result<BatchResult, String> {
+        match action {
+            BatchAction::StartHand(a) => self
+                .start_hand(a.claim, a.claimed_count, a.cards, a.options)
+                .await
+                .map(BatchResult::Hand),
+            BatchAction::Stake(a) => self
+                .stake(a.hand_id, a.cards, a.claimed_count)
+                .await
+                .map(BatchResult::Hand),
+            BatchAction::Check(hand_id) => self.check(hand_id).await.map(BatchResult::Checked),
+            BatchAction::Offer(a) => self
+                .offer(a.cards, a.amount, a.duration_blocks, None, a.buyout_price, None)
+                .await
+                .map(BatchResult::Offer),
+            BatchAction::Bid(a) => self.bid(a.offer_id, a.amount).await.map(|_| BatchResult::Done),
+            BatchAction::BuyNow(offer_id) => self.buy_now(offer_id).await.map(BatchResult::Offer),
+            BatchAction::Resolve(offer_id) => {
+                self.resolve(offer_id).await.map(|_| BatchResult::Done)
+            }
+        }
+    }
+
+    // the sender and recipient of a gift, both registered and distinct
+    fn gift_parties(&self, sender: &String, to_user_id: &String) -> Result<(User, User), String> {
+        if sender == to_user_id {
+            return Err("cannot gift to yourself".to_string());
+        }
+        let giver = self
+            .users
+            .get(sender)
+            .ok_or_else(|| "user not registered".to_string())?;
+        let recipient = self
+            .users
+            .get(to_user_id)
+            .ok_or_else(|| "recipient not registered".to_string())?;
+        Ok((giver, recipient))
+    }
+
+    // an unopened, unlisted pack held by `owner_id`
+    fn owned_pack(&self, pack_id: &String, owner_id: &str) -> Result<Pack, String> {
+        let pack = self
+            .packs
+            .get(pack_id)
+            .ok_or_else(|| "pack not found".to_string())?;
+        if pack.owner_id != owner_id {
+            return Err("pack belongs to another user".to_string());
+        }
+        if pack.is_opened {
+            return Err("pack already opened".to_string());
+        }
+        if pack.is_listed {
+            return Err("pack is listed on an offer".to_string());
+        }
+        Ok(pack)
+    }
+
+    // hands the pack to `to` (possibly its current owner) and takes it off the market
+    fn transfer_pack(&mut self, mut pack: Pack, to: &str) {
+        let mut from_packs = self.user_packs.get(&pack.owner_id).unwrap_or_default();
+        from_packs.retain(|id| *id != pack.pack_id);
+        self.user_packs.insert(pack.owner_id.clone(), from_packs);
+
+        let mut to_packs = self.user_packs.get(&to.to_string()).unwrap_or_default();
+        to_packs.push(pack.pack_id.clone());
+        self.user_packs.insert(to.to_string(), to_packs);
+
+        pack.owner_id = to.to_string();
+        pack.is_listed = false;
+        self.packs.insert(pack.pack_id.clone(), pack);
+    }
+
+    // delivers the pack an offer was selling, if any, once it settles
+    fn settle_offer_pack(&mut self, offer: &Offer, to: &str) {
+        if let Some(pack_id) = &offer.pack_id
+            && let Some(pack) = self.packs.get(pack_id)
+        {
+            self.transfer_pack(pack, to);
+        }
+    }
+
+    // once the reveal window of a sealed-bid offer has closed, the deposits
+    // of bids that were never revealed go to the treasury
+    fn forfeit_unrevealed_bids(&mut self, offer_id: &String) {
+        let mut bids = self.sealed_bids.get(offer_id).unwrap_or_default();
+        let mut forfeited = Vec::new();
+        for bid in bids.iter_mut().filter(|b| b.revealed_amount.is_none() && b.deposit > 0) {
+            if let Some(mut bidder) = self.users.get(&bid.bidder_id) {
+                bidder.settle(bid.deposit);
+                self.users.insert(bid.bidder_id.clone(), bidder);
+                self.treasury += bid.deposit;
+            }
+            forfeited.push((bid.bidder_id.clone(), bid.deposit));
+            bid.deposit = 0;
+        }
+        if forfeited.is_empty() {
+            return;
+        }
+
+        self.sealed_bids.insert(offer_id.clone(), bids);
+        for (bidder_id, deposit) in forfeited {
+            self.emit(EventKind::SealedBidForfeited, offer_id, &[&bidder_id], Some(deposit));
+        }
+    }
+
+    // records a paid-for draw of `count` cards to be revealed in a later block
+    fn commit_draw(
+        &mut self,
+        user_id: &String,
+        count: u32,
+        commitment: String,
+    ) -> Result<PendingDraw, String> {
+        if commitment.len() != 64 {
+            return Err("commitment must be a hex encoded sha256 of a secret salt".to_string());
+        }
+        // cards still to be revealed already count against the cap
+        let draws = self.pending_draws.get(user_id).unwrap_or_default();
+        let pending: u32 = draws.iter().map(|d| d.count).sum();
+        if let Some(user) = self.users.get(user_id) {
+            self.ensure_inventory_room(&user, (count + pending) as usize)?;
+        }
+
+        self.deck.reserve(count as u64)?;
+        let height = env::block_height();
+        self.mix_entropy(&[user_id.as_bytes(), commitment.as_bytes(), &height.to_le_bytes()]);
+
+        let draw = PendingDraw {
+            draw_id: self.next_draw_id(),
+            user_id: user_id.clone(),
+            count,
+            commitment,
+            committed_at_block: height,
+            seed_pool: None,
+            reveal_by_block: height.saturating_add(DRAW_REVEAL_BLOCKS),
+        };
+
+        let mut draws = self.pending_draws.get(user_id).unwrap_or_default();
+        draws.push(draw.clone());
+        self.pending_draws.insert(user_id.clone(), draws);
+        if !self.draws_awaiting_seed.contains(user_id) {
+            self.draws_awaiting_seed.push(user_id.clone());
+        }
+        Ok(draw)
+    }
+
+    // every write to the entropy pool goes through here: the first mix of a
+    // block first freezes the seed pool of each draw committed two or more
+    // blocks ago, which is the pool as the block after its commit left it
+    fn mix_entropy(&mut self, parts: &[&[u8]]) {
+        let height = env::block_height();
+        if self.entropy.is_new_block(height) {
+            self.seed_waiting_draws(height);
+        }
+        self.entropy.mix(height, parts);
+    }
+
+    fn seed_waiting_draws(&mut self, height: u64) {
+        let snapshot = self.entropy.snapshot();
+        let mut waiting = Vec::new();
+        for user_id in std::mem::take(&mut self.draws_awaiting_seed) {
+            let mut draws = self.pending_draws.get(&user_id).unwrap_or_default();
+            for draw in draws.iter_mut().filter(|d| d.seed_pool.is_none()) {
+                if draw.committed_at_block + 1 < height {
+                    draw.seed_pool = Some(snapshot.clone());
+                }
+            }
+            if draws.iter().any(|d| d.seed_pool.is_none()) {
+                waiting.push(user_id.clone());
+            }
+            self.pending_draws.insert(user_id, draws);
+        }
+        self.draws_awaiting_seed = waiting;
+    }
+
+    // appends to the event log and indexes the entry under every user it
+    // concerns; the caller is always one of them
+    fn emit(&mut self, kind: EventKind, subject_id: &str, others: &[&str], amount: Option<u64>) {
+        let actor = env::sender();
+        // only the caller acted; everyone else the event names was acted upon
+        self.last_active_blocks.insert(actor.clone(), env::block_height());
+        let mut users = vec![actor.clone()];
+        for user in others {
+            if !user.is_empty() && !users.iter().any(|u| u == user) {
+                users.push(user.to_string());
+            }
+        }
+
+        let seq = self.events.len() as u64 + 1;
+        for user in &users {
+            self.record_user_event(user, seq);
+        }
+
+        let event = Event {
+            seq,
+            block_height: env::block_height(),
+            kind,
+            actor,
+            subject_id: subject_id.to_string(),
+            users,
+            amount,
+        };
+        self.recent_events.push(event.clone(), RECENT_EVENTS);
+        self.events.push(event);
+    }
+
+    fn latest_claim_totals(&self) -> ClaimTotals {
+        match self.claim_totals.len() {
+            0 => ClaimTotals::default(),
+            len => self.claim_totals.get(len - 1).unwrap_or_default(),
+        }
+    }
+
+    // claims of each rank over the last `CLAIM_DIVERSITY_WINDOW` started hands
+    fn recent_claims(&self) -> Vec<u64> {
+        let latest = self.latest_claim_totals();
+        // snapshot i holds the totals after i + 1 hands
+        let older = match latest.hands.saturating_sub(CLAIM_DIVERSITY_WINDOW) {
+            0 => ClaimTotals::default(),
+            n => self.claim_totals.get(n as usize - 1).unwrap_or_default(),
+        };
+        latest.since(&older)
+    }
+
+    fn claim_bonus_percent(&self, claim: Card) -> u64 {
+        payouts::claim_diversity_bonus_percent(
+            &self.recent_claims(),
+            claim,
+            MAX_CLAIM_DIVERSITY_BONUS_PERCENT,
+            CLAIM_DIVERSITY_MIN_HANDS,
+        )
+    }
+
+    fn latest_resolution_totals(&self) -> ResolutionTotals {
+        match self.resolution_totals.len() {
+            0 => ResolutionTotals::default(),
+            len => self.resolution_totals.get(len - 1).unwrap_or_default(),
+        }
+    }
+
+    // everyone with a stake or a side bet riding on the hand
+    fn hand_participants(&self, hand: &Hand) -> Vec<String> {
+        let mut users: Vec<String> = hand.stakes.iter().map(|s| s.user_id.clone()).collect();
+        users.extend(hand.turn_order.iter().cloned());
+        if let Some(team) = self.team_hands.get(&hand.hand_id) {
+            users.extend(team.team_a);
+            users.extend(team.team_b);
+        }
+        for bet in self.side_bets.get(&hand.hand_id).unwrap_or_default() {
+            users.push(bet.proposer_id);
+            users.push(bet.opponent_id);
+        }
+        if let Some(pool) = self.spectator_pools.get(&hand.hand_id) {
+            users.extend(pool.wagers.into_iter().map(|w| w.user_id));
+        }
+        users
+    }
+
+    // the creator, stakers, seated players and team members, who may not
+    // wager in the spectator pool
+    fn is_player(&self, hand: &Hand, user_id: &String) -> bool {
+        hand.creator == *user_id
+            || hand.stakes.iter().any(|s| s.user_id == *user_id)
+            || hand.turn_order.contains(user_id)
+            || self.team_hands.get(&hand.hand_id).is_some_and(|t| t.team_of(user_id).is_some())
+    }
+
+    // spectators who wagered on a hand cannot start playing in it
+    fn ensure_not_spectating(&self, hand_id: &String, user_id: &str) -> Result<(), String> {
+        if self.spectator_pools.get(hand_id).is_some_and(|p| p.has_wager_from(user_id)) {
+            return Err("spectators who wagered on a hand cannot play in it".to_string());
+        }
+        Ok(())
+    }
+
+    fn mask_hand_for_view(hand: &Hand) -> Hand {
+        let mut masked = hand.clone();
+        masked.stakes = hand
+            .stakes
+            .iter()
+            .map(|s| Stake {
+                user_id: s.user_id.clone(),
+                // preserve count and order, hide card identities
+                cards: vec![Card::JOKER; s.cards.len()],
+                claimed_count: s.claimed_count,
+                seq: s.seq,
+                staked_at_block: s.staked_at_block,
+            })
+            .collect();
+        masked
+    }
+}
+
+#[derive(Serialize, Deserialize, WeilType)]
+pub struct EscalateContractState {
+    users: WeilMap<String, User>,
+    user_ids: WeilVec<String>,
+    hands: WeilMap<String, Hand>,
+    hand_ids: WeilVec<String>,
+    offers: WeilMap<String, Offer>,
+    offer_ids: WeilVec<String>,
+    // ids of the offers each user listed, oldest first
+    user_offers: WeilMap<String, Vec<String>>,
+    // ids of the offers each user buys or sells in installments
+    user_installment_plans: WeilMap<String, Vec<String>>,
+    hand_counter: u64,
+    offer_counter: u64,
+    server: WebServer,
+    weil_id_generator: WeilIdGenerator,
+    // side bets keyed by the hand they wager on
+    side_bets: WeilMap<String, Vec<SideBet>>,
+    side_bet_hand_ids: WeilMap<String, String>,
+    side_bet_counter: u64,
+    // parimutuel wagers of non-players keyed by hand id
+    spectator_pools: WeilMap<String, SpectatorPool>,
+    // table talk of every hand in posting order, with the indexes of each
+    // hand's messages keyed by hand id
+    hand_messages: WeilVec<HandMessage>,
+    hand_message_ids: WeilMap<String, Vec<u64>>,
+    // players each hand's creator silenced at its table, keyed by hand id
+    hand_mutes: WeilMap<String, Vec<String>>,
+    // admin appointed users who may flag table talk
+    moderators: Vec<String>,
+    // payout lines of every checked hand, for the outcomes in activity history
+    check_payouts: WeilMap<String, Vec<PayoutLine>>,
+    // shortfalls from penalties that would have breached the balance floor
+    debts: WeilMap<String, u64>,
+    admin: String,
+    // while set, every entrypoint but the admin ones is rejected
+    paused: bool,
+    // a softer pause the admin schedules ahead of time, queries keep working
+    maintenance: Option<MaintenanceWindow>,
+    params: GameParams,
+    successor: Option<Succession>,
+    // fees, card sales and collected penalties, which fund check rewards
+    treasury: u64,
+    // chips issued to players less what left through withdrawals; always
+    // equal to every balance, escrow and the treasury added up
+    total_supply: u64,
+    identities: WeilMap<String, IdentityProfile>,
+    // admin managed allowlist of region/language codes hands can be tagged with
+    region_tags: Vec<String>,
+    entropy: EntropyPool,
+    deck: Deck,
+    // unrevealed card draws keyed by the user who paid for them
+    pending_draws: WeilMap<String, Vec<PendingDraw>>,
+    // users with a draw whose seed pool is not frozen yet
+    draws_awaiting_seed: Vec<String>,
+    draw_counter: u64,
+    // append-only log of every state transition, `seq` is the index plus one
+    events: WeilVec<Event>,
+    // seqs of the events each user appears in, oldest first, in pages of
+    // `USER_EVENT_PAGE_SIZE` keyed by `user_event_page_key`
+    user_event_pages: WeilMap<String, Vec<u64>>,
+    // how many events each user appears in
+    user_event_counts: WeilMap<String, u64>,
+    // copies of the latest events for `get_recent_activity`
+    recent_events: RecentEvents,
+    // sold offers still being paid off, keyed by offer id
+    installment_plans: WeilMap<String, InstallmentPlan>,
+    // outgoing transfers keyed by intent id, with the ids each user requested
+    transfer_intents: WeilMap<String, TransferIntent>,
+    user_transfers: WeilMap<String, Vec<String>>,
+    transfer_counter: u64,
+    // commitments on sealed-bid offers keyed by offer id
+    sealed_bids: WeilMap<String, Vec<SealedBid>>,
+    packs: WeilMap<String, Pack>,
+    // ids of the packs each user owns, opened ones are dropped
+    user_packs: WeilMap<String, Vec<String>>,
+    pack_counter: u64,
+    // running totals snapshot taken after every check, oldest first
+    resolution_totals: WeilVec<ResolutionTotals>,
+    // running claim counts snapshot taken after every started hand, oldest first
+    claim_totals: WeilVec<ClaimTotals>,
+    // the latest successful `with_idempotency` calls of each user, oldest first
+    idempotent_calls: WeilMap<String, Vec<IdempotentCall>>,
+    // every step of each hand in the order it happened, unmasked
+    hand_actions: WeilMap<String, Vec<HandAction>>,
+    // achievements each user unlocked, in the order they did
+    user_achievements: WeilMap<String, Vec<UnlockedAchievement>>,
+    // the users each referrer brought in and what each of them earned the referrer
+    referral_earnings: WeilMap<String, Vec<ReferralEarning>>,
+    user_stats: WeilMap<String, UserStats>,
+    // ids of the hands each user started, staked on, checked or plays in
+    user_hands: WeilMap<String, Vec<String>>,
+    // ids of the hands still open, in no particular order; only the first
+    // `open_hand_count` slots are live
+    open_hand_ids: WeilVec<String>,
+    // slot of each open hand in `open_hand_ids`
+    open_hand_slots: WeilMap<String, u64>,
+    open_hand_count: u64,
+    // ids of the hands each user started, oldest first
+    hands_by_creator: WeilMap<String, Vec<String>>,
+    // team assignment and check confirmations of 2v2 hands, keyed by hand id
+    team_hands: WeilMap<String, TeamHand>,
+    // offers that paid to be listed first, pruned whenever one is boosted
+    boosted_offer_ids: Vec<String>,
+    trades: WeilMap<String, Trade>,
+    // ids of the trades each user proposed or was asked for
+    user_trades: WeilMap<String, Vec<String>>,
+    trade_counter: u64,
+    auto_bid_rules: WeilMap<String, AutoBidRule>,
+    // ids of the auto-bid rules watching each rank, keyed by the rank's name
+    card_auto_bids: WeilMap<String, Vec<String>>,
+    // ids of the auto-bid rules each user created
+    user_auto_bids: WeilMap<String, Vec<String>>,
+    auto_bid_counter: u64,
+    tournaments: WeilMap<String, Tournament>,
+    // ids of the tournaments each user created or joined
+    user_tournaments: WeilMap<String, Vec<String>>,
+    tournament_counter: u64,
+    seasons: WeilMap<String, Season>,
+    // oldest first, the last one is still running while `current_season` is set
+    season_ids: Vec<String>,
+    current_season: Option<String>,
+    season_counter: u64,
+    // each player's stats within a season, keyed by `season::stats_key`,
+    // dropped once the season ended and was archived
+    season_stats: WeilMap<String, UserStats>,
+    season_archives: WeilMap<String, SeasonArchive>,
+    // users each user added as a friend, who they can challenge
+    friends: WeilMap<String, Vec<String>>,
+    challenges: WeilMap<String, Challenge>,
+    // ids of the challenges each user issued or received
+    user_challenges: WeilMap<String, Vec<String>>,
+    challenge_counter: u64,
+    // players waiting for an opponent, longest waiting first
+    match_queue: Vec<QueueEntry>,
+    recovery_guardians: WeilMap<String, RecoveryGuardians>,
+    // the latest recovery of each account, keyed by the account being recovered
+    recoveries: WeilMap<String, RecoveryRequest>,
+    // admin provided error templates keyed by `<code>/<locale>`
+    message_templates: WeilMap<String, String>,
+    // the block of each user's latest call that emitted an event, the clock
+    // `decay_inventory` runs against
+    last_active_blocks: WeilMap<String, u64>,
+    // cards ever drawn per rank, indexed by token id
+    card_supply: Vec<u64>,
+    // recent sales of each rank, indexed by token id
+    sale_prices: Vec<Vec<SalePrice>>,
+    // operators each owner lets move their cards through `transfer_from`
+    card_operators: WeilMap<String, Vec<String>>,
+}
+
+#[smart_contract]
+impl Escalate for EscalateContractState {
+    #[constructor]
+    fn new() -> Result<Self, String>
+    where
+        Self: Sized,
+    {
+        Ok(
+            Self {
+                users: WeilMap::new(WeilId(1)),
+                user_ids: WeilVec::new(WeilId(2)),
+                hands: WeilMap::new(WeilId(3)),
+                hand_ids: WeilVec::new(WeilId(4)),
+                offers: WeilMap::new(WeilId(5)),
+                offer_ids: WeilVec::new(WeilId(6)),
+                hand_counter: 0,
+                offer_counter: 0,
+                server: WebServer::new(WeilId(7), None),
+                // upload ids count up from here, keep it above every
+                // collection id given out in this constructor
+                weil_id_generator: WeilIdGenerator::new(WeilId(1000)),
+                side_bets: WeilMap::new(WeilId(9)),
+                side_bet_hand_ids: WeilMap::new(WeilId(10)),
+                side_bet_counter: 0,
+                spectator_pools: WeilMap::new(WeilId(30)),
+                check_payouts: WeilMap::new(WeilId(31)),
+                debts: WeilMap::new(WeilId(11)),
+                admin: env::sender(),
+                paused: false,
+                maintenance: None,
+                params: GameParams::default(),
+                successor: None,
+                treasury: 0,
+                total_supply: 0,
+                identities: WeilMap::new(WeilId(12)),
+                region_tags: Vec::new(),
+                entropy: EntropyPool::default(),
+                deck: Deck::standard(STANDARD_DECKS),
+                pending_draws: WeilMap::new(WeilId(13)),
+                draws_awaiting_seed: Vec::new(),
+                draw_counter: 0,
+                events: WeilVec::new(WeilId(14)),
+                user_event_pages: WeilMap::new(WeilId(57)),
+                user_event_counts: WeilMap::new(WeilId(58)),
+                recent_events: RecentEvents::default(),
+                installment_plans: WeilMap::new(WeilId(16)),
+                transfer_intents: WeilMap::new(WeilId(17)),
+                user_transfers: WeilMap::new(WeilId(24)),
+                transfer_counter: 0,
+                resolution_totals: WeilVec::new(WeilId(18)),
+                sealed_bids: WeilMap::new(WeilId(19)),
+                packs: WeilMap::new(WeilId(20)),
+                user_packs: WeilMap::new(WeilId(21)),
+                pack_counter: 0,
+                team_hands: WeilMap::new(WeilId(22)),
+                user_hands: WeilMap::new(WeilId(27)),
+                user_stats: WeilMap::new(WeilId(28)),
+                message_templates: WeilMap::new(WeilId(23)),
+                boosted_offer_ids: Vec::new(),
+                trades: WeilMap::new(WeilId(25)),
+                user_trades: WeilMap::new(WeilId(26)),
+                trade_counter: 0,
+                auto_bid_rules: WeilMap::new(WeilId(32)),
+                card_auto_bids: WeilMap::new(WeilId(33)),
+                user_auto_bids: WeilMap::new(WeilId(34)),
+                auto_bid_counter: 0,
+                tournaments: WeilMap::new(WeilId(35)),
+                user_tournaments: WeilMap::new(WeilId(59)),
+                hand_messages: WeilVec::new(WeilId(36)),
+                hand_message_ids: WeilMap::new(WeilId(37)),
+                hand_mutes: WeilMap::new(WeilId(53)),
+                moderators: Vec::new(),
+                tournament_counter: 0,
+                seasons: WeilMap::new(WeilId(49)),
+                season_ids: Vec::new(),
+                current_season: None,
+                season_counter: 0,
+                season_stats: WeilMap::new(WeilId(50)),
+                season_archives: WeilMap::new(WeilId(54)),
+                user_offers: WeilMap::new(WeilId(51)),
+                user_installment_plans: WeilMap::new(WeilId(60)),
+                last_active_blocks: WeilMap::new(WeilId(52)),
+                friends: WeilMap::new(WeilId(38)),
+                challenges: WeilMap::new(WeilId(39)),
+                user_challenges: WeilMap::new(WeilId(40)),
+                challenge_counter: 0,
+                match_queue: Vec::new(),
+                open_hand_ids: WeilVec::new(WeilId(55)),
+                open_hand_slots: WeilMap::new(WeilId(56)),
+                open_hand_count: 0,
+                hands_by_creator: WeilMap::new(WeilId(41)),
+                recovery_guardians: WeilMap::new(WeilId(42)),
+                recoveries: WeilMap::new(WeilId(43)),
+                claim_totals: WeilVec::new(WeilId(44)),
+                idempotent_calls: WeilMap::new(WeilId(45)),
+                hand_actions: WeilMap::new(WeilId(46)),
+                user_achievements: WeilMap::new(WeilId(47)),
+                referral_earnings: WeilMap::new(WeilId(48)),
+                card_supply: vec![0; Card::ALL.len()],
+                sale_prices: vec![Vec::new(); Card::ALL.len()],
+                card_operators: WeilMap::new(WeilId(29)),
+            }
+        )
+    }
+
+
+    #[mutate]
+    async fn register_user(&mut self, bio: String) -> Result<User, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+
+        if let Some(mut existing) = self.users.get(&sender) {
+            existing.bio = bio;
+            self.users.insert(sender.clone(), existing.clone());
+            self.emit(EventKind::ProfileUpdated, &sender, &[], None);
+            return Ok(existing);
+        }
+
+        self.ensure_creation_allowed()?;
+
+        let balance = self.params.starting_balance;
+        let mut user = User::new(sender.clone(), bio, env::block_height(), balance);
+        self.total_supply += balance;
+        self.grant_starter_cards(&mut user);
+        self.users.insert(sender.clone(), user.clone());
+        self.user_ids.push(sender.clone());
+        self.emit(EventKind::UserRegistered, &sender, &[], None);
+        Ok(user)
+    }
+
+    #[mutate]
+    async fn register_user_with_referrer(
+        &mut self,
+        bio: String,
+        referrer_id: String,
+    ) -> Result<User, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        if self.users.get(&sender).is_some() {
+            return Err("referrers can only be named when registering".to_string());
+        }
+        if referrer_id == sender {
+            return Err("users cannot refer themselves".to_string());
+        }
+        if self.users.get(&referrer_id).is_none() {
+            return Err("referrer not registered".to_string());
+        }
+
+        let mut user = self.register_user(bio).await?;
+        user.referrer = Some(referrer_id.clone());
+        self.users.insert(sender.clone(), user.clone());
+        let mut referees = self.referral_earnings.get(&referrer_id).unwrap_or_default();
+        referees.push(ReferralEarning {
+            referee_id: sender.clone(),
+            earned: 0,
+            ends_at_block: user.registered_at_block.saturating_add(self.params.referral_blocks),
+        });
+        self.referral_earnings.insert(referrer_id, referees);
+        self.update_current_season(|s| s.referees.push(sender));
+        Ok(user)
+    }
+
+    #[query]
+    async fn get_referral_earnings(&self) -> Vec<ReferralEarning> {
+        self.referral_earnings.get(&env::sender()).unwrap_or_default()
+    }
+
+    // the running season's referral leaderboard, empty between seasons
+    #[query]
+    async fn get_referral_standings(&self) -> Vec<ReferralStanding> {
+        self.current_season
+            .as_ref()
+            .and_then(|id| self.seasons.get(id))
+            .map(|season| self.referral_standings(&season))
+            .unwrap_or_default()
+    }
+
+    #[query]
+    async fn get_users(&self) -> Vec<User> {
+        self.users_page(0, DEFAULT_PAGE_LIMIT).items
+    }
+
+    #[query]
+    async fn get_users_page(&self, offset: u64, limit: u64) -> UserPage {
+        self.users_page(offset, limit)
+    }
+
+    #[query]
+    async fn get_user(&self, id: String) -> Option<User> {
+        self.users.get(&id)
+    }
+
+    #[query]
+    async fn get_my_escrow(&self) -> Vec<EscrowedCards> {
+        self.escrowed_cards(&env::sender())
+    }
+
+    #[query]
+    async fn get_my_cards(&self, locale: Option<String>) -> Result<Vec<Card>, String> {
+        let sender = env::sender();
+        match self.users.get(&sender) {
+            Some(u) => Ok(u.cards.clone()),
+            None => Err(self.localize(Message::new(MessageCode::UserNotRegistered), &locale)),
+        }
+    }
+
+    #[query]
+    async fn get_my_debt(&self, locale: Option<String>) -> Result<u64, String> {
+        let sender = env::sender();
+        if self.users.get(&sender).is_none() {
+            return Err(self.localize(Message::new(MessageCode::UserNotRegistered), &locale));
+        }
+        Ok(self.debts.get(&sender).unwrap_or(0))
+    }
+
+    #[query]
+    async fn get_balance_breakdown(
+        &self,
+        locale: Option<String>,
+    ) -> Result<BalanceBreakdown, String> {
+        let sender = env::sender();
+        let user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| self.localize(Message::new(MessageCode::UserNotRegistered), &locale))?;
+        Ok(BalanceBreakdown {
+            available: user.balance,
+            locked: user.locked_balance,
+            debt: self.debts.get(&sender).unwrap_or(0),
+        })
+    }
+
+    #[mutate]
+    async fn start_hand(
+        &mut self,
+        claim: Card,
+        claimed_count: u32,
+        cards: Vec<Card>,
+        options: HandOptions,
+    ) -> Result<Hand, String> {
+        self.ensure_not_paused()?;
+        self.open_hand(claim, claimed_count, cards, options)
+    }
+
+    // opens a new hand with the settings and invite list of `hand_id`,
+    // the caller placing the opening stake
+    #[mutate]
+    async fn rematch(
+        &mut self,
+        hand_id: String,
+        claim: Card,
+        claimed_count: u32,
+        cards: Vec<Card>,
+    ) -> Result<Hand, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let original = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found".to_string())?;
+
+        let played = original.stakes.iter().any(|s| s.user_id == sender)
+            || original.turn_order.contains(&sender);
+        if !played {
+            return Err("only players of the original hand can start a rematch".to_string());
+        }
+        if self.team_hands.get(&hand_id).is_some() {
+            return Err("team hands cannot be rematched, start a new team hand".to_string());
+        }
+
+        // the original creator takes the caller's place on the invite list
+        let mut options = original.options();
+        if !options.allowed_stakers.is_empty() {
+            options.allowed_stakers.retain(|u| *u != sender);
+            if original.creator != sender {
+                options.allowed_stakers.push(original.creator.clone());
+            }
+        }
+
+        let hand = self.open_hand(claim, claimed_count, cards, options)?;
+        let players = self.hand_participants(&original);
+        let players: Vec<&str> = players.iter().map(String::as_str).collect();
+        self.emit(EventKind::RematchStarted, &hand.hand_id, &players, None);
+        Ok(hand)
+    }
+
+    #[mutate]
+    async fn start_team_hand(
+        &mut self,
+        claim: Card,
+        claimed_count: u32,
+        cards: Vec<Card>,
+        options: HandOptions,
+        teams: TeamOptions,
+    ) -> Result<Hand, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        if options.newcomers_only {
+            return Err("team hands cannot be played in the newcomer bracket".to_string());
+        }
+        if options.turn_based {
+            return Err("team hands already alternate between the teams".to_string());
+        }
+        if !options.allowed_stakers.is_empty() {
+            return Err("team hands are already limited to their teams".to_string());
+        }
+        if teams.opponents.len() != team::TEAM_SIZE {
+            return Err(format!("a team hand needs {} opponents", team::TEAM_SIZE));
+        }
+        if teams.confirm_window_blocks == 0 {
+            return Err("the check confirmation window must be at least one block".to_string());
+        }
+        let mut players = vec![&sender, &teams.teammate];
+        players.extend(teams.opponents.iter());
+        for (i, player) in players.iter().enumerate() {
+            if players[..i].contains(player) {
+                return Err(format!("{} cannot be assigned twice", player));
+            }
+            if self.users.get(player).is_none() {
+                return Err(format!("{} is not a registered user", player));
+            }
+        }
+
+        let hand = self.open_hand(claim, claimed_count, cards, options)?;
+        let team = TeamHand::new(hand.hand_id.clone(), sender, teams);
+        for player in team.team_a.iter().chain(&team.team_b) {
+            self.record_participation(player, &hand.hand_id);
+        }
+        self.team_hands.insert(hand.hand_id.clone(), team);
+        Ok(hand)
+    }
+
+    #[query]
+    async fn get_team_hand(&self, hand_id: String) -> Option<TeamHand> {
+        self.team_hands.get(&hand_id)
+    }
+
+    #[query]
+    async fn get_hands(&self) -> Vec<Hand> {
+        self.hands_page(0, DEFAULT_PAGE_LIMIT).items
+    }
+
+    #[query]
+    async fn get_hands_page(&self, offset: u64, limit: u64) -> HandPage {
+        self.hands_page(offset, limit)
+    }
+
+    #[query]
+    async fn get_hands_by_region(&self, region_tag: String) -> Vec<Hand> {
+        self.hand_ids
+            .iter()
+            .filter_map(|id| self.hands.get(&id))
+            .filter(|h| h.region_tag.as_deref() == Some(region_tag.as_str()))
+            .map(|h| EscalateContractState::mask_hand_for_view(&h))
+            .collect()
+    }
+
+    // walks the open hand index newest first, so a lobby's first page shows
+    // the latest hands
+    #[query]
+    async fn get_open_hands(&self, filter: HandFilter) -> HandPage {
+        let height = env::block_height();
+        let caller = env::sender();
+        let hands: Vec<Hand> = self
+            .open_hands_in_start_order()
+            .iter()
+            .rev()
+            .filter_map(|id| self.hands.get(id))
+            .filter(|h| h.admits(&caller) && filter.matches(h, height))
+            .collect();
+        PageResult {
+            total_count: hands.len() as u64,
+            items: hands
+                .iter()
+                .skip(filter.offset as usize)
+                .take(filter.limit.min(MAX_PAGE_LIMIT) as usize)
+                .map(EscalateContractState::mask_hand_for_view)
+                .collect(),
+        }
+    }
+
+    // narrows the candidates down through the creator or open hand indexes
+    // before filtering, instead of walking every hand
+    #[query]
+    async fn search_hands(&self, query: HandQuery) -> HandPage {
+        let caller = env::sender();
+        let candidates: Vec<String> = match (&query.creator, query.status) {
+            (Some(creator), _) => self.hands_by_creator.get(creator).unwrap_or_default(),
+            (None, Some(HandStatus::Open)) => self.open_hands_in_start_order(),
+            (None, _) => self.hand_ids.iter().collect(),
+        };
+        let mut hands: Vec<Hand> = candidates
+            .iter()
+            .filter_map(|id| self.hands.get(id))
+            .filter(|h| h.admits(&caller) && query.matches(h))
+            .collect();
+        query.sort(&mut hands);
+        PageResult {
+            total_count: hands.len() as u64,
+            items: hands
+                .iter()
+                .skip(query.offset as usize)
+                .take(query.limit.min(MAX_PAGE_LIMIT) as usize)
+                .map(EscalateContractState::mask_hand_for_view)
+                .collect(),
+        }
+    }
+
+    #[query]
+    async fn get_hand(&self, id: String) -> Option<Hand> {
+        self.hands
+            .get(&id)
+            .map(|h| EscalateContractState::mask_hand_for_view(&h))
+    }
+
+    // answers right away; the masked hand is only sent back when it changed
+    // since `known_seq`, so clients can poll often without re-fetching it
+    #[query]
+    async fn wait_changed(&self, hand_id: String, known_seq: u64) -> Result<HandUpdate, String> {
+        let hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found".to_string())?;
+        let changed = hand.update_seq != known_seq;
+        Ok(HandUpdate {
+            hand_id,
+            update_seq: hand.update_seq,
+            changed,
+            hand: changed.then(|| EscalateContractState::mask_hand_for_view(&hand)),
+        })
+    }
+
+    #[query]
+    async fn get_hands_by_participant(
+        &self,
+        user_id: String,
+        status_filter: Option<HandStatus>,
+        offset: u64,
+        limit: u64,
+    ) -> HandPage {
+        self.participant_hands_page(&user_id, status_filter, offset, limit)
+    }
+
+    #[mutate]
+    async fn buy_cards(&mut self, amount: u64, commitment: String) -> Result<PendingDraw, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before buying cards".to_string())?;
+
+        // only whole cards are sold, any remainder stays with the buyer
+        let count = amount / self.params.card_price;
+        let spend = count * self.params.card_price;
+        if count == 0 {
+            return Err("must buy at least one card".to_string());
+        }
+        if user.balance < spend {
+            return Err("insufficient balance".to_string());
+        }
+
+        let draw = self.commit_draw(&sender, count as u32, commitment)?;
+        user.balance -= spend;
+        self.treasury += spend;
+
+        self.users.insert(sender.clone(), user);
+        self.emit(EventKind::CardsBought, &draw.draw_id, &[], Some(spend));
+        self.pay_referral(&sender, spend);
+        Ok(draw)
+    }
+
+    // a free draw of a few cards, at most once per claim window; the cards
+    // are revealed like a purchase so the claimer cannot pick them
+    #[mutate]
+    async fn claim_daily_cards(&mut self, commitment: String) -> Result<PendingDraw, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before claiming cards".to_string())?;
+
+        let count = self.params.daily_claim_cards;
+        if count == 0 {
+            return Err("daily card claims are disabled".to_string());
+        }
+        let height = env::block_height();
+        if !user.can_claim_daily(height, self.params.daily_claim_blocks) {
+            let next = user.last_claim_block.unwrap_or_default() + self.params.daily_claim_blocks;
+            return Err(format!("next daily claim is available at block {}", next));
+        }
+
+        let draw = self.commit_draw(&sender, count, commitment)?;
+        user.last_claim_block = Some(height);
+        self.users.insert(sender, user);
+        self.emit(EventKind::DailyCardsClaimed, &draw.draw_id, &[], None);
+        Ok(draw)
+    }
+
+    #[mutate]
+    async fn reveal_cards(&mut self, draw_id: String, salt: String) -> Result<Vec<Card>, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before revealing cards".to_string())?;
+
+        let draws = self.pending_draws.get(&sender).unwrap_or_default();
+        let idx = draws
+            .iter()
+            .position(|d| d.draw_id == draw_id)
+            .ok_or_else(|| "pending draw not found".to_string())?;
+
+        let height = env::block_height();
+        if height <= draws[idx].committed_at_block + 1 {
+            return Err("draw can only be revealed once the block after it has passed".to_string());
+        }
+        if height > draws[idx].reveal_by_block {
+            return Err("draw was not revealed in time and can only be forfeited".to_string());
+        }
+
+        if randomness::commitment_for(&salt) != draws[idx].commitment {
+            return Err("salt does not match the draw commitment".to_string());
+        }
+
+        // mixing the reveal in freezes this draw's seed pool if no call of
+        // this block has yet
+        self.mix_entropy(&[sender.as_bytes(), salt.as_bytes(), &height.to_le_bytes()]);
+        let mut draws = self.pending_draws.get(&sender).unwrap_or_default();
+        let draw = draws.remove(idx);
+        let seed_pool = draw.seed_pool.as_deref().expect("frozen before any later mix");
+        let seed = randomness::draw_seed(&salt, seed_pool);
+        let weights = self.params.rank_drop_weights();
+        let new_cards = self.deck.deal(&seed, draw.count, &weights);
+
+        user.add_cards(new_cards.clone());
+        token::record_minted(&mut self.card_supply, &new_cards);
+
+        self.users.insert(sender.clone(), user);
+        self.pending_draws.insert(sender, draws);
+        self.emit(EventKind::CardsRevealed, &draw_id, &[], None);
+        Ok(new_cards)
+    }
+
+    #[mutate]
+    async fn forfeit_expired_draws(&mut self, user_id: String) -> Result<u32, String> {
+        self.ensure_not_paused()?;
+        let draws = self.pending_draws.get(&user_id).unwrap_or_default();
+        let height = env::block_height();
+        let (expired, kept): (Vec<_>, Vec<_>) =
+            draws.into_iter().partition(|d| height > d.reveal_by_block);
+        if expired.is_empty() {
+            return Err("no draw of the user is past its reveal deadline".to_string());
+        }
+
+        // the draws were paid for, so the chips stay with the treasury
+        for draw in &expired {
+            self.deck.release(draw.count as u64);
+        }
+        self.pending_draws.insert(user_id.clone(), kept);
+        for draw in &expired {
+            self.emit(EventKind::DrawForfeited, &draw.draw_id, &[&user_id], None);
+        }
+        Ok(expired.len() as u32)
+    }
+
+    #[query]
+    async fn get_my_pending_draws(&self) -> Vec<PendingDraw> {
+        self.pending_draws.get(&env::sender()).unwrap_or_default()
+    }
+
+    #[query]
+    async fn get_deck(&self) -> Deck {
+        self.deck.clone()
+    }
+
+    #[query]
+    async fn get_drop_rates(&self) -> Vec<DropRate> {
+        self.deck.drop_rates(&self.params.rank_drop_weights())
+    }
+
+    // returns the cards to the deck for crafting points, answering with the
+    // caller's new point total
+    #[mutate]
+    async fn burn_cards(&mut self, cards: Vec<Card>) -> Result<u64, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user not registered".to_string())?;
+        if cards.is_empty() {
+            return Err("must burn at least one card".to_string());
+        }
+        Self::remove_cards_from_inventory(&mut user.cards, &cards)?;
+
+        let points = crafting::burn_value(&cards);
+        user.crafting_points += points;
+        self.deck.return_cards(&cards);
+        token::record_burned(&mut self.card_supply, &cards);
+        let total = user.crafting_points;
+        self.users.insert(sender.clone(), user);
+        self.emit(EventKind::CardsBurned, &sender, &[], Some(points));
+        Ok(total)
+    }
+
+    // the oldest cards go first, and the clock restarts so an idle
+    // inventory decays at most once per `decay_blocks`
+    #[mutate]
+    async fn decay_inventory(&mut self, user_id: String) -> Result<Vec<Card>, String> {
+        self.ensure_not_paused()?;
+        if self.params.decay_blocks == 0 {
+            return Err("card decay is disabled".to_string());
+        }
+        let mut user = self
+            .users
+            .get(&user_id)
+            .ok_or_else(|| "user not registered".to_string())?;
+        let height = env::block_height();
+        if user.is_locked(height) {
+            return Err("cards in cold storage do not decay".to_string());
+        }
+        if user.cards.is_empty() {
+            return Err("user holds no cards".to_string());
+        }
+        let decays_at = self.last_activity_block(&user).saturating_add(self.params.decay_blocks);
+        if height < decays_at {
+            return Err(format!("cards decay from block {}", decays_at));
+        }
+
+        let count = user.decaying_cards(self.params.decay_percent);
+        let decayed = user.take_oldest_cards(count);
+        self.deck.return_cards(&decayed);
+        token::record_burned(&mut self.card_supply, &decayed);
+        self.users.insert(user_id.clone(), user);
+        self.last_active_blocks.insert(user_id.clone(), height);
+        self.emit(EventKind::InventoryDecayed, &user_id, &[&user_id], Some(count as u64));
+        Ok(decayed)
+    }
+
+    // `points` is the most the caller is willing to spend, so a rate change
+    // between signing and inclusion cannot overcharge them
+    #[mutate]
+    async fn craft_card(&mut self, target: Card, points: u64) -> Result<User, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user not registered".to_string())?;
+
+        let cost = CraftingRate::for_card(target).craft_cost;
+        if cost > points {
+            return Err(format!("crafting {:?} costs {} points", target, cost));
+        }
+        if user.crafting_points < cost {
+            return Err("not enough crafting points".to_string());
+        }
+        self.ensure_inventory_room(&user, 1)?;
+        self.deck.take(&[target])?;
+
+        user.crafting_points -= cost;
+        user.add_cards(vec![target]);
+        token::record_minted(&mut self.card_supply, &[target]);
+        self.users.insert(sender.clone(), user.clone());
+        self.emit(EventKind::CardCrafted, &sender, &[], Some(cost));
+        Ok(user)
+    }
+
+    #[query]
+    async fn get_crafting_rates(&self) -> Vec<CraftingRate> {
+        crafting::crafting_rates()
+    }
+
+    #[mutate]
+    async fn contribute_entropy(&mut self, entropy: String) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        if entropy.is_empty() {
+            return Err("entropy must not be empty".to_string());
+        }
+
+        self.mix_entropy(&[
+            sender.as_bytes(),
+            entropy.as_bytes(),
+            &env::block_height().to_le_bytes(),
+        ]);
+        self.emit(EventKind::EntropyContributed, &sender, &[], None);
+        Ok(())
+    }
+
+    #[mutate]
+    async fn buy_sealed_pack(&mut self, kind: PackKind) -> Result<Pack, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before buying packs".to_string())?;
+
+        if user.balance < kind.price() {
+            return Err("insufficient balance".to_string());
+        }
+        user.balance -= kind.price();
+        self.treasury += kind.price();
+
+        let pack = Pack::new(self.next_pack_id(), kind, sender.clone());
+        let mut packs = self.user_packs.get(&sender).unwrap_or_default();
+        packs.push(pack.pack_id.clone());
+
+        self.users.insert(sender.clone(), user);
+        self.user_packs.insert(sender, packs);
+        self.packs.insert(pack.pack_id.clone(), pack.clone());
+        self.emit(EventKind::PackBought, &pack.pack_id, &[], Some(kind.price()));
+        Ok(pack)
+    }
+
+    #[mutate]
+    async fn gift_pack(&mut self, pack_id: String, recipient: String) -> Result<Pack, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let pack = self.owned_pack(&pack_id, &sender)?;
+
+        if recipient == sender {
+            return Err("cannot gift a pack to yourself".to_string());
+        }
+
+        if self.users.get(&recipient).is_none() {
+            return Err("recipient not registered".to_string());
+        }
+
+        self.transfer_pack(pack, &recipient);
+        self.emit(EventKind::PackGifted, &pack_id, &[&recipient], None);
+        self.packs
+            .get(&pack_id)
+            .ok_or_else(|| "pack not found".to_string())
+    }
+
+    #[mutate]
+    async fn offer_pack(
+        &mut self,
+        pack_id: String,
+        amount: u64,
+        duration_blocks: u64,
+    ) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        if duration_blocks == 0 {
+            return Err("offer duration must be at least one block".to_string());
+        }
+
+        let expires_at = env::block_height().saturating_add(duration_blocks);
+        self.list_offer(Vec::new(), Some(pack_id), OfferTerms::auction(amount, expires_at))
+    }
+
+    #[mutate]
+    async fn open_pack(
+        &mut self,
+        pack_id: String,
+        commitment: String,
+    ) -> Result<PendingDraw, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut pack = self.owned_pack(&pack_id, &sender)?;
+
+        // the pack is already paid for, opening it is a free draw of its cards
+        let draw = self.commit_draw(&sender, pack.kind.card_count(), commitment)?;
+
+        let mut packs = self.user_packs.get(&sender).unwrap_or_default();
+        packs.retain(|id| *id != pack_id);
+        pack.is_opened = true;
+
+        self.user_packs.insert(sender, packs);
+        self.packs.insert(pack_id.clone(), pack);
+        self.emit(EventKind::PackOpened, &pack_id, &[], None);
+        Ok(draw)
+    }
+
+    #[query]
+    async fn get_my_packs(&self) -> Vec<Pack> {
+        self.user_packs
+            .get(&env::sender())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.packs.get(id))
+            .collect()
+    }
+
+    #[query]
+    async fn get_pack(&self, pack_id: String) -> Option<Pack> {
+        self.packs.get(&pack_id)
+    }
+
+    #[mutate]
+    async fn stake(
+        &mut self,
+        hand_id: String,
+        cards: Vec<Card>,
+        claimed_count: u32,
+    ) -> Result<Hand, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before staking".to_string())?;
+
+        let mut hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found for staking".to_string())?;
+
+        if hand.is_resolved() {
+            return Err("cannot stake on a resolved hand".to_string());
+        }
+
+        if hand.is_expired(env::block_height()) {
+            return Err("cannot stake on an expired hand".to_string());
+        }
+
+        if !hand.admits(&sender) {
+            return Err("hand is private and the caller is not invited".to_string());
+        }
+        self.ensure_not_spectating(&hand_id, &sender)?;
+
+        if hand.stakes.len() >= hand.max_stakes as usize {
+            return Err(format!("hand already has the maximum of {} stakes", hand.max_stakes));
+        }
+        if hand.alternate_stakers && hand.last_stake().is_some_and(|s| s.user_id == sender) {
+            return Err("cannot stake twice in a row on this hand".to_string());
+        }
+        if let Some(limit) = hand.max_stakes_per_player
+            && hand.stakes_by(&sender) >= limit
+        {
+            return Err(format!("each player may stake at most {} times on this hand", limit));
+        }
+
+        self.ensure_stake_size(&cards)?;
+        self.ensure_stake_loss_limit(&user, &cards)?;
+        self.ensure_bracket_entry(&hand, &user, &cards)?;
+        EscalateContractState::ensure_turn(&hand, &sender)?;
+
+        if let Some(team) = self.team_hands.get(&hand_id) {
+            let side = team
+                .team_of(&sender)
+                .ok_or_else(|| "only assigned players can stake on a team hand".to_string())?;
+            if side != EscalateContractState::checking_team(&hand, &team) {
+                return Err("team hands alternate stakes, it is the other team's turn".to_string());
+            }
+        }
+
+        let previous = hand.last_stake().map_or(0, |s| s.cards.len());
+        if !escalate_rules::is_valid_raise(previous, cards.len(), hand.min_raise) {
+            return Err(format!(
+                "stake must contain at least {} cards",
+                previous + hand.min_raise as usize
+            ));
+        }
+
+        EscalateContractState::remove_cards_from_inventory(&mut user.cards, &cards)
+            .map_err(|e| e.to_string())?;
+
+        let height = env::block_height();
+        hand.add_stake(Stake::new(sender.clone(), cards.clone(), claimed_count, height)?);
+        if hand.is_turn_based() {
+            hand.advance_turn();
+        }
+
+        // the stake hands the check to the other team, so confirmations
+        // gathered towards the last one are void
+        if let Some(mut team) = self.team_hands.get(&hand_id) {
+            team.pending_check = None;
+            self.team_hands.insert(hand_id.clone(), team);
+        }
+
+        self.users.insert(sender.clone(), user);
+        self.store_hand(&mut hand);
+        self.record_participation(&sender, &hand_id);
+        let stake = hand.last_stake().cloned();
+        self.log_hand_action(&hand_id, &sender, HandActionKind::Staked, stake.as_ref(), None);
+        self.emit(EventKind::Staked, &hand_id, &[&hand.creator], None);
+        Ok(hand)
+    }
+
+    #[mutate]
+    async fn join_hand(&mut self, hand_id: String) -> Result<Hand, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before joining a hand".to_string())?;
+
+        let mut hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found".to_string())?;
+
+        if !hand.is_turn_based() {
+            return Err("only turn-based hands have seats to join".to_string());
+        }
+        if hand.is_resolved() || hand.is_expired(env::block_height()) {
+            return Err("cannot join a hand that is no longer open".to_string());
+        }
+        if hand.turn_order.contains(&sender) {
+            return Err("already seated at this hand".to_string());
+        }
+        if !hand.admits(&sender) {
+            return Err("hand is private and the caller is not invited".to_string());
+        }
+        self.ensure_not_spectating(&hand_id, &sender)?;
+        self.ensure_bracket_entry(&hand, &user, &[])?;
+
+        hand.turn_order.push(sender.clone());
+        self.store_hand(&mut hand);
+        self.record_participation(&sender, &hand_id);
+        self.log_hand_action(&hand_id, &sender, HandActionKind::Joined, None, None);
+        self.emit(EventKind::HandJoined, &hand_id, &[&hand.creator], None);
+        Ok(EscalateContractState::mask_hand_for_view(&hand))
+    }
+
+    // the passing player sits out the rest of the hand; once only one
+    // player is left the hand folds and every stake is returned
+    #[mutate]
+    async fn pass_turn(&mut self, hand_id: String) -> Result<Hand, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found".to_string())?;
+
+        if hand.is_resolved() || hand.is_expired(env::block_height()) {
+            return Err("cannot pass on a hand that is no longer open".to_string());
+        }
+        if !hand.is_turn_based() {
+            return Err("only turn-based hands can be passed on".to_string());
+        }
+        EscalateContractState::ensure_turn(&hand, &sender)?;
+
+        self.log_hand_action(&hand_id, &sender, HandActionKind::Passed, None, None);
+        hand.passed.push(sender);
+        if hand.active_players() <= 1 {
+            hand.status = HandStatus::Folded;
+            self.unwind_hand(&mut hand, EventKind::HandFolded);
+            return Ok(EscalateContractState::mask_hand_for_view(&hand));
+        }
+
+        hand.advance_turn();
+        self.store_hand(&mut hand);
+        self.emit(EventKind::TurnPassed, &hand_id, &[&hand.creator], None);
+        Ok(EscalateContractState::mask_hand_for_view(&hand))
+    }
+
+    // the unmasked action log of a hand, only once nothing is left to hide
+    #[query]
+    async fn get_hand_replay(&self, hand_id: String) -> Result<HandReplay, String> {
+        let hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found".to_string())?;
+        if !hand.is_resolved() {
+            return Err("hands can only be replayed once resolved".to_string());
+        }
+
+        Ok(HandReplay {
+            actions: self.hand_actions.get(&hand_id).unwrap_or_default(),
+            payouts: self.check_payouts.get(&hand_id).unwrap_or_default(),
+            hand,
+        })
+    }
+
+    #[mutate]
+    async fn check(&mut self, hand_id: String) -> Result<CheckOutcome, String> {
+        self.ensure_not_paused()?;
+        let checker_id = env::sender();
+        self.ensure_unlocked(&checker_id)?;
+        let checker = self
+            .users
+            .get(&checker_id)
+            .ok_or_else(|| "user must register before checking".to_string())?;
+
+        let hand = self.checkable_hand(&hand_id)?;
+        if self.team_hands.get(&hand_id).is_some() {
+            return Err("team hands are checked through confirm_team_check".to_string());
+        }
+        if !hand.admits(&checker_id) {
+            return Err("hand is private and the caller is not invited".to_string());
+        }
+        // a check decides the pool, so its wagerers may not make it
+        self.ensure_not_spectating(&hand_id, &checker_id)?;
+        self.ensure_bracket_entry(&hand, &checker, &[])?;
+        EscalateContractState::ensure_checker_role(&hand, &checker_id)?;
+        EscalateContractState::ensure_turn(&hand, &checker_id)?;
+        self.ensure_loss_limit(&checker, payouts::checker_delta(&hand, false).unsigned_abs())?;
+        let bluff_detected = is_bluff(&hand);
+        Ok(self.finish_check(hand, bluff_detected))
+    }
+
+    #[mutate]
+    async fn confirm_team_check(
+        &mut self,
+        hand_id: String,
+    ) -> Result<Option<CheckOutcome>, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut hand = self.checkable_hand(&hand_id)?;
+        let mut team = self
+            .team_hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand is not a team hand".to_string())?;
+
+        let checking_team = EscalateContractState::checking_team(&hand, &team);
+        if team.team_of(&sender) != Some(checking_team) {
+            return Err("only the team that did not place the last stake can check".to_string());
+        }
+
+        let confirmed = team.confirm(&sender, checking_team, env::block_height());
+        let teammates: Vec<&str> =
+            team.members(checking_team).iter().map(String::as_str).collect();
+        self.emit(EventKind::TeamCheckConfirmed, &hand_id, &teammates, None);
+        if !confirmed {
+            self.team_hands.insert(hand_id, team);
+            self.store_hand(&mut hand);
+            return Ok(None);
+        }
+
+        team.pending_check = None;
+        self.team_hands.insert(hand_id, team);
+        let bluff_detected = is_bluff(&hand);
+        Ok(Some(self.finish_check(hand, bluff_detected)))
+    }
+
+    #[mutate]
+    async fn claim_expired_hand(&mut self, hand_id: String) -> Result<Hand, String> {
+        self.ensure_not_paused()?;
+        let mut hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found".to_string())?;
+
+        if hand.is_resolved() {
+            return Err("hand already resolved".to_string());
+        }
+
+        if !hand.is_expired(env::block_height()) {
+            return Err("hand has not expired yet".to_string());
+        }
+
+        // nobody checked in time, so every staker gets their cards back
+        hand.status = HandStatus::Expired;
+        self.unwind_hand(&mut hand, EventKind::HandExpired);
+        Ok(EscalateContractState::mask_hand_for_view(&hand))
+    }
+
+    #[mutate]
+    async fn cancel_hand(&mut self, hand_id: String) -> Result<Hand, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found".to_string())?;
+
+        if hand.creator != sender {
+            return Err("only the creator can cancel a hand".to_string());
+        }
+
+        if hand.is_resolved() {
+            return Err("hand already resolved".to_string());
+        }
+
+        if hand.stakes.iter().any(|s| s.user_id != sender) {
+            return Err("cannot cancel a hand someone else has staked on".to_string());
+        }
+
+        hand.status = HandStatus::Canceled;
+        self.unwind_hand(&mut hand, EventKind::HandCanceled);
+        Ok(hand)
+    }
+
+    #[query]
+    async fn preview_check(
+        &self,
+        hand_id: String,
+        locale: Option<String>,
+    ) -> Result<CheckPreview, String> {
+        let caller = env::sender();
+        let user = self
+            .users
+            .get(&caller)
+            .ok_or_else(|| self.localize(Message::new(MessageCode::UserNotRegistered), &locale))?;
+        let debt = self.debts.get(&caller).unwrap_or(0);
+
+        let hand = self
+            .checkable_hand(&hand_id)
+            .map_err(|m| self.localize(m, &locale))?;
+        self.ensure_bracket_entry(&hand, &user, &[])
+            .map_err(|m| self.localize(m, &locale))?;
+        EscalateContractState::ensure_checker_role(&hand, &caller)
+            .map_err(|m| self.localize(m, &locale))?;
+        let bets = self.side_bets.get(&hand_id).unwrap_or_default();
+        let delta_for_caller = |bluff_detected: bool| -> i64 {
+            let mut after = user.clone();
+            let mut debt_after = debt;
+            let payouts = self.settle_check(&hand, &bets, &caller, bluff_detected);
+            for line in payouts {
+                if line.user_id == caller {
+                    after.apply_delta(line.amount, &mut debt_after, self.params.balance_floor);
+                }
+            }
+            after.balance as i64 - user.balance as i64
+        };
+
+        Ok(CheckPreview {
+            hand_id,
+            if_bluff: delta_for_caller(true),
+            if_not_bluff: delta_for_caller(false),
+        })
+    }
+
+    #[query]
+    async fn get_resolution_stats(&self, window: u64) -> ResolutionStats {
+        let latest = self.latest_resolution_totals();
+        let window = window.min(latest.resolutions);
+        // snapshot i holds the totals after i + 1 checks
+        let older = match latest.resolutions - window {
+            0 => ResolutionTotals::default(),
+            n => self.resolution_totals.get(n as usize - 1).unwrap_or_default(),
+        };
+        ResolutionStats::between(&older, &latest)
+    }
+
+    #[query]
+    async fn get_reputation(&self, user_id: String) -> Option<i64> {
+        self.users.get(&user_id).map(|u| u.reputation)
+    }
+
+    // the bonus a hand started now would lock in for each claimed rank
+    #[query]
+    async fn get_claim_bonuses(&self) -> Vec<ClaimBonus> {
+        let recent = self.recent_claims();
+        Card::ALL
+            .iter()
+            .zip(&recent)
+            .map(|(card, count)| ClaimBonus {
+                card: *card,
+                recent_claims: *count,
+                bonus_percent: payouts::claim_diversity_bonus_percent(
+                    &recent,
+                    *card,
+                    MAX_CLAIM_DIVERSITY_BONUS_PERCENT,
+                    CLAIM_DIVERSITY_MIN_HANDS,
+                ),
+            })
+            .collect()
+    }
+
+    #[query]
+    async fn get_user_stats(&self, user_id: String) -> Option<UserStats> {
+        self.user_stats.get(&user_id)
+    }
+
+    #[query]
+    async fn get_my_achievements(&self) -> Vec<AchievementProgress> {
+        let sender = env::sender();
+        let stats = self
+            .user_stats
+            .get(&sender)
+            .unwrap_or_else(|| UserStats::new(sender.clone()));
+        let unlocked = self.user_achievements.get(&sender).unwrap_or_default();
+        achievements::achievements()
+            .into_iter()
+            .map(|achievement| AchievementProgress {
+                progress: achievement.kind.progress(&stats).min(achievement.goal),
+                unlocked: unlocked.iter().find(|u| u.kind == achievement.kind).cloned(),
+                achievement,
+            })
+            .collect()
+    }
+
+    #[query]
+    async fn get_user_badges(&self, user_id: String) -> Vec<UnlockedAchievement> {
+        self.user_achievements.get(&user_id).unwrap_or_default()
+    }
+
+    // reward cards are dealt from the deck; chips come from the treasury and
+    // pay down any debt first
+    #[mutate]
+    async fn claim_achievement_rewards(&mut self) -> Result<User, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user not registered".to_string())?;
+        let mut unlocked = self.user_achievements.get(&sender).unwrap_or_default();
+        let pending: Vec<Achievement> = unlocked
+            .iter()
+            .filter(|u| !u.rewards_claimed)
+            .map(|u| Achievement::for_kind(u.kind))
+            .collect();
+        if pending.is_empty() {
+            return Err("no achievement rewards to claim".to_string());
+        }
+
+        // nothing is marked claimed unless all of it can be paid
+        let balance = pending.iter().map(|a| a.reward_balance).sum::<u64>();
+        if balance > self.treasury {
+            return Err("the treasury cannot cover the achievement rewards yet".to_string());
+        }
+        let cards: Vec<Card> = pending.iter().flat_map(|a| a.reward_cards.clone()).collect();
+        self.ensure_inventory_room(&user, cards.len())?;
+        self.deck.take(&cards)?;
+        token::record_minted(&mut self.card_supply, &cards);
+        user.add_cards(cards);
+        self.users.insert(sender.clone(), user);
+
+        self.treasury -= balance;
+        self.apply_payouts(vec![(sender.clone(), balance as i64)]);
+        for achievement in unlocked.iter_mut() {
+            achievement.rewards_claimed = true;
+        }
+        self.user_achievements.insert(sender.clone(), unlocked);
+        self.emit(EventKind::AchievementRewardsClaimed, &sender, &[], Some(balance));
+        self.users
+            .get(&sender)
+            .ok_or_else(|| "user not registered".to_string())
+    }
+
+    // players with the highest value of `metric`, ties in registration order
+    #[query]
+    async fn get_leaderboard(&self, metric: StatsMetric, limit: u64) -> Vec<UserStats> {
+        let mut board: Vec<UserStats> = (0..self.user_ids.len())
+            .filter_map(|i| self.user_ids.get(i))
+            .filter_map(|id| self.user_stats.get(&id))
+            .collect();
+        board.sort_by_key(|s| std::cmp::Reverse(s.metric(metric)));
+        board.truncate(limit.min(MAX_PAGE_LIMIT) as usize);
+        board
+    }
+
+    #[query]
+    async fn get_total_supply(&self) -> u64 {
+        self.total_supply
+    }
+
+    #[query]
+    async fn get_card_supply(&self) -> Vec<CardSupply> {
+        Card::ALL
+            .iter()
+            .map(|card| CardSupply {
+                card: *card,
+                supply: self.card_supply[token::token_id(*card)],
+            })
+            .collect()
+    }
+
+    #[query]
+    async fn get_card_price_stats(&self, card: Card) -> CardPriceStats {
+        market::price_stats(card, &self.sale_prices[token::token_id(card)])
+    }
+
+    #[query]
+    async fn get_treasury_balance(&self) -> u64 {
+        self.treasury
+    }
+
+    // pays treasury chips out to a registered user, answering with what the
+    // treasury holds afterwards; a recipient in debt repays it first, which
+    // goes straight back into the treasury
+    #[mutate]
+    async fn withdraw_treasury(&mut self, to: String, amount: u64) -> Result<u64, String> {
+        self.ensure_admin(&env::sender())?;
+        if self.users.get(&to).is_none() {
+            return Err("recipient not registered".to_string());
+        }
+        if amount == 0 {
+            return Err("withdrawal amount must be positive".to_string());
+        }
+        if amount > self.treasury {
+            return Err("treasury holds less than that".to_string());
+        }
+
+        self.treasury -= amount;
+        self.apply_payouts(vec![(to.clone(), amount as i64)]);
+        self.emit(EventKind::TreasuryWithdrawn, &to, &[&to], Some(amount));
+        Ok(self.treasury)
+    }
+
+    #[mutate]
+    async fn offer(
+        &mut self,
+        cards: Vec<Card>,
+        amount: u64,
+        duration_blocks: u64,
+        installments: Option<InstallmentTerms>,
+        buyout_price: Option<u64>,
+        card_prices: Option<Vec<u64>>,
+    ) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        if duration_blocks == 0 {
+            return Err("offer duration must be at least one block".to_string());
+        }
+
+        if let Some(prices) = &card_prices {
+            if prices.len() != cards.len() {
+                return Err("card prices must list one price per offered card".to_string());
+            }
+            if prices.iter().sum::<u64>() != amount {
+                return Err("card prices must add up to the offer price".to_string());
+            }
+        }
+
+        if let Some(terms) = &installments {
+            if amount < MIN_INSTALLMENT_PRICE {
+                return Err(format!(
+                    "only offers priced at {} or more can be paid in installments",
+                    MIN_INSTALLMENT_PRICE
+                ));
+            }
+            if terms.count < 2 || terms.count > MAX_INSTALLMENTS {
+                return Err(format!(
+                    "installment count must be between 2 and {}",
+                    MAX_INSTALLMENTS
+                ));
+            }
+            if terms.interval_blocks == 0 {
+                return Err("installment interval must be at least one block".to_string());
+            }
+            if terms.forfeit_percent > 100 {
+                return Err("forfeit percent cannot exceed 100".to_string());
+            }
+        }
+
+        if let Some(price) = buyout_price {
+            if price <= amount {
+                return Err("buyout price must be above the initial price".to_string());
+            }
+            if installments.is_some() {
+                return Err("offers paid in installments cannot have a buyout price".to_string());
+            }
+        }
+
+        let expires_at = env::block_height().saturating_add(duration_blocks);
+        let terms = OfferTerms {
+            installments,
+            buyout_price,
+            card_prices,
+            ..OfferTerms::auction(amount, expires_at)
+        };
+        self.list_offer(cards, None, terms)
+    }
+
+    #[mutate]
+    async fn offer_sealed(
+        &mut self,
+        cards: Vec<Card>,
+        reserve: u64,
+        reveal_deadline: u64,
+    ) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        let commit_end = reveal_deadline.saturating_sub(SEALED_REVEAL_BLOCKS);
+        if commit_end <= env::block_height() {
+            return Err(format!(
+                "reveal deadline must leave room for bids before its {} block reveal window",
+                SEALED_REVEAL_BLOCKS
+            ));
+        }
+
+        let expires_at = reveal_deadline.saturating_add(SEALED_SETTLE_BLOCKS);
+        let terms = OfferTerms {
+            reveal_deadline: Some(reveal_deadline),
+            ..OfferTerms::auction(reserve, expires_at)
+        };
+        self.list_offer(cards, None, terms)
+    }
+
+    #[mutate]
+    async fn bid_sealed(
+        &mut self,
+        offer_id: String,
+        commitment: String,
+    ) -> Result<SealedBid, String> {
+        self.ensure_not_paused()?;
+        let bidder_id = env::sender();
+        self.ensure_unlocked(&bidder_id)?;
+        let mut bidder = self
+            .users
+            .get(&bidder_id)
+            .ok_or_else(|| "user must register before bidding".to_string())?;
+
+        let offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        let Some(reveal_deadline) = offer.reveal_deadline else {
+            return Err("offer takes open bids, use bid instead".to_string());
+        };
+
+        if offer.is_resolved {
+            return Err("cannot bid on resolved offer".to_string());
+        }
+
+        let height = env::block_height();
+        if height >= reveal_deadline.saturating_sub(SEALED_REVEAL_BLOCKS) {
+            return Err("sealed bidding has closed, only reveals are accepted".to_string());
+        }
+
+        if offer.creator_id == bidder_id {
+            return Err("creator cannot bid on own offer".to_string());
+        }
+
+        if commitment.len() != 64 {
+            return Err("commitment must be a hex encoded sha256 of amount:salt".to_string());
+        }
+
+        // a bidder holds one commitment per offer, bidding again replaces it
+        // and keeps the deposit already locked
+        let mut bids = self.sealed_bids.get(&offer_id).unwrap_or_default();
+        let deposit = match bids.iter().position(|b| b.bidder_id == bidder_id) {
+            Some(idx) => bids.remove(idx).deposit,
+            None => {
+                let deposit = offer.initial_price.max(SEALED_BID_MIN_DEPOSIT);
+                if bidder.lock(deposit).is_err() {
+                    return Err(format!("a sealed bid needs a deposit of {}", deposit));
+                }
+                deposit
+            }
+        };
+        let bid = SealedBid::new(bidder_id.clone(), commitment, height, deposit);
+        bids.push(bid.clone());
+
+        self.users.insert(bidder_id, bidder);
+        self.sealed_bids.insert(offer_id.clone(), bids);
+        self.emit(EventKind::SealedBidPlaced, &offer_id, &[&offer.creator_id], None);
+        Ok(bid)
+    }
+
+    #[mutate]
+    async fn reveal_bid(
+        &mut self,
+        offer_id: String,
+        amount: u64,
+        salt: String,
+    ) -> Result<SealedBid, String> {
+        self.ensure_not_paused()?;
+        let bidder_id = env::sender();
+        self.ensure_unlocked(&bidder_id)?;
+        let mut bidder = self
+            .users
+            .get(&bidder_id)
+            .ok_or_else(|| "user must register before revealing a bid".to_string())?;
+
+        let mut offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        let Some(reveal_deadline) = offer.reveal_deadline else {
+            return Err("offer takes open bids, nothing to reveal".to_string());
+        };
+
+        if offer.is_resolved {
+            return Err("cannot reveal a bid on resolved offer".to_string());
+        }
+
+        let height = env::block_height();
+        let reveal_start = reveal_deadline.saturating_sub(SEALED_REVEAL_BLOCKS);
+        if height < reveal_start || height >= reveal_deadline {
+            return Err("bids can only be revealed during the reveal window".to_string());
+        }
+
+        let mut bids = self.sealed_bids.get(&offer_id).unwrap_or_default();
+        let bid = bids
+            .iter_mut()
+            .find(|b| b.bidder_id == bidder_id)
+            .ok_or_else(|| "no sealed bid to reveal".to_string())?;
+
+        if bid.revealed_amount.is_some() {
+            return Err("bid already revealed".to_string());
+        }
+
+        if !bid.matches(amount, &salt) {
+            return Err("amount and salt do not match the bid commitment".to_string());
+        }
+
+        if amount < offer.initial_price {
+            return Err("bid is below the reserve price".to_string());
+        }
+        self.ensure_room_for_offer(&bidder, &offer)?;
+
+        // only a new highest bid is escrowed, ties go to the earlier reveal.
+        // A bid the bidder cannot cover stays hidden and loses its deposit
+        let is_best = offer.current_bid.is_none_or(|best| amount > best);
+        if is_best && bidder.balance + bid.deposit < amount {
+            return Err("insufficient balance for bid".to_string());
+        }
+        bidder.unlock(bid.deposit);
+        bid.deposit = 0;
+
+        if is_best {
+            if let Some((prev_bidder_id, prev_amount)) = payouts::outbid_refund(&offer)
+                && let Some(mut prev_bidder) = self.users.get(&prev_bidder_id)
+            {
+                prev_bidder.unlock(prev_amount);
+                self.users.insert(prev_bidder_id, prev_bidder);
+            }
+
+            bidder.lock(amount)?;
+            offer.current_bid = Some(amount);
+            offer.current_bidder_id = Some(bidder_id.clone());
+        }
+
+        bid.revealed_amount = Some(amount);
+        let revealed = bid.clone();
+        let creator_id = offer.creator_id.clone();
+
+        self.users.insert(bidder_id, bidder);
+        self.offers.insert(offer_id.clone(), offer);
+        self.sealed_bids.insert(offer_id.clone(), bids);
+        self.emit(EventKind::BidRevealed, &offer_id, &[&creator_id], Some(amount));
+        Ok(revealed)
+    }
+
+    #[query]
+    async fn get_sealed_bids(&self, offer_id: String) -> Vec<SealedBid> {
+        self.sealed_bids.get(&offer_id).unwrap_or_default()
+    }
+
+    #[query]
+    async fn get_offers(&self) -> Vec<Offer> {
+        self.offers_page(0, DEFAULT_PAGE_LIMIT).items
+    }
+
+    #[query]
+    async fn get_offers_page(&self, offset: u64, limit: u64) -> OfferPage {
+        self.offers_page(offset, limit)
+    }
+
+    // the offers page with each seller's profile attached
+    #[query]
+    async fn get_offers_enriched(&self, offset: u64, limit: u64) -> EnrichedOfferPage {
+        let page = self.offers_page(offset, limit);
+        let mut sellers: Vec<SellerProfile> = Vec::new();
+        let mut items = Vec::with_capacity(page.items.len());
+        for offer in page.items {
+            let known = sellers.iter().find(|p| p.user_id == offer.creator_id).cloned();
+            let seller = known.or_else(|| {
+                let profile = self.seller_profile(&offer.creator_id)?;
+                sellers.push(profile.clone());
+                Some(profile)
+            });
+            items.push(EnrichedOffer { offer, seller });
+        }
+        PageResult {
+            items,
+            total_count: page.total_count,
+        }
+    }
+
+    // the highest bid, or the initial price before any, attributed to each card
+    #[query]
+    async fn get_offer_card_values(&self, offer_id: String) -> Result<Vec<CardValue>, String> {
+        let offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+        Ok(offer.card_values(offer.current_bid.unwrap_or(offer.initial_price)))
+    }
+
+    #[mutate]
+    async fn boost_offer(&mut self, offer_id: String, blocks: u64) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        if offer.creator_id != sender {
+            return Err("only creator can boost offer".to_string());
+        }
+
+        let height = env::block_height();
+        if offer.is_resolved || offer.is_expired(height) {
+            return Err("only live offers can be boosted".to_string());
+        }
+
+        if blocks == 0 {
+            return Err("boost must last at least one block".to_string());
+        }
+
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user not registered".to_string())?;
+        let fee = blocks.saturating_mul(BOOST_FEE_PER_BLOCK);
+        if user.balance < fee {
+            return Err("insufficient balance for boost fee".to_string());
+        }
+
+        user.balance -= fee;
+        self.treasury += fee;
+        // boosting again extends a running boost instead of restarting it
+        offer.boost_until = offer.boost_until.max(height).saturating_add(blocks);
+        offer.boost_fees_paid += fee;
+
+        let offers = &self.offers;
+        self.boosted_offer_ids
+            .retain(|id| *id != offer_id && offers.get(id).is_some_and(|o| o.is_boosted(height)));
+        self.boosted_offer_ids.push(offer_id.clone());
+
+        self.users.insert(sender, user);
+        self.offers.insert(offer_id.clone(), offer.clone());
+        self.emit(EventKind::OfferBoosted, &offer_id, &[], Some(fee));
+        Ok(offer)
+    }
+
+    #[mutate]
+    async fn bid(&mut self, offer_id: String, bid_amout: u64) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let bidder_id = env::sender();
+        self.ensure_unlocked(&bidder_id)?;
+        let mut bidder = self
+            .users
+            .get(&bidder_id)
+            .ok_or_else(|| "user must register before bidding".to_string())?;
+
+        let mut offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        if offer.is_resolved {
+            return Err("cannot bid on resolved offer".to_string());
+        }
+
+        if offer.is_expired(env::block_height()) {
+            return Err("cannot bid on expired offer".to_string());
+        }
+        if offer.awaiting_confirmation() {
+            return Err("offer is waiting for the bidder to confirm the purchase".to_string());
+        }
+
+        if offer.is_sealed() {
+            return Err("offer is a sealed-bid auction, use bid_sealed instead".to_string());
+        }
+
+        if offer.dutch.is_some() {
+            return Err("offer is a dutch auction, use accept_dutch instead".to_string());
+        }
+
+        if offer.price_per_card.is_some() {
+            return Err("offer is a bundle, use buy_from_bundle instead".to_string());
+        }
+
+        if offer.creator_id == bidder_id {
+            return Err("creator cannot bid on own offer".to_string());
+        }
+
+        if offer.counter_accepted() {
+            return Err("offer is settling at an accepted counter price".to_string());
+        }
+
+        let min_bid = offer.min_next_bid();
+        if bid_amout < min_bid {
+            return Err(format!("bid must be at least {}", min_bid));
+        }
+        self.ensure_room_for_offer(&bidder, &offer)?;
+
+        // a raise of one's own bid may reuse its escrow; checked before
+        // anyone's chips move
+        let refund = payouts::outbid_refund(&offer);
+        let own_escrow = refund
+            .as_ref()
+            .filter(|(prev_bidder_id, _)| *prev_bidder_id == bidder_id)
+            .map_or(0, |(_, amount)| *amount);
+        let escrow = offer.bid_escrow(bid_amout);
+        if bidder.balance + own_escrow < escrow {
+            return Err("insufficient balance for bid".to_string());
+        }
+
+        let outbid_id = offer.current_bidder_id.clone().unwrap_or_default();
+        // refund previous highest bidder, if any
+        if let Some((prev_bidder_id, prev_amount)) = refund {
+            if prev_bidder_id == bidder_id {
+                bidder.unlock(prev_amount);
+            } else if let Some(mut prev_bidder) = self.users.get(&prev_bidder_id) {
+                prev_bidder.unlock(prev_amount);
+                self.users.insert(prev_bidder_id, prev_bidder);
+            }
+        }
+
+        bidder.lock(escrow)?;
+
+        offer.current_bid = Some(bid_amout);
+        offer.current_bidder_id = Some(bidder_id.clone());
+        offer.clear_counter();
+        let creator_id = offer.creator_id.clone();
+
+        self.users.insert(bidder_id, bidder);
+        self.offers.insert(offer_id.clone(), offer);
+        self.emit(
+            EventKind::BidPlaced,
+            &offer_id,
+            &[&creator_id, &outbid_id],
+            Some(bid_amout),
+        );
+        Ok(())
+    }
+
+    // the seller can only change the rules while nobody has bid yet
+    #[mutate]
+    async fn set_offer_bid_rules(
+        &mut self,
+        offer_id: String,
+        min_increment: u64,
+        reserve_price: Option<u64>,
+    ) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        if offer.creator_id != sender {
+            return Err("only creator can change the bid rules".to_string());
+        }
+        if offer.is_resolved {
+            return Err("offer already resolved".to_string());
+        }
+        if offer.current_bid.is_some() {
+            return Err("bid rules cannot change once the offer has a bid".to_string());
+        }
+        if offer.is_sealed() || offer.dutch.is_some() {
+            return Err("only open auctions take bid rules".to_string());
+        }
+        if min_increment == 0 {
+            return Err("minimum bid increment must be at least one chip".to_string());
+        }
+        if reserve_price.is_some_and(|reserve| reserve <= offer.initial_price) {
+            return Err("reserve price must be above the initial price".to_string());
+        }
+
+        offer.min_increment = min_increment;
+        offer.reserve_price = reserve_price;
+        self.offers.insert(offer_id, offer.clone());
+        Ok(offer)
+    }
+
+    // like the bid rules, only before anyone has bid
+    #[mutate]
+    async fn require_bidder_confirmation(&mut self, offer_id: String) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        if offer.creator_id != sender {
+            return Err("only creator can require a confirmation".to_string());
+        }
+        if offer.is_resolved {
+            return Err("offer already resolved".to_string());
+        }
+        if offer.current_bid.is_some() {
+            return Err("a confirmation cannot be required once the offer has a bid".to_string());
+        }
+        if !offer.can_require_confirmation() {
+            return Err("only open auctions paid in full can require a confirmation".to_string());
+        }
+
+        offer.requires_confirmation = true;
+        self.offers.insert(offer_id, offer.clone());
+        Ok(offer)
+    }
+
+    #[mutate]
+    async fn confirm_purchase(&mut self, offer_id: String) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        if offer.current_bidder_id.as_deref() != Some(&sender) {
+            return Err("only the winning bidder can confirm the purchase".to_string());
+        }
+        let Some(by) = offer.confirm_by.filter(|_| offer.awaiting_confirmation()) else {
+            return Err("offer is not waiting for a confirmation".to_string());
+        };
+        if env::block_height() >= by {
+            return Err("the confirmation window has closed".to_string());
+        }
+
+        self.complete_offer_sale(offer)
+    }
+
+    // the seller answers the current bid with a higher price the bidder can
+    // take or leave; countering again replaces the earlier price
+    #[mutate]
+    async fn counter(&mut self, offer_id: String, price: u64) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        if offer.creator_id != sender {
+            return Err("only creator can counter a bid".to_string());
+        }
+        if offer.is_resolved || offer.is_expired(env::block_height()) {
+            return Err("offer is no longer open".to_string());
+        }
+        if offer.is_sealed() || offer.dutch.is_some() {
+            return Err("only open auctions can be negotiated".to_string());
+        }
+        if offer.counter_accepted() {
+            return Err("bidder already accepted a counter".to_string());
+        }
+        if offer.awaiting_confirmation() {
+            return Err("offer is waiting for the bidder to confirm the purchase".to_string());
+        }
+        let (Some(bid), Some(bidder_id)) = (offer.current_bid, offer.current_bidder_id.clone())
+        else {
+            return Err("offer has no bid to counter".to_string());
+        };
+        if price <= bid {
+            return Err("counter price must be above the current bid".to_string());
+        }
+
+        offer.negotiation_state = NegotiationState::Countered;
+        offer.counter_price = Some(price);
+        self.offers.insert(offer_id.clone(), offer.clone());
+        self.emit(EventKind::OfferCountered, &offer_id, &[&bidder_id], Some(price));
+        Ok(offer)
+    }
+
+    // the current bidder raises their bid to the counter price, escrowing
+    // the difference; the seller settles it through `resolve`
+    #[mutate]
+    async fn accept_counter(&mut self, offer_id: String) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut offer = self.countered_offer(&offer_id, &sender)?;
+        let mut bidder = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user not registered".to_string())?;
+
+        let price = offer.counter_price.unwrap_or_default();
+        let held = offer.bid_escrow(offer.current_bid.unwrap_or_default());
+        let top_up = offer.bid_escrow(price).saturating_sub(held);
+        if bidder.balance < top_up {
+            return Err("insufficient balance for counter price".to_string());
+        }
+        bidder.lock(top_up)?;
+
+        offer.current_bid = Some(price);
+        offer.negotiation_state = NegotiationState::Accepted;
+        let creator_id = offer.creator_id.clone();
+        self.users.insert(sender, bidder);
+        self.offers.insert(offer_id.clone(), offer.clone());
+        self.emit(EventKind::CounterAccepted, &offer_id, &[&creator_id], Some(price));
+        Ok(offer)
+    }
+
+    // the bid stands at its amount, the seller may still resolve at it
+    #[mutate]
+    async fn decline_counter(&mut self, offer_id: String) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut offer = self.countered_offer(&offer_id, &sender)?;
+
+        offer.negotiation_state = NegotiationState::Declined;
+        offer.counter_price = None;
+        let creator_id = offer.creator_id.clone();
+        self.offers.insert(offer_id.clone(), offer.clone());
+        self.emit(EventKind::CounterDeclined, &offer_id, &[&creator_id], None);
+        Ok(offer)
+    }
+
+    #[mutate]
+    async fn buy_now(&mut self, offer_id: String) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        let buyer_id = env::sender();
+        let offer = self.offer_for_instant_sale(&offer_id, &buyer_id)?;
+        let price = offer
+            .buyout_price
+            .ok_or_else(|| "offer has no buyout price".to_string())?;
+        self.complete_instant_sale(offer, buyer_id, price, EventKind::OfferBoughtOut)
+    }
+
+    #[mutate]
+    async fn offer_dutch(
+        &mut self,
+        cards: Vec<Card>,
+        start_price: u64,
+        floor_price: u64,
+        decay_per_block: u64,
+    ) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        if floor_price >= start_price {
+            return Err("floor price must be below the start price".to_string());
+        }
+        if decay_per_block == 0 {
+            return Err("price must decay by at least one chip per block".to_string());
+        }
+
+        let height = env::block_height();
+        let blocks_to_floor = (start_price - floor_price).div_ceil(decay_per_block);
+        let expires_at = height
+            .saturating_add(blocks_to_floor)
+            .saturating_add(DUTCH_FLOOR_BLOCKS);
+        let terms = OfferTerms {
+            dutch: Some(DutchTerms {
+                floor_price,
+                decay_per_block,
+                started_at_block: height,
+            }),
+            ..OfferTerms::auction(start_price, expires_at)
+        };
+        self.list_offer(cards, None, terms)
+    }
+
+    #[query]
+    async fn get_offer_price(&self, offer_id: String) -> Result<u64, String> {
+        let offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+        offer
+            .dutch_price(env::block_height())
+            .ok_or_else(|| "offer is not a dutch auction".to_string())
+    }
+
+    #[mutate]
+    async fn accept_dutch(&mut self, offer_id: String) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        let buyer_id = env::sender();
+        let offer = self.offer_for_instant_sale(&offer_id, &buyer_id)?;
+        let price = offer
+            .dutch_price(env::block_height())
+            .ok_or_else(|| "offer is not a dutch auction".to_string())?;
+        self.complete_instant_sale(offer, buyer_id, price, EventKind::DutchOfferAccepted)
+    }
+
+    #[mutate]
+    async fn offer_bundle(
+        &mut self,
+        cards: Vec<Card>,
+        price_per_card: u64,
+        duration_blocks: u64,
+    ) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        if cards.is_empty() {
+            return Err("a bundle must hold at least one card".to_string());
+        }
+        if price_per_card == 0 {
+            return Err("price per card must be at least one chip".to_string());
+        }
+        if duration_blocks == 0 {
+            return Err("offer duration must be at least one block".to_string());
+        }
+
+        let amount = price_per_card
+            .checked_mul(cards.len() as u64)
+            .ok_or_else(|| "bundle price overflows".to_string())?;
+        let expires_at = env::block_height().saturating_add(duration_blocks);
+        let terms = OfferTerms {
+            price_per_card: Some(price_per_card),
+            ..OfferTerms::auction(amount, expires_at)
+        };
+        self.list_offer(cards, None, terms)
+    }
+
+    // pays the seller for the picked cards only; the listing fee is
+    // refunded with the last card
+    #[mutate]
+    async fn buy_from_bundle(
+        &mut self,
+        offer_id: String,
+        cards: Vec<Card>,
+    ) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        let buyer_id = env::sender();
+        let mut offer = self.offer_for_instant_sale(&offer_id, &buyer_id)?;
+        let price_per_card = offer
+            .price_per_card
+            .ok_or_else(|| "offer is not a bundle".to_string())?;
+        if cards.is_empty() {
+            return Err("pick at least one card from the bundle".to_string());
+        }
+        EscalateContractState::remove_cards_from_inventory(&mut offer.cards, &cards)
+            .map_err(|_| "bundle does not hold the picked cards".to_string())?;
+
+        let mut buyer = self
+            .users
+            .get(&buyer_id)
+            .ok_or_else(|| "user must register before buying".to_string())?;
+        let price = price_per_card * cards.len() as u64;
+        if buyer.balance < price {
+            return Err("insufficient balance for the picked cards".to_string());
+        }
+        self.ensure_inventory_room(&buyer, cards.len())?;
+        if self.users.get(&offer.creator_id).is_none() {
+            return Err("creator not registered anymore".to_string());
+        }
+
+        buyer.balance -= price;
+        let jokers = achievements::jokers_in(&cards);
+        self.record_sale_prices(&cards, price_per_card);
+        buyer.add_cards(cards);
+        let refund = if offer.cards.is_empty() {
+            payouts::listing_refund(&offer, self.treasury)
+        } else {
+            0
+        };
+        let rake = payouts::sale_rake(price, self.params.sale_rake_percent);
+        let proceeds = price + refund - rake;
+        self.treasury = self.treasury - refund + rake;
+        self.update_stats(&offer.creator_id, |s| {
+            s.total_winnings += (price - rake) as i64;
+            s.jokers_sold += jokers;
+        });
+        offer.is_resolved = offer.cards.is_empty();
+
+        let creator_id = offer.creator_id.clone();
+        self.users.insert(buyer_id, buyer);
+        self.apply_payouts(vec![(creator_id.clone(), proceeds as i64)]);
+        self.offers.insert(offer_id.clone(), offer.clone());
+        self.emit(EventKind::BundlePurchased, &offer_id, &[&creator_id], Some(price));
+        self.pay_referral(&creator_id, rake);
+        Ok(offer)
+    }
+
+    #[mutate]
+    async fn resolve(&mut self, offer_id: String) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        if offer.creator_id != sender {
+            return Err("only creator can resolve offer".to_string());
+        }
+
+        if offer.is_resolved {
+            return Ok(());
+        }
+
+        if !offer.awaiting_confirmation() && offer.is_expired(env::block_height()) {
+            return Err("offer expired, reclaim it instead".to_string());
+        }
+
+        if offer
+            .reveal_deadline
+            .is_some_and(|deadline| env::block_height() < deadline)
+        {
+            return Err("sealed bids are still being revealed".to_string());
+        }
+        self.forfeit_unrevealed_bids(&offer_id);
+
+        // an accepted counter is the price the seller asked for, it sells
+        // regardless of the reserve
+        if !offer.counter_accepted() && offer.current_bid.is_some_and(|b| !offer.meets_reserve(b)) {
+            return self.unwind_unsold_offer(offer, EventKind::OfferReserveNotMet);
+        }
+
+        // a stale auction's bidder gets the last word before the cards move
+        if offer.requires_confirmation && offer.current_bid.is_some() {
+            let height = env::block_height();
+            match offer.confirm_by {
+                None => {
+                    let reputation = self.users.get(&offer.creator_id).map_or(0, |u| u.reputation);
+                    let window = CONFIRM_PURCHASE_BLOCKS;
+                    let window = escalate_rules::confirm_window_for_reputation(window, reputation);
+                    offer.confirm_by = Some(height.saturating_add(window));
+                    let bidder_id = offer.current_bidder_id.clone().unwrap_or_default();
+                    let price = offer.current_bid;
+                    self.offers.insert(offer_id.clone(), offer);
+                    let kind = EventKind::OfferAwaitingConfirmation;
+                    self.emit(kind, &offer_id, &[&bidder_id], price);
+                    return Ok(());
+                }
+                Some(by) if height < by => {
+                    return Err("waiting for the bidder to confirm the purchase".to_string());
+                }
+                Some(_) => {
+                    return self.unwind_unsold_offer(offer, EventKind::PurchaseConfirmationLapsed);
+                }
+            }
+        }
+
+        self.complete_offer_sale(offer)
+    }
+
+    #[mutate]
+    async fn reclaim_expired_offer(&mut self, offer_id: String) -> Result<Offer, String> {
+        self.ensure_not_paused()?;
+        let mut offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        if offer.is_resolved {
+            return Err("offer already resolved".to_string());
+        }
+
+        if !offer.is_expired(env::block_height()) {
+            return Err("offer has not expired yet".to_string());
+        }
+        if offer.confirm_by.is_some_and(|by| env::block_height() < by) {
+            return Err("offer is waiting for the bidder to confirm the purchase".to_string());
+        }
+        self.forfeit_unrevealed_bids(&offer_id);
+
+        let bidder_id = offer.current_bidder_id.clone().unwrap_or_default();
+        if let Some((bidder_id, amount)) = payouts::outbid_refund(&offer)
+            && let Some(mut bidder) = self.users.get(&bidder_id)
+        {
+            bidder.unlock(amount);
+            self.users.insert(bidder_id, bidder);
+        }
+
+        if let Some(mut creator) = self.users.get(&offer.creator_id) {
+            creator.add_cards(offer.cards.clone());
+            self.users.insert(offer.creator_id.clone(), creator);
+        }
+        self.settle_offer_pack(&offer, &offer.creator_id);
+
+        offer.current_bid = None;
+        offer.current_bidder_id = None;
+        offer.is_resolved = true;
+        self.offers.insert(offer_id.clone(), offer.clone());
+        self.emit(
+            EventKind::OfferExpired,
+            &offer_id,
+            &[&offer.creator_id, &bidder_id],
+            None,
+        );
+
+        Ok(offer)
+    }
+
+    #[mutate]
+    async fn withdraw_bid(&mut self, offer_id: String) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut offer = self
+            .offers
+            .get(&offer_id)
+            .ok_or_else(|| "offer not found".to_string())?;
+
+        if offer.is_resolved {
+            return Err("cannot withdraw bid from resolved offer".to_string());
+        }
+
+        if offer.current_bidder_id.as_deref() != Some(&sender) {
+            return Err("only current bidder can withdraw bid".to_string());
+        }
+
+        if offer.is_sealed() {
+            return Err("revealed sealed bids cannot be withdrawn".to_string());
+        }
+
+        if offer.counter_accepted() {
+            return Err("an accepted counter cannot be withdrawn".to_string());
+        }
+
+        if offer.awaiting_confirmation() {
+            return Err("confirm the purchase or let the confirmation window close".to_string());
+        }
+
+        let withdrawn = offer.current_bid;
+        if let Some((bidder_id, amount)) = payouts::outbid_refund(&offer)
+            && let Some(mut bidder) = self.users.get(&bidder_id)
+        {
+            bidder.unlock(amount);
+            self.users.insert(bidder_id, bidder);
+        }
+
+        offer.current_bid = None;
+        offer.current_bidder_id = None;
+        offer.clear_counter();
+        let creator_id = offer.creator_id.clone();
+
+        self.offers.insert(offer_id.clone(), offer);
+        self.emit(EventKind::BidWithdrawn, &offer_id, &[&creator_id], withdrawn);
+        Ok(())
+    }
+
+    #[mutate]
+    async fn pay_installment(&mut self, offer_id: String) -> Result<InstallmentPlan, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut plan = self
+            .installment_plans
+            .get(&offer_id)
+            .ok_or_else(|| "installment plan not found".to_string())?;
+
+        if plan.buyer_id != sender {
+            return Err("only the buyer can pay installments".to_string());
+        }
+
+        if plan.is_settled {
+            return Err("installment plan already settled".to_string());
+        }
+
+        if plan.is_overdue(env::block_height()) {
+            return Err("installment overdue, the plan can only be forfeited".to_string());
+        }
+
+        let mut buyer = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "buyer not registered anymore".to_string())?;
+
+        // cards received since bidding can fill the inventory; the sale then
+        // falls through instead of holding the payment back
+        if plan.is_last_installment_due()
+            && self.ensure_inventory_room(&buyer, plan.cards.len()).is_err()
+        {
+            return Ok(self.unwind_installment_plan(plan));
+        }
+
+        let due = plan.next_installment();
+        if buyer.balance < due {
+            return Err("insufficient balance for installment".to_string());
+        }
+
+        buyer.balance -= due;
+        plan.record_payment(due);
+
+        let completed = plan.is_complete();
+        if completed {
+            buyer.add_cards(plan.cards.clone());
+            let rake = payouts::sale_rake(plan.total, self.params.sale_rake_percent);
+            let net = (plan.total - rake) as i64;
+            self.treasury += rake;
+            self.apply_payouts(vec![(plan.seller_id.clone(), net)]);
+            self.record_lot_sale(&plan.cards, plan.total);
+            let jokers = achievements::jokers_in(&plan.cards);
+            self.update_stats(&plan.seller_id, |s| {
+                s.total_winnings += net;
+                s.jokers_sold += jokers;
+            });
+            plan.is_settled = true;
+        }
+
+        self.users.insert(sender, buyer);
+        self.installment_plans.insert(offer_id.clone(), plan.clone());
+        self.emit(EventKind::InstallmentPaid, &offer_id, &[&plan.seller_id], Some(due));
+        if completed {
+            self.emit(
+                EventKind::InstallmentPlanCompleted,
+                &offer_id,
+                &[&plan.seller_id],
+                Some(plan.total),
+            );
+            let rake = payouts::sale_rake(plan.total, self.params.sale_rake_percent);
+            self.pay_referral(&plan.seller_id, rake);
+        }
+
+        Ok(plan)
+    }
+
+    #[mutate]
+    async fn forfeit_installment_plan(
+        &mut self,
+        offer_id: String,
+    ) -> Result<InstallmentPlan, String> {
+        self.ensure_not_paused()?;
+        let mut plan = self
+            .installment_plans
+            .get(&offer_id)
+            .ok_or_else(|| "installment plan not found".to_string())?;
+
+        if plan.is_settled {
+            return Err("installment plan already settled".to_string());
+        }
+
+        if !plan.is_overdue(env::block_height()) {
+            return Err("installment plan is not overdue".to_string());
+        }
+
+        // the buyer missed a due block: cards go back to the seller and
+        // the payments so far are split per the forfeit schedule
+        if let Some(mut seller) = self.users.get(&plan.seller_id) {
+            seller.add_cards(plan.cards.clone());
+            self.users.insert(plan.seller_id.clone(), seller);
+        }
+        self.apply_payouts(payouts::installment_forfeit(&plan));
+
+        plan.is_settled = true;
+        self.installment_plans.insert(offer_id.clone(), plan.clone());
+        self.emit(
+            EventKind::InstallmentPlanDefaulted,
+            &offer_id,
+            &[&plan.seller_id, &plan.buyer_id],
+            Some(plan.paid),
+        );
+
+        Ok(plan)
+    }
+
+    #[query]
+    async fn get_installment_plan(&self, offer_id: String) -> Option<InstallmentPlan> {
+        self.installment_plans.get(&offer_id)
+    }
+
+    #[mutate]
+    async fn propose_trade(
+        &mut self,
+        counterparty: String,
+        offered_cards: Vec<Card>,
+        requested_cards: Vec<Card>,
+    ) -> Result<Trade, String> {
+        self.ensure_not_paused()?;
+        self.ensure_creation_allowed()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before trading".to_string())?;
+
+        if counterparty == sender {
+            return Err("cannot trade with yourself".to_string());
+        }
+
+        if self.users.get(&counterparty).is_none() {
+            return Err("counterparty is not a registered user".to_string());
+        }
+
+        if offered_cards.is_empty() && requested_cards.is_empty() {
+            return Err("a trade must swap at least one card".to_string());
+        }
+
+        EscalateContractState::remove_cards_from_inventory(&mut user.cards, &offered_cards)?;
+
+        let trade = Trade::new(
+            self.next_trade_id(),
+            sender.clone(),
+            counterparty.clone(),
+            offered_cards,
+            requested_cards,
+            env::block_height(),
+        );
+        for user_id in [&sender, &counterparty] {
+            let mut ids = self.user_trades.get(user_id).unwrap_or_default();
+            ids.push(trade.trade_id.clone());
+            self.user_trades.insert(user_id.clone(), ids);
+        }
+
+        self.users.insert(sender, user);
+        self.trades.insert(trade.trade_id.clone(), trade.clone());
+        self.emit(EventKind::TradeProposed, &trade.trade_id, &[&counterparty], None);
+        Ok(trade)
+    }
+
+    #[mutate]
+    async fn accept_trade(&mut self, trade_id: String) -> Result<Trade, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut trade = self.open_trade(&trade_id)?;
+
+        if trade.counterparty_id != sender {
+            return Err("only the counterparty can accept a trade".to_string());
+        }
+
+        let mut counterparty = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user not registered".to_string())?;
+        let mut proposer = self
+            .users
+            .get(&trade.proposer_id)
+            .ok_or_else(|| "proposer not found".to_string())?;
+
+        // the counterparty makes room by giving the requested cards away
+        let (offered, requested) = (trade.offered_cards.len(), trade.requested_cards.len());
+        self.ensure_inventory_room(&counterparty, offered.saturating_sub(requested))?;
+        self.ensure_inventory_room(&proposer, requested)?;
+
+        // both sides move in the same call, so the swap is all or nothing
+        EscalateContractState::remove_cards_from_inventory(
+            &mut counterparty.cards,
+            &trade.requested_cards,
+        )?;
+        counterparty.add_cards(trade.offered_cards.clone());
+        proposer.add_cards(trade.requested_cards.clone());
+        trade.status = TradeStatus::Accepted;
+
+        self.users.insert(sender, counterparty);
+        self.users.insert(trade.proposer_id.clone(), proposer);
+        self.trades.insert(trade_id.clone(), trade.clone());
+        self.emit(EventKind::TradeAccepted, &trade_id, &[&trade.proposer_id], None);
+        Ok(trade)
+    }
+
+    #[mutate]
+    async fn reject_trade(&mut self, trade_id: String) -> Result<Trade, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut trade = self.open_trade(&trade_id)?;
+
+        // the counterparty declines, the proposer withdraws
+        if !trade.involves(&sender) {
+            return Err("only the parties to a trade can reject it".to_string());
+        }
+
+        if let Some(mut proposer) = self.users.get(&trade.proposer_id) {
+            proposer.add_cards(trade.offered_cards.clone());
+            self.users.insert(trade.proposer_id.clone(), proposer);
+        }
+        trade.status = TradeStatus::Rejected;
+
+        self.trades.insert(trade_id.clone(), trade.clone());
+        let others = [trade.proposer_id.as_str(), trade.counterparty_id.as_str()];
+        self.emit(EventKind::TradeRejected, &trade_id, &others, None);
+        Ok(trade)
+    }
+
+    #[query]
+    async fn get_my_trades(&self) -> Vec<Trade> {
+        self.user_trades
+            .get(&env::sender())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.trades.get(id))
+            .collect()
+    }
+
+    // escrows `budget` to open the bidding on future offers holding `card`
+    #[mutate]
+    async fn create_auto_bid(
+        &mut self,
+        card: Card,
+        max_bid: u64,
+        budget: u64,
+    ) -> Result<AutoBidRule, String> {
+        self.ensure_not_paused()?;
+        self.ensure_creation_allowed()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before creating auto-bids".to_string())?;
+
+        if max_bid == 0 || budget == 0 {
+            return Err("auto-bid cap and budget must be positive".to_string());
+        }
+        if user.balance < budget {
+            return Err("insufficient balance for auto-bid budget".to_string());
+        }
+
+        user.lock(budget)?;
+        let rule = AutoBidRule::new(
+            self.next_auto_bid_id(),
+            sender.clone(),
+            card,
+            max_bid,
+            budget,
+            env::block_height(),
+        );
+        let card_key = format!("{:?}", card);
+        let mut card_rules = self.card_auto_bids.get(&card_key).unwrap_or_default();
+        card_rules.push(rule.rule_id.clone());
+        let mut user_rules = self.user_auto_bids.get(&sender).unwrap_or_default();
+        user_rules.push(rule.rule_id.clone());
+
+        self.users.insert(sender.clone(), user);
+        self.card_auto_bids.insert(card_key, card_rules);
+        self.user_auto_bids.insert(sender, user_rules);
+        self.auto_bid_rules.insert(rule.rule_id.clone(), rule.clone());
+        self.emit(EventKind::AutoBidCreated, &rule.rule_id, &[], Some(budget));
+        Ok(rule)
+    }
+
+    // stops the rule and refunds what is left of its budget; bids it
+    // already placed stay in their auctions
+    #[mutate]
+    async fn cancel_auto_bid(&mut self, rule_id: String) -> Result<AutoBidRule, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut rule = self
+            .auto_bid_rules
+            .get(&rule_id)
+            .ok_or_else(|| "auto-bid rule not found".to_string())?;
+
+        if rule.owner_id != sender {
+            return Err("only the owner can cancel an auto-bid rule".to_string());
+        }
+        if !rule.is_active {
+            return Err("auto-bid rule already canceled".to_string());
+        }
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user not registered".to_string())?;
+
+        let refund = rule.budget;
+        user.unlock(refund);
+        rule.budget = 0;
+        rule.is_active = false;
+        let card_key = format!("{:?}", rule.card);
+        let mut card_rules = self.card_auto_bids.get(&card_key).unwrap_or_default();
+        card_rules.retain(|id| *id != rule_id);
+
+        self.users.insert(sender, user);
+        self.card_auto_bids.insert(card_key, card_rules);
+        self.auto_bid_rules.insert(rule_id.clone(), rule.clone());
+        self.emit(EventKind::AutoBidCanceled, &rule_id, &[], Some(refund));
+        Ok(rule)
+    }
+
+    #[query]
+    async fn get_my_auto_bids(&self) -> Vec<AutoBidRule> {
+        self.user_auto_bids
+            .get(&env::sender())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.auto_bid_rules.get(id))
+            .collect()
+    }
+
+    // the admin can run a tournament on entry fees alone, anyone else puts
+    // up a prize escrow that stays locked in their balance until it is paid
+    // out, or refunded if the tournament is cancelled before it starts
+    #[mutate]
+    async fn create_tournament(&mut self, config: TournamentConfig) -> Result<Tournament, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let height = env::block_height();
+        config.validate(height)?;
+        if config.prize_escrow == 0 && sender != self.admin {
+            return Err("a community tournament must put up a prize escrow".to_string());
+        }
+        if config.prize_escrow > 0 {
+            self.ensure_unlocked(&sender)?;
+            let mut creator = self
+                .users
+                .get(&sender)
+                .ok_or_else(|| "user must register before creating a tournament".to_string())?;
+            creator
+                .lock(config.prize_escrow)
+                .map_err(|_| "insufficient balance for the prize escrow".to_string())?;
+            self.users.insert(sender.clone(), creator);
+        }
+
+        let escrow = config.prize_escrow;
+        let tournament = Tournament::new(self.next_tournament_id(), sender, config, height);
+        self.tournaments.insert(tournament.tournament_id.clone(), tournament.clone());
+        self.record_tournament(&tournament.creator_id, &tournament.tournament_id);
+        let (id, creator_id) = (&tournament.tournament_id, tournament.creator_id.as_str());
+        self.emit(EventKind::TournamentCreated, id, &[creator_id], Some(escrow));
+        Ok(tournament)
+    }
+
+    // players pay the entry fee into the prize pool and can join until it starts
+    #[mutate]
+    async fn join_tournament(&mut self, tournament_id: String) -> Result<Tournament, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before joining a tournament".to_string())?;
+        let mut tournament = self
+            .tournaments
+            .get(&tournament_id)
+            .ok_or_else(|| "tournament not found".to_string())?;
+
+        if tournament.status != TournamentStatus::Open
+            || tournament.has_started(env::block_height())
+        {
+            return Err("tournament registration is closed".to_string());
+        }
+        if tournament.has_player(&sender) {
+            return Err("already joined this tournament".to_string());
+        }
+        if tournament.is_full() {
+            return Err("tournament is full".to_string());
+        }
+        let fee = tournament.config.entry_fee;
+        if user.balance < fee {
+            return Err("insufficient balance for the entry fee".to_string());
+        }
+
+        user.balance -= fee;
+        tournament.prize_pool += fee;
+        tournament.players.push(sender.clone());
+        self.record_tournament(&sender, &tournament_id);
+        self.users.insert(sender, user);
+        self.tournaments.insert(tournament_id.clone(), tournament.clone());
+        self.emit(EventKind::TournamentJoined, &tournament_id, &[], Some(fee));
+        Ok(tournament)
+    }
+
+    // anyone can pay out the prize pool to the top of the standings once
+    // the tournament is over; hands still open by then do not count, and
+    // entry fees are refunded when no hand was checked at all
+    #[mutate]
+    async fn finalize_tournament(&mut self, tournament_id: String) -> Result<Tournament, String> {
+        self.ensure_not_paused()?;
+        let mut tournament = self
+            .tournaments
+            .get(&tournament_id)
+            .ok_or_else(|| "tournament not found".to_string())?;
+
+        match tournament.status {
+            TournamentStatus::Finalized => return Err("tournament already finalized".to_string()),
+            TournamentStatus::Cancelled => return Err("tournament was cancelled".to_string()),
+            TournamentStatus::Open => {}
+        }
+        if !tournament.has_ended(env::block_height()) {
+            return Err("tournament has not ended yet".to_string());
+        }
+
+        let standings: Vec<Standing> = self
+            .tournament_standings(&tournament)
+            .into_iter()
+            .filter(|s| s.hands_played > 0)
+            .collect();
+        let pool = tournament.prize_pool;
+        let escrow = tournament.config.prize_escrow;
+        let payouts: Vec<(String, u64)> = if standings.is_empty() {
+            self.release_escrow(&tournament.creator_id, escrow);
+            tournament.entry_fee_refunds()
+        } else {
+            self.settle_escrow(&tournament.creator_id, escrow);
+            let shares = tournament::prize_shares(pool, &TOURNAMENT_PRIZE_SPLIT, standings.len());
+            standings.into_iter().map(|s| s.user_id).zip(shares).collect()
+        };
+        self.pay_tournament(&payouts);
+
+        tournament.status = TournamentStatus::Finalized;
+        self.tournaments.insert(tournament_id.clone(), tournament.clone());
+        let paid: Vec<&str> = payouts.iter().map(|(id, _)| id.as_str()).collect();
+        self.emit(EventKind::TournamentFinalized, &tournament_id, &paid, Some(pool));
+        Ok(tournament)
+    }
+
+    // the creator or the admin can call a tournament off until it starts,
+    // refunding every entry fee and unlocking the creator's prize escrow
+    #[mutate]
+    async fn cancel_tournament(&mut self, tournament_id: String) -> Result<Tournament, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut tournament = self
+            .tournaments
+            .get(&tournament_id)
+            .ok_or_else(|| "tournament not found".to_string())?;
+
+        if tournament.creator_id != sender && sender != self.admin {
+            return Err("only the creator or admin can cancel a tournament".to_string());
+        }
+        if tournament.status != TournamentStatus::Open
+            || tournament.has_started(env::block_height())
+        {
+            return Err("a tournament can only be cancelled before it starts".to_string());
+        }
+
+        let refunds = tournament.entry_fee_refunds();
+        self.pay_tournament(&refunds);
+        self.release_escrow(&tournament.creator_id, tournament.config.prize_escrow);
+        tournament.status = TournamentStatus::Cancelled;
+        self.tournaments.insert(tournament_id.clone(), tournament.clone());
+        let refunded: Vec<&str> = refunds.iter().map(|(id, _)| id.as_str()).collect();
+        let pool = Some(tournament.prize_pool);
+        self.emit(EventKind::TournamentCancelled, &tournament_id, &refunded, pool);
+        Ok(tournament)
+    }
+
+    #[query]
+    async fn get_tournament(&self, tournament_id: String) -> Option<Tournament> {
+        self.tournaments.get(&tournament_id)
+    }
+
+    #[query]
+    async fn get_tournament_standings(
+        &self,
+        tournament_id: String,
+    ) -> Result<Vec<Standing>, String> {
+        let tournament = self
+            .tournaments
+            .get(&tournament_id)
+            .ok_or_else(|| "tournament not found".to_string())?;
+        Ok(self.tournament_standings(&tournament))
+    }
+
+    #[mutate]
+    async fn start_season(&mut self, config: SeasonConfig) -> Result<Season, String> {
+        self.ensure_admin(&env::sender())?;
+        if self.current_season.is_some() {
+            return Err("end the running season before starting another".to_string());
+        }
+        config.validate()?;
+
+        let season_id = self.next_season_id();
+        let season = Season::new(season_id.clone(), config, env::block_height());
+        self.seasons.insert(season_id.clone(), season.clone());
+        self.season_ids.push(season_id.clone());
+        self.current_season = Some(season_id.clone());
+        self.emit(EventKind::SeasonStarted, &season_id, &[], None);
+        Ok(season)
+    }
+
+    #[mutate]
+    async fn end_season(&mut self) -> Result<Season, String> {
+        self.ensure_admin(&env::sender())?;
+        let season_id = self
+            .current_season
+            .clone()
+            .ok_or_else(|| "no season is running".to_string())?;
+        let mut season = self
+            .seasons
+            .get(&season_id)
+            .ok_or_else(|| "season not found".to_string())?;
+
+        let metric = season.config.metric;
+        let standings = self.season_standings(&season);
+        let ranked = standings.iter().filter(|s| s.metric(metric) > 0);
+        for (rank, (stats, prize)) in ranked.zip(season.config.prizes.clone()).enumerate() {
+            let amount = prize.min(self.treasury);
+            if amount == 0 {
+                continue;
+            }
+            self.treasury -= amount;
+            self.apply_payouts(vec![(stats.user_id.clone(), amount as i64)]);
+            season.prizes_paid.push(SeasonPrize {
+                user_id: stats.user_id.clone(),
+                rank: rank as u32 + 1,
+                amount,
+            });
+        }
+
+        self.pay_referral_rewards(&mut season);
+        season.ended_at_block = Some(env::block_height());
+        self.current_season = None;
+        // the archive keeps the records, the per player stats can go
+        let archive = SeasonArchive::new(&season, standings);
+        for player in &season.players {
+            self.season_stats.remove(&season::stats_key(&season_id, player));
+        }
+        self.season_archives.insert(season_id.clone(), archive);
+        self.seasons.insert(season_id.clone(), season.clone());
+        let winners: Vec<&str> = season.prizes_paid.iter().map(|p| p.user_id.as_str()).collect();
+        let paid = season.prizes_paid.iter().map(|p| p.amount).sum();
+        self.emit(EventKind::SeasonEnded, &season_id, &winners, Some(paid));
+        Ok(season)
+    }
+
+    #[query]
+    async fn get_current_season(&self) -> Option<Season> {
+        self.current_season.as_ref().and_then(|id| self.seasons.get(id))
+    }
+
+    #[query]
+    async fn get_seasons(&self) -> Vec<Season> {
+        self.season_ids.iter().filter_map(|id| self.seasons.get(id)).collect()
+    }
+
+    #[query]
+    async fn get_season_standings(
+        &self,
+        season_id: String,
+        limit: u64,
+    ) -> Result<Vec<UserStats>, String> {
+        let season = self
+            .seasons
+            .get(&season_id)
+            .ok_or_else(|| "season not found".to_string())?;
+        let mut standings = match self.season_archives.get(&season_id) {
+            Some(archive) => archive.standings,
+            None => self.season_standings(&season),
+        };
+        standings.truncate(limit.min(MAX_PAGE_LIMIT) as usize);
+        Ok(standings)
+    }
+
+    #[query]
+    async fn get_season_archive(&self, season_id: String) -> Option<SeasonArchive> {
+        self.season_archives.get(&season_id)
+    }
+
+    #[mutate]
+    async fn add_friend(&mut self, friend_id: String) -> Result<Vec<String>, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        if self.users.get(&sender).is_none() {
+            return Err("user must register before adding friends".to_string());
+        }
+        if friend_id == sender {
+            return Err("cannot add yourself as a friend".to_string());
+        }
+        if self.users.get(&friend_id).is_none() {
+            return Err("friend is not a registered user".to_string());
+        }
+        let mut friends = self.friends.get(&sender).unwrap_or_default();
+        if friends.contains(&friend_id) {
+            return Err("already on the friend list".to_string());
+        }
+        if friends.len() >= MAX_FRIENDS {
+            return Err(format!("friend lists are capped at {} users", MAX_FRIENDS));
+        }
+
+        friends.push(friend_id.clone());
+        self.friends.insert(sender, friends.clone());
+        self.emit(EventKind::FriendAdded, &friend_id, &[&friend_id], None);
+        Ok(friends)
+    }
+
+    #[mutate]
+    async fn remove_friend(&mut self, friend_id: String) -> Result<Vec<String>, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut friends = self.friends.get(&sender).unwrap_or_default();
+        if !friends.contains(&friend_id) {
+            return Err("not on the friend list".to_string());
+        }
+
+        friends.retain(|f| *f != friend_id);
+        self.friends.insert(sender, friends.clone());
+        self.emit(EventKind::FriendRemoved, &friend_id, &[], None);
+        Ok(friends)
+    }
+
+    #[query]
+    async fn get_friends(&self) -> Vec<String> {
+        self.friends.get(&env::sender()).unwrap_or_default()
+    }
+
+    // escrows the opening stake, claiming every card is `claim`, until the
+    // friend answers
+    #[mutate]
+    async fn challenge(
+        &mut self,
+        friend_id: String,
+        claim: Card,
+        cards: Vec<Card>,
+    ) -> Result<Challenge, String> {
+        self.ensure_not_paused()?;
+        self.ensure_creation_allowed()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before challenging".to_string())?;
+
+        if !self.friends.get(&sender).unwrap_or_default().contains(&friend_id) {
+            return Err("only friends can be challenged".to_string());
+        }
+        if cards.is_empty() {
+            return Err("a challenge needs an opening stake".to_string());
+        }
+        self.ensure_stake_size(&cards)?;
+        EscalateContractState::remove_cards_from_inventory(&mut user.cards, &cards)?;
+
+        let challenge = Challenge::new(
+            self.next_challenge_id(),
+            sender.clone(),
+            friend_id.clone(),
+            claim,
+            cards,
+            env::block_height().saturating_add(CHALLENGE_EXPIRY_BLOCKS),
+        );
+        for user_id in [&sender, &friend_id] {
+            let mut ids = self.user_challenges.get(user_id).unwrap_or_default();
+            ids.push(challenge.challenge_id.clone());
+            self.user_challenges.insert(user_id.clone(), ids);
+        }
+
+        self.users.insert(sender, user);
+        self.challenges.insert(challenge.challenge_id.clone(), challenge.clone());
+        let id = challenge.challenge_id.clone();
+        self.emit(EventKind::ChallengeIssued, &id, &[&friend_id], None);
+        Ok(challenge)
+    }
+
+    // opens the private hand with the challenger's escrowed stake, the friend
+    // acts next
+    #[mutate]
+    async fn accept_challenge(&mut self, challenge_id: String) -> Result<Hand, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut challenge = self.pending_challenge(&challenge_id)?;
+
+        if challenge.friend_id != sender {
+            return Err("only the challenged friend can accept".to_string());
+        }
+        if challenge.is_expired(env::block_height()) {
+            return Err("challenge expired".to_string());
+        }
+
+        // the escrowed stake goes back to the challenger for the hand to take it
+        let challenger_id = challenge.challenger_id.clone();
+        let mut challenger = self
+            .users
+            .get(&challenger_id)
+            .ok_or_else(|| "challenger not registered anymore".to_string())?;
+        challenger.add_cards(challenge.cards.clone());
+        self.users.insert(challenger_id.clone(), challenger.clone());
+
+        let options = challenge.hand_options(CHALLENGE_HAND_BLOCKS);
+        let count = challenge.cards.len() as u32;
+        let cards = challenge.cards.clone();
+        let opened =
+            self.open_hand_as(challenger_id.clone(), challenge.claim, count, cards, options);
+        let hand = match opened {
+            Ok(hand) => hand,
+            Err(e) => {
+                EscalateContractState::remove_cards_from_inventory(
+                    &mut challenger.cards,
+                    &challenge.cards,
+                )?;
+                self.users.insert(challenger_id, challenger);
+                return Err(e);
+            }
+        };
+
+        challenge.status = ChallengeStatus::Accepted;
+        challenge.hand_id = Some(hand.hand_id.clone());
+        self.challenges.insert(challenge_id.clone(), challenge);
+        self.emit(EventKind::ChallengeAccepted, &challenge_id, &[&challenger_id], None);
+        Ok(hand)
+    }
+
+    // the friend declines or the challenger withdraws; once expired the
+    // challenger reclaims the stake the same way
+    #[mutate]
+    async fn decline_challenge(&mut self, challenge_id: String) -> Result<Challenge, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut challenge = self.pending_challenge(&challenge_id)?;
+
+        if !challenge.involves(&sender) {
+            return Err("only the challenger or the friend can decline".to_string());
+        }
+
+        if let Some(mut challenger) = self.users.get(&challenge.challenger_id) {
+            challenger.add_cards(challenge.cards.clone());
+            self.users.insert(challenge.challenger_id.clone(), challenger);
+        }
+        challenge.status = if challenge.is_expired(env::block_height()) {
+            ChallengeStatus::Expired
+        } else {
+            ChallengeStatus::Declined
+        };
+
+        self.challenges.insert(challenge_id.clone(), challenge.clone());
+        let others = [challenge.challenger_id.as_str(), challenge.friend_id.as_str()];
+        self.emit(EventKind::ChallengeDeclined, &challenge_id, &others, None);
+        Ok(challenge)
+    }
+
+    #[query]
+    async fn get_my_challenges(&self) -> Vec<Challenge> {
+        self.user_challenges
+            .get(&env::sender())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.challenges.get(id))
+            .collect()
+    }
+
+    #[mutate]
+    async fn join_queue(&mut self, stake_size: u32) -> Result<Option<Hand>, String> {
+        self.ensure_not_paused()?;
+        self.ensure_creation_allowed()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before joining the queue".to_string())?;
+
+        if stake_size == 0 || stake_size > self.params.max_stake_cards {
+            return Err(format!(
+                "stake size must be between 1 and {}",
+                self.params.max_stake_cards
+            ));
+        }
+        if matchmaking::opening_stake(&user.cards, stake_size).is_none() {
+            return Err("not enough cards for the opening stake".to_string());
+        }
+        if self.match_queue.iter().any(|e| e.user_id == sender) {
+            return Err("already waiting in the queue".to_string());
+        }
+
+        let entry = QueueEntry::new(sender.clone(), stake_size, env::block_height());
+        self.match_queue.push(entry);
+        self.emit(EventKind::QueueJoined, &sender, &[], Some(stake_size as u64));
+        let hands = self.pair_queued_players();
+        Ok(hands
+            .into_iter()
+            .find(|h| h.creator == sender || h.allowed_stakers.contains(&sender)))
+    }
+
+    #[mutate]
+    async fn leave_queue(&mut self) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let pos = self
+            .match_queue
+            .iter()
+            .position(|e| e.user_id == sender)
+            .ok_or_else(|| "not waiting in the queue".to_string())?;
+        self.match_queue.remove(pos);
+        self.emit(EventKind::QueueLeft, &sender, &[], None);
+        Ok(())
+    }
+
+    #[mutate]
+    async fn match_players(&mut self) -> Result<Vec<Hand>, String> {
+        self.ensure_not_paused()?;
+        self.ensure_creation_allowed()?;
+        Ok(self.pair_queued_players())
+    }
+
+    #[query]
+    async fn get_match_queue(&self) -> Vec<QueueEntry> {
+        self.match_queue.clone()
+    }
+
+    #[mutate]
+    async fn deposit(&mut self, amount: u64) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        // TODO: add the ability for users to deposit
+        // some amount
+
+        Err("Deposit is not allowed yet".into())
+        // let sender = env::sender();
+        // let mut user = self
+        //     .users
+        //     .get(&sender)
+        //     .unwrap_or_else(|| User::new(sender.clone(), "".to_string()));
+
+        // if amount == 0 {
+        //     return Err("deposit amount must be positive".to_string());
+        // }
+
+        // user.balance += amount;
+        // if self.users.get(&sender).is_none() {
+        //     self.user_ids.push(sender.clone());
+        // }
+        // self.users.insert(sender.clone(), user);
+        // self.emit(EventKind::Deposited, &sender, &[], Some(amount));
+        // Ok(())
+    }
+
+    #[mutate]
+    async fn withdraw(&mut self, amount: u64) -> Result<TransferIntent, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before withdrawing".to_string())?;
+
+        if amount == 0 {
+            return Err("withdrawal amount must be positive".to_string());
+        }
+
+        // outstanding penalties have to be repaid before anything leaves
+        if self.debts.get(&sender).unwrap_or(0) > 0 {
+            return Err("repay your debt before withdrawing".to_string());
+        }
+
+        // bids, side bets and installments are escrowed outside the balance,
+        // so only the part above the floor is free to leave
+        if user.balance.saturating_sub(self.params.balance_floor) < amount {
+            return Err("insufficient unlocked balance for withdrawal".to_string());
+        }
+
+        // the amount stays in escrow on the intent until the transfer is
+        // reported on, so a failed transfer can always be reclaimed
+        let intent = TransferIntent::new(
+            self.next_transfer_id(),
+            sender.clone(),
+            amount,
+            env::block_height(),
+            TRANSFER_TIMEOUT_BLOCKS,
+        );
+        user.balance -= amount;
+
+        let mut ids = self.user_transfers.get(&sender).unwrap_or_default();
+        ids.push(intent.intent_id.clone());
+
+        self.users.insert(sender.clone(), user);
+        self.user_transfers.insert(sender, ids);
+        self.transfer_intents.insert(intent.intent_id.clone(), intent.clone());
+        self.emit(EventKind::Withdrawn, &intent.intent_id, &[], Some(amount));
+        Ok(intent)
+    }
+
+    // the runtime cannot report back on external transfers yet, so the
+    // admin relays whether each one went through
+    #[mutate]
+    async fn settle_transfer(
+        &mut self,
+        intent_id: String,
+        succeeded: bool,
+    ) -> Result<TransferIntent, String> {
+        self.ensure_admin(&env::sender())?;
+        let mut intent = self.pending_transfer(&intent_id)?;
+
+        let kind = if succeeded {
+            intent.status = TransferStatus::Completed;
+            self.total_supply -= intent.amount;
+            EventKind::TransferCompleted
+        } else {
+            intent.status = TransferStatus::Failed;
+            EventKind::TransferFailed
+        };
+
+        self.transfer_intents.insert(intent_id.clone(), intent.clone());
+        self.emit(kind, &intent_id, &[&intent.user_id], Some(intent.amount));
+        Ok(intent)
+    }
+
+    #[mutate]
+    async fn reclaim_failed_transfer(
+        &mut self,
+        intent_id: String,
+    ) -> Result<TransferIntent, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut intent = self
+            .transfer_intents
+            .get(&intent_id)
+            .ok_or_else(|| "transfer not found".to_string())?;
+
+        if intent.user_id != sender {
+            return Err("only the requester can reclaim a transfer".to_string());
+        }
+
+        if !intent.is_reclaimable(env::block_height()) {
+            return Err("transfer has not failed or timed out".to_string());
+        }
+
+        if self.users.get(&sender).is_none() {
+            return Err("user not registered".to_string());
+        }
+        // the reclaimed chips repay any debt taken on while they were away
+        self.apply_payouts(vec![(sender, intent.amount as i64)]);
+        intent.status = TransferStatus::Reclaimed;
+
+        self.transfer_intents.insert(intent_id.clone(), intent.clone());
+        self.emit(EventKind::TransferReclaimed, &intent_id, &[], Some(intent.amount));
+        Ok(intent)
+    }
+
+    #[query]
+    async fn get_my_transfers(&self) -> Vec<TransferIntent> {
+        self.user_transfers
+            .get(&env::sender())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.transfer_intents.get(id))
+            .collect()
+    }
+
+    #[query]
+    async fn get_transfer(&self, intent_id: String) -> Option<TransferIntent> {
+        self.transfer_intents.get(&intent_id)
+    }
+
+    #[mutate]
+    async fn enable_cold_storage(&mut self) -> Result<User, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user not registered".to_string())?;
+
+        // re-enabling also cancels any unlock that was pending
+        user.cold_storage = true;
+        user.unlock_at_block = None;
+
+        self.users.insert(sender.clone(), user.clone());
+        self.emit(EventKind::ColdStorageEnabled, &sender, &[], None);
+        Ok(user)
+    }
+
+    // high rollers may risk more than the per-hand loss limit allows
+    #[mutate]
+    async fn set_high_roller(&mut self, enabled: bool) -> Result<User, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user not registered".to_string())?;
+
+        user.high_roller = enabled;
+        self.users.insert(sender.clone(), user.clone());
+        self.emit(EventKind::HighRollerChanged, &sender, &[], None);
+        Ok(user)
+    }
+
+    // replaces the caller's guardians, unless a recovery is underway
+    #[mutate]
+    async fn set_recovery_guardians(
+        &mut self,
+        guardians: Vec<String>,
+        threshold: u32,
+    ) -> Result<RecoveryGuardians, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        if self.users.get(&sender).is_none() {
+            return Err("user not registered".to_string());
+        }
+        if self.pending_recovery(&sender).is_some() {
+            return Err("veto the pending recovery before changing guardians".to_string());
+        }
+        let setup = RecoveryGuardians { guardians, threshold };
+        setup.validate(&sender, MAX_RECOVERY_GUARDIANS)?;
+        if let Some(g) = setup.guardians.iter().find(|g| self.users.get(g).is_none()) {
+            return Err(format!("guardian {} is not a registered user", g));
+        }
+
+        self.recovery_guardians.insert(sender.clone(), setup.clone());
+        self.emit(EventKind::RecoveryGuardiansSet, &sender, &[], None);
+        Ok(setup)
+    }
+
+    // a guardian starts or approves moving `old_id` to the unregistered
+    // `new_id`; the last approval needed opens the veto window
+    #[mutate]
+    async fn recover_account(
+        &mut self,
+        old_id: String,
+        new_id: String,
+    ) -> Result<RecoveryRequest, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let setup = self
+            .recovery_guardians
+            .get(&old_id)
+            .ok_or_else(|| "account has no recovery guardians".to_string())?;
+
+        if !setup.is_guardian(&sender) {
+            return Err("only a guardian of the account can recover it".to_string());
+        }
+        if self.users.get(&new_id).is_some() {
+            return Err("the new key is already a registered user".to_string());
+        }
+        let height = env::block_height();
+        let mut request = match self.pending_recovery(&old_id) {
+            Some(r) if r.new_id != new_id => {
+                return Err(format!("a recovery to {} is already pending", r.new_id));
+            }
+            Some(r) => r,
+            None => RecoveryRequest::new(old_id.clone(), new_id, height),
+        };
+        if request.approvals.contains(&sender) {
+            return Err("guardian already approved this recovery".to_string());
+        }
+
+        request.approve(sender, setup.threshold, height, RECOVERY_CHALLENGE_BLOCKS);
+        self.recoveries.insert(old_id.clone(), request.clone());
+        self.emit(EventKind::RecoveryApproved, &old_id, &[&old_id], None);
+        Ok(request)
+    }
+
+    // the original key stops a recovery of its account before it completes
+    #[mutate]
+    async fn veto_recovery(&mut self) -> Result<RecoveryRequest, String> {
+        let sender = env::sender();
+        let mut request = self
+            .pending_recovery(&sender)
+            .ok_or_else(|| "no recovery is pending for this account".to_string())?;
+
+        request.status = RecoveryStatus::Vetoed;
+        self.recoveries.insert(sender.clone(), request.clone());
+        let guardians: Vec<&str> = request.approvals.iter().map(|g| g.as_str()).collect();
+        self.emit(EventKind::RecoveryVetoed, &sender, &guardians, None);
+        Ok(request)
+    }
+
+    // anyone can complete a recovery once its veto window has passed
+    #[mutate]
+    async fn complete_recovery(&mut self, old_id: String) -> Result<User, String> {
+        self.ensure_not_paused()?;
+        let mut request = self
+            .pending_recovery(&old_id)
+            .ok_or_else(|| "no recovery is pending for this account".to_string())?;
+
+        if !request.is_executable(env::block_height()) {
+            return Err("recovery is still waiting for approvals or its veto window".to_string());
+        }
+        if self.users.get(&request.new_id).is_some() {
+            return Err("the new key registered in the meantime".to_string());
+        }
+        if let Some(kind) = self.users.get(&old_id).and_then(|u| self.open_position(&u)) {
+            return Err(format!("settle the account's {} before recovering it", kind));
+        }
+        let user = self
+            .migrate_account(&old_id, &request.new_id)
+            .ok_or_else(|| "account to recover not found".to_string())?;
+
+        request.status = RecoveryStatus::Completed;
+        self.recoveries.insert(old_id.clone(), request.clone());
+        self.emit(EventKind::AccountRecovered, &old_id, &[&request.new_id], None);
+        Ok(user)
+    }
+
+    #[query]
+    async fn get_recovery_guardians(&self, user_id: String) -> Option<RecoveryGuardians> {
+        self.recovery_guardians.get(&user_id)
+    }
+
+    #[query]
+    async fn get_recovery(&self, user_id: String) -> Option<RecoveryRequest> {
+        self.recoveries.get(&user_id)
+    }
+
+    #[mutate]
+    async fn request_unlock(&mut self) -> Result<User, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user not registered".to_string())?;
+
+        if !user.cold_storage {
+            return Err("account is not in cold storage".to_string());
+        }
+
+        if user.unlock_at_block.is_some() {
+            return Err("unlock already requested".to_string());
+        }
+
+        let unlock_at = env::block_height().saturating_add(COLD_STORAGE_UNLOCK_BLOCKS);
+        user.unlock_at_block = Some(unlock_at);
+
+        self.users.insert(sender.clone(), user.clone());
+        self.emit(EventKind::UnlockRequested, &sender, &[], None);
+        Ok(user)
+    }
+
+    #[mutate]
+    async fn propose_side_bet(
+        &mut self,
+        opponent: String,
+        hand_id: String,
+        predicts_bluff: bool,
+        amount: u64,
+    ) -> Result<SideBet, String> {
+        self.ensure_not_paused()?;
+        self.ensure_creation_allowed()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut proposer = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before proposing a side bet".to_string())?;
+
+        if opponent == sender {
+            return Err("cannot propose a side bet against yourself".to_string());
+        }
+
+        if self.users.get(&opponent).is_none() {
+            return Err("opponent not registered".to_string());
+        }
+
+        let hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found for side bet".to_string())?;
+
+        if hand.is_resolved() || hand.is_expired(env::block_height()) {
+            return Err("cannot bet on a resolved or expired hand".to_string());
+        }
+
+        if amount == 0 {
+            return Err("side bet amount must be positive".to_string());
+        }
+
+        if proposer.balance < amount {
+            return Err("insufficient balance for side bet".to_string());
+        }
+
+        proposer.lock(amount)?;
+
+        let bet_id = self.next_side_bet_id();
+        let bet = SideBet::new(
+            bet_id.clone(),
+            hand_id.clone(),
+            sender.clone(),
+            opponent.clone(),
+            predicts_bluff,
+            amount,
+        );
+
+        let mut bets = self.side_bets.get(&hand_id).unwrap_or_default();
+        bets.push(bet.clone());
+
+        self.users.insert(sender, proposer);
+        self.side_bets.insert(hand_id.clone(), bets);
+        self.side_bet_hand_ids.insert(bet_id.clone(), hand_id);
+        self.emit(EventKind::SideBetProposed, &bet_id, &[&opponent], Some(amount));
+
+        Ok(bet)
+    }
+
+    #[mutate]
+    async fn accept_side_bet(&mut self, bet_id: String) -> Result<SideBet, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut opponent = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before accepting a side bet".to_string())?;
+
+        let hand_id = self
+            .side_bet_hand_ids
+            .get(&bet_id)
+            .ok_or_else(|| "side bet not found".to_string())?;
+
+        let hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found for side bet".to_string())?;
+
+        if hand.is_resolved() || hand.is_expired(env::block_height()) {
+            return Err("cannot accept a side bet on a resolved or expired hand".to_string());
+        }
+
+        let mut bets = self.side_bets.get(&hand_id).unwrap_or_default();
+        let bet = bets
+            .iter_mut()
+            .find(|b| b.bet_id == bet_id)
+            .ok_or_else(|| "side bet not found".to_string())?;
+
+        if bet.opponent_id != sender {
+            return Err("only the named opponent can accept a side bet".to_string());
+        }
+
+        if bet.is_accepted {
+            return Err("side bet already accepted".to_string());
+        }
+
+        if opponent.balance < bet.amount {
+            return Err("insufficient balance for side bet".to_string());
+        }
+
+        opponent.lock(bet.amount)?;
+        bet.is_accepted = true;
+        let accepted = bet.clone();
+
+        self.users.insert(sender, opponent);
+        self.side_bets.insert(hand_id, bets);
+        self.emit(
+            EventKind::SideBetAccepted,
+            &bet_id,
+            &[&accepted.proposer_id],
+            Some(accepted.amount),
+        );
+
+        Ok(accepted)
+    }
+
+    #[query]
+    async fn get_side_bets(&self, hand_id: String) -> Vec<SideBet> {
+        self.side_bets.get(&hand_id).unwrap_or_default()
+    }
+
+    // wagers into the hand's parimutuel pool, open to everyone but its players
+    #[mutate]
+    async fn place_side_bet(
+        &mut self,
+        hand_id: String,
+        predicts_bluff: bool,
+        amount: u64,
+    ) -> Result<PoolWager, String> {
+        self.ensure_not_paused()?;
+        self.ensure_creation_allowed()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let mut user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user must register before placing a side bet".to_string())?;
+
+        let hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found for side bet".to_string())?;
+
+        if hand.is_resolved() || hand.is_expired(env::block_height()) {
+            return Err("cannot bet on a resolved or expired hand".to_string());
+        }
+        if self.is_player(&hand, &sender) {
+            return Err("players cannot wager in the spectator pool of their hand".to_string());
+        }
+        if !hand.admits(&sender) {
+            return Err("hand is private and the caller is not invited".to_string());
+        }
+        if amount == 0 {
+            return Err("side bet amount must be positive".to_string());
+        }
+        if user.balance < amount {
+            return Err("insufficient balance for side bet".to_string());
+        }
+
+        user.lock(amount)?;
+        let mut pool = self
+            .spectator_pools
+            .get(&hand_id)
+            .unwrap_or_else(|| SpectatorPool::new(hand_id.clone()));
+        pool.place(sender.clone(), predicts_bluff, amount, env::block_height());
+        let wager = pool.wagers.last().cloned().expect("a wager was just placed");
+
+        self.users.insert(sender, user);
+        self.spectator_pools.insert(hand_id.clone(), pool);
+        self.emit(EventKind::SpectatorBetPlaced, &hand_id, &[&hand.creator], Some(amount));
+        Ok(wager)
+    }
+
+    #[query]
+    async fn get_spectator_pool(&self, hand_id: String) -> Option<SpectatorPool> {
+        self.spectator_pools.get(&hand_id)
+    }
+
+    // players of the hand only, also after it ended
+    #[mutate]
+    async fn post_hand_message(
+        &mut self,
+        hand_id: String,
+        text: String,
+    ) -> Result<HandMessage, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found".to_string())?;
+
+        if !self.is_player(&hand, &sender) {
+            return Err("only players of the hand can post to its table".to_string());
+        }
+        if self.hand_mutes.get(&hand_id).is_some_and(|muted| muted.contains(&sender)) {
+            return Err("you are muted at this table".to_string());
+        }
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            return Err("message cannot be empty".to_string());
+        }
+        if text.chars().count() > MAX_HAND_MESSAGE_LENGTH {
+            return Err(format!(
+                "messages are capped at {} characters",
+                MAX_HAND_MESSAGE_LENGTH
+            ));
+        }
+        let mut ids = self.hand_message_ids.get(&hand_id).unwrap_or_default();
+        let posted: Vec<HandMessage> = ids
+            .iter()
+            .filter_map(|i| self.hand_messages.get(*i as usize))
+            .filter(|m| m.author_id == sender)
+            .collect();
+        if posted.len() >= MAX_HAND_MESSAGES_PER_USER {
+            return Err(format!(
+                "players can post at most {} messages per hand",
+                MAX_HAND_MESSAGES_PER_USER
+            ));
+        }
+        let height = env::block_height();
+        if posted
+            .last()
+            .is_some_and(|m| height < m.posted_at_block + HAND_MESSAGE_COOLDOWN_BLOCKS)
+        {
+            return Err(format!(
+                "wait {} blocks between messages at a table",
+                HAND_MESSAGE_COOLDOWN_BLOCKS
+            ));
+        }
+
+        let message_id = self.hand_messages.len() as u64;
+        let message = HandMessage {
+            message_id,
+            hand_id: hand_id.clone(),
+            author_id: sender,
+            text,
+            posted_at_block: height,
+            flagged: false,
+        };
+        ids.push(message_id);
+        self.hand_messages.push(message.clone());
+        self.hand_message_ids.insert(hand_id.clone(), ids);
+        self.emit(EventKind::HandMessagePosted, &hand_id, &[], None);
+        Ok(message)
+    }
+
+    // oldest first; private hands only show their table to who may play them
+    #[query]
+    async fn get_hand_messages(
+        &self,
+        hand_id: String,
+        offset: u64,
+        limit: u64,
+    ) -> Result<HandMessagePage, String> {
+        let hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found".to_string())?;
+        if !hand.admits(&env::sender()) {
+            return Err("hand not found".to_string());
+        }
+        let ids = self.hand_message_ids.get(&hand_id).unwrap_or_default();
+        let items = ids
+            .iter()
+            .skip(offset as usize)
+            .take(limit.min(MAX_PAGE_LIMIT) as usize)
+            .filter_map(|i| self.hand_messages.get(*i as usize))
+            .collect();
+        Ok(PageResult { items, total_count: ids.len() as u64 })
+    }
+
+    // the creator keeps the table usable in public games; a muted player
+    // still plays the hand, they just cannot post to it
+    #[mutate]
+    async fn mute_in_hand(&mut self, hand_id: String, user_id: String) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let hand = self
+            .hands
+            .get(&hand_id)
+            .ok_or_else(|| "hand not found".to_string())?;
+
+        if hand.creator != sender {
+            return Err("only the hand creator can mute players".to_string());
+        }
+        if user_id == sender {
+            return Err("cannot mute yourself".to_string());
+        }
+        if !self.is_player(&hand, &user_id) {
+            return Err("only players of the hand can be muted".to_string());
+        }
+        let mut muted = self.hand_mutes.get(&hand_id).unwrap_or_default();
+        if !muted.contains(&user_id) {
+            muted.push(user_id.clone());
+            self.hand_mutes.insert(hand_id.clone(), muted);
+            self.emit(EventKind::HandPlayerMuted, &hand_id, &[&user_id], None);
+        }
+        Ok(())
+    }
+
+    #[mutate]
+    async fn flag_hand_message(
+        &mut self,
+        message_id: u64,
+        flagged: bool,
+    ) -> Result<HandMessage, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        if sender != self.admin && !self.moderators.contains(&sender) {
+            return Err("only moderators can flag messages".to_string());
+        }
+        let mut message = self
+            .hand_messages
+            .get(message_id as usize)
+            .ok_or_else(|| "message not found".to_string())?;
+
+        message.flagged = flagged;
+        self.hand_messages.set(message_id as usize, message.clone());
+        let author_id = message.author_id.clone();
+        self.emit(EventKind::HandMessageFlagged, &message.hand_id, &[&author_id], None);
+        Ok(message)
+    }
+
+    #[mutate]
+    async fn set_moderator(&mut self, user_id: String, is_moderator: bool) -> Result<(), String> {
+        self.ensure_admin(&env::sender())?;
+        if is_moderator == self.moderators.contains(&user_id) {
+            return Ok(());
+        }
+        if is_moderator {
+            self.moderators.push(user_id.clone());
+        } else {
+            self.moderators.retain(|m| *m != user_id);
+        }
+        self.emit(EventKind::ModeratorSet, &user_id, &[&user_id], None);
+        Ok(())
+    }
+
+    #[query]
+    async fn get_moderators(&self) -> Vec<String> {
+        self.moderators.clone()
+    }
+
+    #[mutate]
+    async fn set_successor(
+        &mut self,
+        successor: String,
+        freeze_creation: bool,
+    ) -> Result<Succession, String> {
+        self.ensure_admin(&env::sender())?;
+
+        if successor.is_empty() {
+            return Err("successor address must not be empty".to_string());
+        }
+
+        let succession = Succession {
+            successor,
+            announced_at_block: env::block_height(),
+            creation_frozen: freeze_creation,
+        };
+        self.successor = Some(succession.clone());
+        self.emit(EventKind::SuccessorAnnounced, &succession.successor, &[], None);
+        Ok(succession)
+    }
+
+    #[query]
+    async fn get_successor(&self) -> Option<Succession> {
+        self.successor.clone()
+    }
+
+    #[mutate]
+    async fn pause(&mut self) -> Result<(), String> {
+        self.ensure_admin(&env::sender())?;
+
+        if self.paused {
+            return Err("contract is already paused".to_string());
+        }
+        self.paused = true;
+        self.emit(EventKind::Paused, "", &[], None);
+        Ok(())
+    }
+
+    #[mutate]
+    async fn unpause(&mut self) -> Result<(), String> {
+        self.ensure_admin(&env::sender())?;
+
+        if !self.paused {
+            return Err("contract is not paused".to_string());
+        }
+        self.paused = false;
+        self.emit(EventKind::Unpaused, "", &[], None);
+        Ok(())
+    }
+
+    #[query]
+    async fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // replaces any window scheduled before
+    #[mutate]
+    async fn schedule_maintenance(
+        &mut self,
+        start_block: u64,
+        end_block: u64,
+    ) -> Result<MaintenanceWindow, String> {
+        self.ensure_admin(&env::sender())?;
+
+        if start_block >= end_block {
+            return Err("maintenance must end after it starts".to_string());
+        }
+        if end_block <= env::block_height() {
+            return Err("maintenance window is already over".to_string());
+        }
+        let window = MaintenanceWindow {
+            start_block,
+            end_block,
+        };
+        self.maintenance = Some(window.clone());
+        self.emit(EventKind::MaintenanceScheduled, "", &[], Some(end_block));
+        Ok(window)
+    }
+
+    #[mutate]
+    async fn cancel_maintenance(&mut self) -> Result<(), String> {
+        self.ensure_admin(&env::sender())?;
+
+        if self.maintenance.take().is_none() {
+            return Err("no maintenance is scheduled".to_string());
+        }
+        self.emit(EventKind::MaintenanceCanceled, "", &[], None);
+        Ok(())
+    }
+
+    #[query]
+    async fn health(&self) -> Health {
+        let height = env::block_height();
+        let maintenance = self.maintenance.clone().filter(|w| height < w.end_block);
+        Health {
+            block_height: height,
+            paused: self.paused,
+            in_maintenance: maintenance.as_ref().is_some_and(|w| w.is_active(height)),
+            maintenance,
+        }
+    }
+
+    #[query]
+    async fn describe(&self) -> Vec<EntrypointSchema> {
+        describe::catalog()
+    }
+
+    // takes effect for everything that happens afterwards, hands and
+    // balances that already exist are left as they are
+    #[mutate]
+    async fn set_params(&mut self, params: GameParams) -> Result<GameParams, String> {
+        self.ensure_admin(&env::sender())?;
+
+        params.validate()?;
+        self.params = params.clone();
+        self.emit(EventKind::ParamsUpdated, "", &[], None);
+        Ok(params)
+    }
+
+    #[query]
+    async fn get_params(&self) -> GameParams {
+        self.params.clone()
+    }
+
+    #[mutate]
+    async fn link_external_identity(
+        &mut self,
+        platform: String,
+        handle: String,
+        proof: String,
+    ) -> Result<ExternalIdentity, String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        if self.users.get(&sender).is_none() {
+            return Err("user must register before linking an identity".to_string());
+        }
+
+        if platform.is_empty() || handle.is_empty() {
+            return Err("platform and handle must not be empty".to_string());
+        }
+
+        if proof.is_empty() {
+            return Err("a proof is required to link an identity".to_string());
+        }
+
+        // stays unverified until the admin has checked the proof
+        let identity = ExternalIdentity {
+            platform,
+            handle,
+            proof,
+            is_verified: false,
+            linked_at_block: env::block_height(),
+        };
+
+        let mut profile = self
+            .identities
+            .get(&sender)
+            .unwrap_or_else(IdentityProfile::new);
+        profile.link(identity.clone());
+        self.identities.insert(sender, profile);
+        self.emit(EventKind::IdentityLinked, &identity.platform, &[], None);
+
+        Ok(identity)
+    }
+
+    #[mutate]
+    async fn verify_external_identity(
+        &mut self,
+        user_id: String,
+        platform: String,
+    ) -> Result<ExternalIdentity, String> {
+        self.ensure_admin(&env::sender())?;
+
+        let mut profile = self
+            .identities
+            .get(&user_id)
+            .ok_or_else(|| "user has no linked identities".to_string())?;
+        let identity = profile
+            .verify(&platform)
+            .ok_or_else(|| "identity not linked for platform".to_string())?;
+        self.identities.insert(user_id.clone(), profile);
+        self.emit(EventKind::IdentityVerified, &platform, &[&user_id], None);
+
+        Ok(identity)
+    }
+
+    #[mutate]
+    async fn unlink_external_identity(&mut self, platform: String) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let mut profile = self
+            .identities
+            .get(&sender)
+            .ok_or_else(|| "user has no linked identities".to_string())?;
+
+        if !profile.unlink(&platform) {
+            return Err("identity not linked for platform".to_string());
+        }
+
+        self.identities.insert(sender, profile);
+        self.emit(EventKind::IdentityUnlinked, &platform, &[], None);
+        Ok(())
+    }
+
+    #[mutate]
+    async fn set_identities_public(&mut self, is_public: bool) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        if self.users.get(&sender).is_none() {
+            return Err("user not registered".to_string());
+        }
+
+        let mut profile = self
+            .identities
+            .get(&sender)
+            .unwrap_or_else(IdentityProfile::new);
+        profile.is_public = is_public;
+        self.identities.insert(sender.clone(), profile);
+        self.emit(EventKind::IdentityVisibilityChanged, &sender, &[], None);
+        Ok(())
+    }
+
+    #[query]
+    async fn get_external_identities(&self, user_id: String) -> Vec<ExternalIdentity> {
+        let Some(profile) = self.identities.get(&user_id) else {
+            return Vec::new();
+        };
+
+        // owners see their pending links too, everyone else only
+        // verified ones and only if the profile is public
+        if env::sender() == user_id {
+            profile.identities
+        } else {
+            profile.public_view()
+        }
+    }
+
+    #[mutate]
+    async fn add_region_tag(&mut self, region_tag: String) -> Result<(), String> {
+        self.ensure_admin(&env::sender())?;
+
+        if region_tag.is_empty() {
+            return Err("region tag must not be empty".to_string());
+        }
+
+        if !self.region_tags.contains(&region_tag) {
+            self.region_tags.push(region_tag.clone());
+            self.emit(EventKind::RegionTagAdded, &region_tag, &[], None);
+        }
+        Ok(())
+    }
+
+    #[mutate]
+    async fn remove_region_tag(&mut self, region_tag: String) -> Result<(), String> {
+        self.ensure_admin(&env::sender())?;
+        if self.region_tags.contains(&region_tag) {
+            self.region_tags.retain(|t| *t != region_tag);
+            self.emit(EventKind::RegionTagRemoved, &region_tag, &[], None);
+        }
+        Ok(())
+    }
+
+    #[query]
+    async fn get_region_tags(&self) -> Vec<String> {
+        self.region_tags.clone()
+    }
+
+    #[mutate]
+    async fn set_message_template(
+        &mut self,
+        code: MessageCode,
+        locale: String,
+        template: String,
+    ) -> Result<(), String> {
+        self.ensure_admin(&env::sender())?;
+
+        if locale.is_empty() || template.is_empty() {
+            return Err("locale and template must not be empty".to_string());
+        }
+
+        let key = code.catalog_key(&locale);
+        self.message_templates.insert(key.clone(), template);
+        self.emit(EventKind::MessageTemplateSet, &key, &[], None);
+        Ok(())
+    }
+
+    #[mutate]
+    async fn remove_message_template(
+        &mut self,
+        code: MessageCode,
+        locale: String,
+    ) -> Result<(), String> {
+        self.ensure_admin(&env::sender())?;
+        let key = code.catalog_key(&locale);
+        if self.message_templates.remove(&key).is_some() {
+            self.emit(EventKind::MessageTemplateRemoved, &key, &[], None);
+        }
+        Ok(())
+    }
+
+    #[query]
+    async fn get_message_template(&self, code: MessageCode, locale: Option<String>) -> String {
+        self.message_template(code, locale.as_deref())
+    }
+
+    #[query]
+    async fn get_events_since(&self, seq: u64) -> Vec<Event> {
+        let end = seq.saturating_add(MAX_PAGE_LIMIT).min(self.events.len() as u64);
+        (seq..end).filter_map(|i| self.events.get(i as usize)).collect()
+    }
+
+    #[query]
+    async fn get_recent_activity(&self) -> Vec<Event> {
+        self.recent_events.newest_first()
+    }
+
+    #[query]
+    async fn get_user_events_since(&self, user_id: String, seq: u64) -> Vec<Event> {
+        self.user_event_seqs_after(&user_id, seq, MAX_PAGE_LIMIT)
+            .into_iter()
+            .filter_map(|s| self.events.get(s as usize - 1))
+            .collect()
+    }
+
+    #[query]
+    async fn export_my_data(&self) -> Result<UserDataExport, String> {
+        let sender = env::sender();
+        let user = self
+            .users
+            .get(&sender)
+            .ok_or_else(|| "user is not registered".to_string())?;
+        Ok(self.export_data(user))
+    }
+
+    #[query]
+    async fn get_my_activity(&self, offset: u64, limit: u64) -> ActivityPage {
+        self.activity_page(&env::sender(), offset, limit)
+    }
+
+    // multi-token adapter: ranks are token ids, see `token`
+    #[query]
+    async fn balance_of(&self, owner: String, card: Card) -> u64 {
+        self.users.get(&owner).map_or(0, |u| token::balance_of(&u.cards, card))
+    }
+
+    #[query]
+    async fn card_total_supply(&self, card: Card) -> u64 {
+        self.card_supply[token::token_id(card)]
+    }
+
+    #[mutate]
+    async fn set_approval_for_all(
+        &mut self,
+        operator: String,
+        approved: bool,
+    ) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        if self.users.get(&sender).is_none() {
+            return Err("user must register before approving operators".to_string());
+        }
+        if operator == sender {
+            return Err("cannot approve yourself as an operator".to_string());
+        }
+
+        let mut operators = self.card_operators.get(&sender).unwrap_or_default();
+        operators.retain(|o| *o != operator);
+        if approved {
+            operators.push(operator.clone());
+        }
+        self.card_operators.insert(sender, operators);
+        self.emit(EventKind::OperatorApprovalChanged, &operator, &[&operator], None);
+        Ok(())
+    }
+
+    #[query]
+    async fn is_approved_for_all(&self, owner: String, operator: String) -> bool {
+        self.card_operators.get(&owner).is_some_and(|ops| ops.contains(&operator))
+    }
+
+    // moves `amount` cards of one rank from `from` to `to`, called by the
+    // owner or an operator they approved
+    #[mutate]
+    async fn transfer_from(
+        &mut self,
+        from: String,
+        to: String,
+        card: Card,
+        amount: u64,
+    ) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        let approved = self.card_operators.get(&from).is_some_and(|ops| ops.contains(&sender));
+        if sender != from && !approved {
+            return Err("caller is neither the owner nor an approved operator".to_string());
+        }
+        if amount == 0 {
+            return Err("transfer amount must be positive".to_string());
+        }
+        if from == to {
+            return Err("cannot transfer cards to the same account".to_string());
+        }
+        self.ensure_unlocked(&from)?;
+
+        let mut owner = self
+            .users
+            .get(&from)
+            .ok_or_else(|| "owner is not registered".to_string())?;
+        let mut recipient = self
+            .users
+            .get(&to)
+            .ok_or_else(|| "recipient is not registered".to_string())?;
+
+        if token::balance_of(&owner.cards, card) < amount {
+            return Err(format!("not enough {:?} cards to transfer", card));
+        }
+        let cards = vec![card; amount as usize];
+        self.ensure_inventory_room(&recipient, cards.len())?;
+        EscalateContractState::remove_cards_from_inventory(&mut owner.cards, &cards)?;
+        recipient.add_cards(cards);
+
+        self.users.insert(from.clone(), owner);
+        self.users.insert(to.clone(), recipient);
+        self.emit(EventKind::CardsTransferred, &format!("{:?}", card), &[&from, &to], None);
+        Ok(())
+    }
+
+    #[mutate]
+    async fn gift_cards(&mut self, to_user_id: String, cards: Vec<Card>) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let (mut giver, mut recipient) = self.gift_parties(&sender, &to_user_id)?;
+        if cards.is_empty() {
+            return Err("must gift at least one card".to_string());
+        }
+
+        self.ensure_inventory_room(&recipient, cards.len())?;
+        EscalateContractState::remove_cards_from_inventory(&mut giver.cards, &cards)?;
+        recipient.add_cards(cards.clone());
+        self.users.insert(sender, giver);
+        self.users.insert(to_user_id.clone(), recipient);
+        let count = cards.len() as u64;
+        self.emit(EventKind::CardsGifted, &to_user_id, &[&to_user_id], Some(count));
+        Ok(())
+    }
+
+    // the gift pays down the recipient's debt before it reaches their balance
+    #[mutate]
+    async fn gift_balance(&mut self, to_user_id: String, amount: u64) -> Result<(), String> {
+        self.ensure_not_paused()?;
+        let sender = env::sender();
+        self.ensure_unlocked(&sender)?;
+        let (mut giver, _) = self.gift_parties(&sender, &to_user_id)?;
+        if amount == 0 {
+            return Err("gift amount must be positive".to_string());
+        }
+        // a gift is a way out of the account too, so it has to respect the
+        // same debt and floor rules as a withdrawal
+        if self.debts.get(&sender).unwrap_or(0) > 0 {
+            return Err("repay your debt before gifting".to_string());
+        }
+        if giver.balance.saturating_sub(self.params.balance_floor) < amount {
+            return Err("insufficient balance".to_string());
+        }
+
+        giver.balance -= amount;
+        self.users.insert(sender, giver);
+        self.apply_payouts(vec![(to_user_id.clone(), amount as i64)]);
+        self.emit(EventKind::BalanceGifted, &to_user_id, &[&to_user_id], Some(amount));
+        Ok(())
+    }
+
+    // runs the action once per caller and `request_id`; retrying a call that
+    // succeeded answers with its original result without running it again,
+    // while a failed call left nothing behind and simply runs again
+    #[mutate]
+    async fn with_idempotency(
+        &mut self,
+        request_id: String,
+        action: BatchAction,
+    ) -> Result<BatchResult, String> {
+        if request_id.is_empty() || request_id.len() > MAX_REQUEST_ID_LENGTH {
+            return Err(format!("request id must be 1 to {} bytes", MAX_REQUEST_ID_LENGTH));
+        }
+        let sender = env::sender();
+        let mut calls = self.idempotent_calls.get(&sender).unwrap_or_default();
+        if let Some(call) = calls.iter().find(|c| c.request_id == request_id) {
+            return Ok(call.result.clone());
+        }
+
+        let result = self.run_batch_action(action).await?;
+        if calls.len() >= MAX_IDEMPOTENT_CALLS {
+            calls.remove(0);
+        }
+        calls.push(IdempotentCall { request_id, result: result.clone() });
+        self.idempotent_calls.insert(sender, calls);
+        Ok(result)
+    }
+
+    // runs the actions in order as the caller; the first one to fail fails
+    // the whole call, and the runtime discards every write of a call that
+    // returns an error, so none of the batch is applied
+    #[mutate]
+    async fn batch(&mut self, actions: Vec<BatchAction>) -> Result<Vec<BatchResult>, String> {
+        if actions.len() > MAX_BATCH_ACTIONS {
+            return Err(format!("a batch may hold at most {} actions", MAX_BATCH_ACTIONS));
+        }
+
+        let mut results = Vec::with_capacity(actions.len());
+        for (i, action) in actions.into_iter().enumerate() {
+            let result = self.run_batch_action(action).await;
+            results.push(result.map_err(|e| format!("batch action {} failed: {}", i, e))?);
+        }
+        Ok(results)
+    }
+
+    #[mutate]
+    fn start_file_upload(&mut self, path: String, total_chunks: u32) -> Result<(), String> {
+        self.server.start_file_upload(self.weil_id_generator.next_id(), path, total_chunks)
+    }
+
+    #[query]
+    fn total_chunks(&self, path: String) -> Result<u32, String> {
+        self.server.total_chunks(path)
+    }
+
+    #[mutate]
+    fn add_path_content(
+        &mut self,
+        path: String,
+        chunk: Vec<u8>,
+        index: u32,
+    ) -> Result<(), String> {
+        self.server.add_path_content(path, chunk, index)
+    }
+
+    #[mutate]
+    fn finish_upload(&mut self, path: String, size_bytes: u32) -> Result<(), String> {
+        self.server.finish_upload(path, size_bytes)
+    }
+
+    #[query]
+    fn http_content(
+        &self,
+        path: String,
+        index: u32,
+        method: String,
+    ) -> (u16, std::collections::HashMap<String, String>, Vec<u8>) {
+        self.server.http_content(path, index, method)
+    }
+
+    #[query]
+    fn size_bytes(&self, path: String) -> Result<u32, String> {
+        self.server.size_bytes(path)
+    }
+
+    #[query]
+    fn get_chunk_size(&self) -> u32 {
+        self.server.get_chunk_size()
+    }
+}
+
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::elements::{AchievementKind, EntrypointRole, HandActionKind, HandSort, StakeArgs};
+    use crate::testing::{Fixture, MockEnv, block_on, fixture};
+
+    #[test]
+    fn second_check_in_the_same_block_reports_the_winner() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        MockEnv::set_block_height(10);
+
+        MockEnv::set_sender("user-1");
+        let outcome = block_on(fx.state.check(hand_id.clone())).expect("first check wins");
+
+        MockEnv::set_sender("user-2");
+        let err = block_on(fx.state.check(hand_id.clone())).unwrap_err();
+        assert_eq!(
+            err,
+            format!("hand already checked by user-1, bluff detected: {}", outcome.bluff_detected)
+        );
+
+        let resolution = fx.hand(&hand_id).unwrap().resolution.unwrap();
+        assert_eq!(resolution.checker_id, "user-1");
+        assert_eq!(resolution.checked_at_block, 10);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn losing_checker_is_not_charged() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        MockEnv::set_block_height(10);
+
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.check(hand_id.clone())).unwrap();
+        let before = fx.user("user-2").unwrap().balance;
+
+        MockEnv::set_sender("user-2");
+        assert!(block_on(fx.state.check(hand_id.clone())).is_err());
+        assert_eq!(fx.user("user-2").unwrap().balance, before);
+        assert_eq!(fx.state.resolution_totals.len(), 1);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn checks_pay_rewards_out_of_the_treasury() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        let held = |fx: &Fixture| -> u64 {
+            fx.user_ids.iter().map(|id| fx.user(id).unwrap().balance).sum::<u64>()
+                + fx.state.treasury
+        };
+        assert_eq!(held(&fx), block_on(fx.state.get_total_supply()));
+
+        // the creator staked an honest ACE, so the checker's penalty funds
+        // the creator's reward and nothing is minted
+        MockEnv::set_sender("user-1");
+        let outcome = block_on(fx.state.check(hand_id)).unwrap();
+        assert!(!outcome.bluff_detected);
+        assert_eq!(held(&fx), block_on(fx.state.get_total_supply()));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn activity_reports_the_callers_net_result_of_a_check() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+
+        MockEnv::set_sender("user-1");
+        let outcome = block_on(fx.state.check(hand_id.clone())).unwrap();
+        let net: i64 = outcome
+            .payouts
+            .iter()
+            .filter(|l| l.user_id == "user-1")
+            .map(|l| l.amount)
+            .sum();
+        let page = block_on(fx.state.get_my_activity(0, 50));
+        let last = page.items.last().unwrap();
+        assert_eq!(page.total_count, page.items.len() as u64);
+        assert!(matches!(last.kind, EventKind::Checked));
+        assert_eq!(last.subject_id, hand_id);
+        assert_eq!(last.net_change, Some(net));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn checks_risking_too_much_of_the_balance_need_a_high_roller() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        let mut checker = fx.user("user-1").unwrap();
+        checker.balance = 0;
+        fx.state.users.insert("user-1".to_string(), checker);
+
+        MockEnv::set_sender("user-1");
+        let err = block_on(fx.state.check(hand_id.clone())).unwrap_err();
+        assert!(err.contains("high roller"));
+
+        block_on(fx.state.set_high_roller(true)).unwrap();
+        assert!(block_on(fx.state.check(hand_id)).is_ok());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn checked_stakes_go_back_to_the_deck() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        let size = fx.state.deck.size();
+        let aces = block_on(fx.state.card_total_supply(Card::ACE));
+
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.check(hand_id)).unwrap();
+        assert_eq!(fx.state.deck.size(), size + 1);
+        assert_eq!(block_on(fx.state.card_total_supply(Card::ACE)), aces - 1);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn recent_activity_keeps_only_the_latest_events() {
+        let mut fx = fixture(1, 0, 0);
+        MockEnv::set_sender("user-0");
+        for i in 0..RECENT_EVENTS + 8 {
+            block_on(fx.state.contribute_entropy(format!("entropy {}", i))).unwrap();
+        }
+
+        let recent = block_on(fx.state.get_recent_activity());
+        let last = fx.state.events.len() as u64;
+        assert_eq!(recent.len(), RECENT_EVENTS);
+        assert_eq!(recent[0].seq, last);
+        assert_eq!(recent[RECENT_EVENTS - 1].seq, last + 1 - RECENT_EVENTS as u64);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn dutch_offers_sell_at_the_decayed_price() {
+        let mut fx = fixture(2, 0, 0);
+        MockEnv::set_block_height(10);
+        MockEnv::set_sender("user-0");
+        let offer = block_on(fx.state.offer_dutch(vec![Card::KING], 1_000, 400, 50)).unwrap();
+        let seller_balance = fx.user("user-0").unwrap().balance;
+
+        MockEnv::set_block_height(14);
+        assert_eq!(block_on(fx.state.get_offer_price(offer.offer_id.clone())), Ok(800));
+        MockEnv::set_block_height(100);
+        assert_eq!(block_on(fx.state.get_offer_price(offer.offer_id.clone())), Ok(400));
+
+        MockEnv::set_sender("user-1");
+        let buyer_balance = fx.user("user-1").unwrap().balance;
+        assert!(block_on(fx.state.bid(offer.offer_id.clone(), 500)).is_err());
+        let sold = block_on(fx.state.accept_dutch(offer.offer_id)).unwrap();
+        assert_eq!(sold.current_bid, Some(400));
+        assert_eq!(fx.user("user-1").unwrap().balance, buyer_balance - 400);
+        assert!(fx.user("user-0").unwrap().balance > seller_balance);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn checks_update_reputation_and_gate_hands_that_require_it() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        let mut hand = fx.state.hands.get(&hand_id).unwrap();
+        hand.min_reputation = Some(1);
+        fx.state.hands.insert(hand_id.clone(), hand);
+
+        MockEnv::set_sender("user-1");
+        let err = block_on(fx.state.check(hand_id.clone())).unwrap_err();
+        assert_eq!(err, "this hand requires a reputation of at least 1");
+
+        let mut hand = fx.state.hands.get(&hand_id).unwrap();
+        hand.min_reputation = None;
+        fx.state.hands.insert(hand_id.clone(), hand);
+        block_on(fx.state.check(hand_id)).unwrap();
+        // the creator's honest ACE was checked, the checker caught nothing
+        assert_eq!(block_on(fx.state.get_reputation("user-0".to_string())), Some(1));
+        assert_eq!(block_on(fx.state.get_reputation("user-1".to_string())), Some(0));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn maintenance_rejects_mutations_until_its_end() {
+        let mut fx = fixture(2, 0, 0);
+        MockEnv::set_sender("admin");
+        fx.state.admin = "admin".to_string();
+        block_on(fx.state.schedule_maintenance(10, 20)).unwrap();
+
+        MockEnv::set_sender("user-0");
+        MockEnv::set_block_height(12);
+        assert!(block_on(fx.state.health()).in_maintenance);
+        let err = block_on(fx.state.contribute_entropy("x".to_string())).unwrap_err();
+        assert_eq!(err, "contract is under maintenance until block 20");
+
+        MockEnv::set_block_height(20);
+        assert!(block_on(fx.state.health()).maintenance.is_none());
+        assert!(block_on(fx.state.contribute_entropy("x".to_string())).is_ok());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn message_templates_fall_back_to_the_default_locale() {
+        let mut fx = fixture(1, 0, 0);
+        MockEnv::set_sender("admin");
+        fx.state.admin = "admin".to_string();
+        let code = MessageCode::HandExpired;
+        let template = |fx: &Fixture, locale: &str| {
+            block_on(fx.state.get_message_template(code, Some(locale.to_string())))
+        };
+        assert_eq!(template(&fx, "pt-BR"), code.default_template());
+
+        let set = |fx: &mut Fixture, locale: &str, text: &str| {
+            block_on(fx.state.set_message_template(code, locale.to_string(), text.to_string()))
+        };
+        set(&mut fx, "en", "hand ran out").unwrap();
+        set(&mut fx, "pt", "mao expirada").unwrap();
+        assert_eq!(template(&fx, "pt-BR"), "mao expirada");
+        assert_eq!(template(&fx, "de"), "hand ran out");
+        assert_eq!(block_on(fx.state.get_message_template(code, None)), "hand ran out");
+
+        MockEnv::set_sender("user-0");
+        assert!(set(&mut fx, "de", "abgelaufen").is_err());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn batch_reports_the_failing_action() {
+        let mut fx = fixture(2, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+
+        MockEnv::set_sender("user-1");
+        let actions = vec![
+            BatchAction::Check(hand_id.clone()),
+            BatchAction::Check(hand_id.clone()),
+        ];
+        let err = block_on(fx.state.batch(actions)).unwrap_err();
+        assert!(err.starts_with("batch action 1 failed"));
+
+        let results = block_on(fx.state.batch(vec![BatchAction::Resolve("none".to_string())]));
+        assert_eq!(results.unwrap_err(), "batch action 0 failed: offer not found");
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn catching_a_stronger_claim_pays_the_checker_more() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        // the creator claims ACE but staked a TWO
+        let mut hand = fx.state.hands.get(&hand_id).unwrap();
+        hand.stakes[0].cards = vec![Card::TWO];
+        fx.state.hands.insert(hand_id.clone(), hand);
+        fx.state.treasury = 10 * CHIPS_PER_UNIT;
+
+        MockEnv::set_sender("user-1");
+        let outcome = block_on(fx.state.check(hand_id)).unwrap();
+        assert!(outcome.bluff_detected);
+        let reward = outcome.payouts.iter().find(|l| l.user_id == "user-1").unwrap().amount;
+        assert_eq!(reward as u64, CHIPS_PER_UNIT * 160 / 100);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn drop_rates_favour_common_cards() {
+        let fx = fixture(0, 0, 0);
+        let rates = block_on(fx.state.get_drop_rates());
+        let rate = |card: Card| rates.iter().find(|r| r.card == card).unwrap().per_million;
+        assert!(rate(Card::TWO) > rate(Card::KING));
+        assert!(rate(Card::KING) > rate(Card::JOKER));
+        let total: u64 = rates.iter().map(|r| r.per_million).sum();
+        assert!(total <= 1_000_000 && total > 1_000_000 - Card::ALL.len() as u64);
+    }
+
+    #[test]
+    fn auto_bids_open_the_bidding_on_matching_offers() {
+        let mut fx = fixture(3, 0, 0);
+        MockEnv::set_block_height(10);
+
+        MockEnv::set_sender("user-1");
+        let low = block_on(fx.state.create_auto_bid(Card::KING, 30, 100)).unwrap();
+        MockEnv::set_sender("user-2");
+        block_on(fx.state.create_auto_bid(Card::KING, 20, 100)).unwrap();
+        let balance = fx.user("user-2").unwrap().balance;
+
+        MockEnv::set_sender("user-0");
+        let offer = block_on(fx.state.offer(vec![Card::KING], 10, 100, None, None, None)).unwrap();
+        assert_eq!(offer.current_bidder_id.as_deref(), Some("user-1"));
+        assert_eq!(offer.current_bid, Some(21));
+        assert_eq!(fx.state.auto_bid_rules.get(&low.rule_id).unwrap().budget, 79);
+
+        // other ranks are left alone
+        let queen = block_on(fx.state.offer(vec![Card::QUEEN], 10, 100, None, None, None));
+        assert_eq!(queen.unwrap().current_bidder_id, None);
+
+        MockEnv::set_sender("user-2");
+        let rules = block_on(fx.state.get_my_auto_bids());
+        let canceled = block_on(fx.state.cancel_auto_bid(rules[0].rule_id.clone())).unwrap();
+        assert!(!canceled.is_active);
+        assert_eq!(fx.user("user-2").unwrap().balance, balance + 100);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn tournament_prize_pool_goes_to_the_top_of_the_standings() {
+        let mut fx = fixture(3, 1, 0);
+        let mut options = fx.state.hands.get(&fx.hand_ids[0]).unwrap().options();
+        MockEnv::set_sender("admin");
+        fx.state.admin = "admin".to_string();
+        let config = TournamentConfig {
+            name: "friday night".to_string(),
+            entry_fee: 50,
+            max_players: 8,
+            starts_at_block: 10,
+            ends_at_block: 100,
+            prize_escrow: 0,
+        };
+        let id = block_on(fx.state.create_tournament(config)).unwrap().tournament_id;
+
+        for user in ["user-0", "user-1"] {
+            MockEnv::set_sender(user);
+            block_on(fx.state.join_tournament(id.clone())).unwrap();
+        }
+        MockEnv::set_block_height(10);
+        MockEnv::set_sender("user-2");
+        assert!(block_on(fx.state.join_tournament(id.clone())).is_err());
+
+        MockEnv::set_sender("user-0");
+        options.tournament_id = Some(id.clone());
+        let hand = block_on(fx.state.start_hand(Card::ACE, 1, vec![Card::ACE], options)).unwrap();
+        MockEnv::set_sender("user-2");
+        let err = block_on(fx.state.check(hand.hand_id.clone())).unwrap_err();
+        assert!(err.contains("only tournament"));
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.check(hand.hand_id)).unwrap();
+
+        assert!(block_on(fx.state.finalize_tournament(id.clone())).is_err());
+        MockEnv::set_block_height(100);
+        let standings = block_on(fx.state.get_tournament_standings(id.clone())).unwrap();
+        assert_eq!(standings[0].user_id, "user-0");
+        let before = fx.user("user-0").unwrap().balance;
+        let tournament = block_on(fx.state.finalize_tournament(id)).unwrap();
+        assert_eq!(tournament.status, TournamentStatus::Finalized);
+        // 50 and 30 percent scaled over the two places
+        assert_eq!(fx.user("user-0").unwrap().balance, before + 63);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn community_tournaments_lock_their_prize_until_paid_or_cancelled() {
+        let mut fx = fixture(3, 0, 0);
+        let start = fx.user("user-0").unwrap().balance;
+        let config = |prize_escrow| TournamentConfig {
+            name: "open cup".to_string(),
+            entry_fee: 10,
+            max_players: 4,
+            starts_at_block: 10,
+            ends_at_block: 100,
+            prize_escrow,
+        };
+        MockEnv::set_sender("user-0");
+        let err = block_on(fx.state.create_tournament(config(0))).unwrap_err();
+        assert_eq!(err, "a community tournament must put up a prize escrow");
+        assert!(block_on(fx.state.create_tournament(config(start + 1))).is_err());
+
+        let first = block_on(fx.state.create_tournament(config(100))).unwrap();
+        assert_eq!(first.prize_pool, 100);
+        let user = fx.user("user-0").unwrap();
+        assert_eq!((user.balance, user.locked_balance), (start - 100, 100));
+        MockEnv::set_sender("user-1");
+        let joined = block_on(fx.state.join_tournament(first.tournament_id.clone())).unwrap();
+        assert_eq!(joined.prize_pool, 110);
+        let err = block_on(fx.state.cancel_tournament(first.tournament_id.clone())).unwrap_err();
+        assert_eq!(err, "only the creator or admin can cancel a tournament");
+
+        MockEnv::set_sender("user-0");
+        let cancelled = block_on(fx.state.cancel_tournament(first.tournament_id.clone())).unwrap();
+        assert_eq!(cancelled.status, TournamentStatus::Cancelled);
+        let user = fx.user("user-0").unwrap();
+        assert_eq!((user.balance, user.locked_balance), (start, 0));
+        assert_eq!(fx.user("user-1").unwrap().balance, start);
+
+        // once it starts the escrow can only go to the winners
+        let second = block_on(fx.state.create_tournament(config(100))).unwrap();
+        MockEnv::set_block_height(10);
+        let err = block_on(fx.state.cancel_tournament(second.tournament_id.clone())).unwrap_err();
+        assert_eq!(err, "a tournament can only be cancelled before it starts");
+        MockEnv::set_block_height(100);
+        block_on(fx.state.finalize_tournament(second.tournament_id)).unwrap();
+        let user = fx.user("user-0").unwrap();
+        assert_eq!((user.balance, user.locked_balance), (start, 0));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn enriched_offers_carry_the_seller_profile() {
+        let mut fx = fixture(2, 1, 2);
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.check(fx.hand_ids[0].clone())).unwrap();
+
+        let page = block_on(fx.state.get_offers_enriched(0, 10));
+        assert_eq!(page.total_count, 2);
+        let seller = page.items[0].seller.clone().unwrap();
+        assert_eq!(seller.user_id, "user-0");
+        assert_eq!(seller.reputation, 1);
+        assert_eq!(seller.honesty_index, Some(100));
+        let seller = page.items[1].seller.clone().unwrap();
+        assert_eq!((seller.user_id.as_str(), seller.honesty_index), ("user-1", None));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn offers_below_their_reserve_go_back_to_the_seller() {
+        let mut fx = fixture(3, 0, 0);
+        MockEnv::set_sender("user-0");
+        let offer = block_on(fx.state.offer(vec![Card::KING], 10, 100, None, None, None)).unwrap();
+        let id = offer.offer_id;
+        let kings = fx.user("user-0").unwrap().cards.len();
+        block_on(fx.state.set_offer_bid_rules(id.clone(), 5, Some(100))).unwrap();
+
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.bid(id.clone(), 11)).unwrap();
+        MockEnv::set_sender("user-2");
+        let balance = fx.user("user-2").unwrap().balance;
+        let err = block_on(fx.state.bid(id.clone(), 15)).unwrap_err();
+        assert_eq!(err, "bid must be at least 16");
+        block_on(fx.state.bid(id.clone(), 16)).unwrap();
+
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.resolve(id.clone())).unwrap();
+        let offer = fx.state.offers.get(&id).unwrap();
+        assert!(offer.is_resolved && offer.current_bidder_id.is_none());
+        assert_eq!(fx.user("user-0").unwrap().cards.len(), kings + 1);
+        assert_eq!(fx.user("user-2").unwrap().balance, balance);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn new_accounts_get_the_starter_bundle_once() {
+        let mut fx = fixture(1, 0, 0);
+        fx.state.params.starter_cards = vec![Card::ACE, Card::ACE, Card::TWO];
+        let aces = block_on(fx.state.card_total_supply(Card::ACE));
+        let deck = fx.state.deck.size();
+
+        MockEnv::set_sender("newbie");
+        let user = block_on(fx.state.register_user("hi".to_string())).unwrap();
+        assert!(user.starter_granted);
+        assert_eq!(user.cards, vec![Card::ACE, Card::ACE, Card::TWO]);
+        assert_eq!(block_on(fx.state.card_total_supply(Card::ACE)), aces + 2);
+        assert_eq!(fx.state.deck.size(), deck - 3);
+
+        // registering again only updates the bio
+        let user = block_on(fx.state.register_user("hello".to_string())).unwrap();
+        assert_eq!(user.cards.len(), 3);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn only_players_talk_at_the_table() {
+        let mut fx = fixture(2, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.post_hand_message(hand_id.clone(), " all aces ".to_string())).unwrap();
+        let long = "a".repeat(MAX_HAND_MESSAGE_LENGTH + 1);
+        assert!(block_on(fx.state.post_hand_message(hand_id.clone(), long)).is_err());
+
+        MockEnv::set_sender("user-1");
+        let err = block_on(fx.state.post_hand_message(hand_id.clone(), "sure".to_string()));
+        assert_eq!(err.unwrap_err(), "only players of the hand can post to its table");
+
+        let page = block_on(fx.state.get_hand_messages(hand_id, 0, 10)).unwrap();
+        assert_eq!(page.total_count, 1);
+        assert_eq!(page.items[0].text, "all aces");
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn table_talk_is_rate_limited_and_moderated() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        let post = |fx: &mut Fixture, text: &str| {
+            block_on(fx.state.post_hand_message(fx.hand_ids[0].clone(), text.to_string()))
+        };
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.stake(hand_id.clone(), vec![Card::ACE; 2], 2)).unwrap();
+        let first = post(&mut fx, "raising").unwrap();
+        let err = post(&mut fx, "again").unwrap_err();
+        assert_eq!(err, "wait 3 blocks between messages at a table");
+        MockEnv::set_block_height(HAND_MESSAGE_COOLDOWN_BLOCKS);
+        post(&mut fx, "again").unwrap();
+
+        let err = block_on(fx.state.mute_in_hand(hand_id.clone(), "user-0".to_string()));
+        assert_eq!(err.unwrap_err(), "only the hand creator can mute players");
+        MockEnv::set_sender("user-0");
+        let err = block_on(fx.state.mute_in_hand(hand_id.clone(), "user-2".to_string()));
+        assert_eq!(err.unwrap_err(), "only players of the hand can be muted");
+        block_on(fx.state.mute_in_hand(hand_id.clone(), "user-1".to_string())).unwrap();
+        MockEnv::set_sender("user-1");
+        MockEnv::set_block_height(2 * HAND_MESSAGE_COOLDOWN_BLOCKS);
+        assert_eq!(post(&mut fx, "hey").unwrap_err(), "you are muted at this table");
+
+        let err = block_on(fx.state.flag_hand_message(first.message_id, true)).unwrap_err();
+        assert_eq!(err, "only moderators can flag messages");
+        MockEnv::set_sender("admin");
+        fx.state.admin = "admin".to_string();
+        block_on(fx.state.set_moderator("user-2".to_string(), true)).unwrap();
+        block_on(fx.state.pause()).unwrap();
+        MockEnv::set_sender("user-2");
+        let err = block_on(fx.state.flag_hand_message(first.message_id, true)).unwrap_err();
+        assert_eq!(err, "contract is paused");
+        MockEnv::set_sender("admin");
+        block_on(fx.state.unpause()).unwrap();
+        MockEnv::set_sender("user-2");
+        let flagged = block_on(fx.state.flag_hand_message(first.message_id, true)).unwrap();
+        assert!(flagged.flagged);
+        let page = block_on(fx.state.get_hand_messages(hand_id, 0, 10)).unwrap();
+        assert!(page.items[0].flagged && !page.items[1].flagged);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn spectators_who_wagered_cannot_play_the_hand() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        MockEnv::set_sender("user-2");
+        block_on(fx.state.place_side_bet(hand_id.clone(), true, 10)).unwrap();
+        let err = "spectators who wagered on a hand cannot play in it";
+        let staked = block_on(fx.state.stake(hand_id.clone(), vec![Card::ACE; 2], 2));
+        assert_eq!(staked.unwrap_err(), err);
+        assert_eq!(block_on(fx.state.check(hand_id.clone())).unwrap_err(), err);
+
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.stake(hand_id.clone(), vec![Card::ACE; 2], 2)).unwrap();
+        assert!(block_on(fx.state.place_side_bet(hand_id, false, 10)).is_err());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn accepted_challenges_open_a_private_hand() {
+        let mut fx = fixture(3, 0, 0);
+        MockEnv::set_sender("user-0");
+        assert!(block_on(fx.state.challenge("user-1".to_string(), Card::ACE, vec![Card::ACE]))
+            .is_err());
+        block_on(fx.state.add_friend("user-1".to_string())).unwrap();
+        let cards = fx.user("user-0").unwrap().cards.len();
+        let friend = "user-1".to_string();
+        let first = block_on(fx.state.challenge(friend.clone(), Card::ACE, vec![Card::ACE]));
+        let second = block_on(fx.state.challenge(friend, Card::KING, vec![Card::TWO]));
+        assert_eq!(fx.user("user-0").unwrap().cards.len(), cards - 2);
+
+        MockEnv::set_sender("user-1");
+        let hand = block_on(fx.state.accept_challenge(first.unwrap().challenge_id)).unwrap();
+        assert_eq!(hand.creator, "user-0");
+        assert!(hand.admits("user-1") && !hand.admits("user-2"));
+        block_on(fx.state.decline_challenge(second.unwrap().challenge_id)).unwrap();
+        // the declined stake is back, the accepted one sits in the hand
+        assert_eq!(fx.user("user-0").unwrap().cards.len(), cards - 1);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn stakes_are_numbered_in_arrival_order() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        MockEnv::set_block_height(7);
+        for user in ["user-1", "user-2"] {
+            MockEnv::set_sender(user);
+            block_on(fx.state.stake(hand_id.clone(), vec![Card::ACE; 2], 2)).unwrap();
+        }
+
+        let hand = block_on(fx.state.get_hand(hand_id)).unwrap();
+        let seqs: Vec<u32> = hand.stakes.iter().map(|s| s.seq).collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+        assert_eq!(hand.last_stake().unwrap().user_id, "user-2");
+        assert_eq!(hand.stakes[2].staked_at_block, 7);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn open_hands_are_listed_newest_first_in_pages() {
+        let mut fx = fixture(2, 3, 0);
+        let filter = |offset, limit| HandFilter {
+            claimed_card: None,
+            min_pot: None,
+            creator: None,
+            unresolved_only: true,
+            offset,
+            limit,
+        };
+        let ids = |page: HandPage| page.items.into_iter().map(|h| h.hand_id).collect::<Vec<_>>();
+        let page = block_on(fx.state.get_open_hands(filter(0, 2)));
+        assert_eq!(page.total_count, 3);
+        assert_eq!(ids(page), vec![fx.hand_ids[2].clone(), fx.hand_ids[1].clone()]);
+        let last = block_on(fx.state.get_open_hands(filter(2, 2)));
+        assert_eq!(ids(last), vec![fx.hand_ids[0].clone()]);
+
+        // a checked hand leaves the lobby
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.check(fx.hand_ids[2].clone())).unwrap();
+        let page = block_on(fx.state.get_open_hands(filter(0, 10)));
+        assert_eq!(page.total_count, 2);
+        assert_eq!(page.items[0].hand_id, fx.hand_ids[1]);
+
+        // new hands reuse the freed slots, closing one moves another into
+        // its slot, and the lobby stays newest first all the same
+        MockEnv::set_sender("user-0");
+        let options = fx.state.hands.get(&fx.hand_ids[0]).unwrap().options();
+        let mut start = || {
+            let kings = vec![Card::KING];
+            block_on(fx.state.start_hand(Card::KING, 1, kings, options.clone())).unwrap().hand_id
+        };
+        let [a, b] = [start(), start()];
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.check(fx.hand_ids[0].clone())).unwrap();
+        let page = block_on(fx.state.get_open_hands(filter(0, 10)));
+        assert_eq!(ids(page), vec![b, a, fx.hand_ids[1].clone()]);
+        assert_eq!((fx.state.open_hand_count, fx.state.open_hand_ids.len()), (3, 4));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn search_hands_filters_and_sorts_through_the_indexes() {
+        let mut fx = fixture(3, 3, 0);
+        let query = |status, creator: Option<&str>, sort| HandQuery {
+            status,
+            creator: creator.map(str::to_string),
+            sort,
+            offset: 0,
+            limit: 10,
+        };
+        MockEnv::set_sender("user-2");
+        block_on(fx.state.stake(fx.hand_ids[1].clone(), vec![Card::ACE; 2], 2)).unwrap();
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.check(fx.hand_ids[0].clone())).unwrap();
+        assert_eq!(fx.state.open_hand_count, 2);
+
+        let ids = |page: HandPage| page.items.into_iter().map(|h| h.hand_id).collect::<Vec<_>>();
+        let open = query(Some(HandStatus::Open), None, HandSort::Newest);
+        let expected = vec![fx.hand_ids[2].clone(), fx.hand_ids[1].clone()];
+        assert_eq!(ids(block_on(fx.state.search_hands(open))), expected);
+        let busiest = block_on(fx.state.search_hands(query(None, None, HandSort::MostStakes)));
+        assert_eq!(busiest.total_count, 3);
+        assert_eq!(busiest.items[0].hand_id, fx.hand_ids[1]);
+        let mine = query(None, Some("user-0"), HandSort::BiggestPot);
+        assert_eq!(ids(block_on(fx.state.search_hands(mine))), vec![fx.hand_ids[0].clone()]);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn recovery_moves_the_account_after_the_veto_window() {
+        let mut fx = fixture(4, 1, 0);
+        fx.state.admin = "admin".to_string();
+        // a closed hand, a rejected trade and a settled withdrawal, all of
+        // them listed under user-0
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.check(fx.hand_ids[0].clone())).unwrap();
+        MockEnv::set_sender("user-0");
+        let trade = block_on(fx.state.propose_trade("user-3".into(), vec![Card::KING], vec![]));
+        let intent = block_on(fx.state.withdraw(10)).unwrap();
+        MockEnv::set_sender("user-3");
+        block_on(fx.state.reject_trade(trade.unwrap().trade_id)).unwrap();
+        MockEnv::set_sender("admin");
+        block_on(fx.state.settle_transfer(intent.intent_id, true)).unwrap();
+
+        MockEnv::set_sender("user-0");
+        let guardians = vec!["user-1".to_string(), "user-2".to_string()];
+        block_on(fx.state.set_recovery_guardians(guardians, 2)).unwrap();
+        let cards = fx.user("user-0").unwrap().cards.len();
+
+        MockEnv::set_block_height(10);
+        MockEnv::set_sender("user-3");
+        let new_id = "user-0-new".to_string();
+        assert!(block_on(fx.state.recover_account("user-0".into(), new_id.clone())).is_err());
+        for guardian in ["user-1", "user-2"] {
+            MockEnv::set_sender(guardian);
+            block_on(fx.state.recover_account("user-0".into(), new_id.clone())).unwrap();
+        }
+        let request = block_on(fx.state.get_recovery("user-0".to_string())).unwrap();
+        assert_eq!(request.executable_at_block, Some(10 + RECOVERY_CHALLENGE_BLOCKS));
+        assert!(block_on(fx.state.complete_recovery("user-0".to_string())).is_err());
+
+        MockEnv::set_block_height(10 + RECOVERY_CHALLENGE_BLOCKS);
+        let user = block_on(fx.state.complete_recovery("user-0".to_string())).unwrap();
+        assert_eq!(user.user_id, new_id);
+        assert!(fx.user("user-0").is_none());
+        assert_eq!(fx.user(&new_id).unwrap().cards.len(), cards);
+        assert!(block_on(fx.state.get_recovery_guardians(new_id.clone())).is_some());
+
+        MockEnv::set_sender(&new_id);
+        let export = block_on(fx.state.export_my_data()).unwrap();
+        assert_eq!(export.trades.len(), 1);
+        assert_eq!(export.trades[0].proposer_id, new_id);
+        assert_eq!(export.transfers.len(), 1);
+        assert_eq!(export.transfers[0].user_id, new_id);
+        let mine = HandQuery {
+            status: None,
+            creator: Some(new_id.clone()),
+            sort: HandSort::Newest,
+            offset: 0,
+            limit: 10,
+        };
+        let hands = block_on(fx.state.search_hands(mine)).items;
+        assert_eq!(hands.len(), 1);
+        assert_eq!(hands[0].hand_id, fx.hand_ids[0]);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn user_events_are_kept_in_pages_and_read_across_them() {
+        let mut fx = fixture(2, 0, 0);
+        let user_id = "user-0".to_string();
+        MockEnv::set_sender("user-1");
+        for _ in 0..2 * USER_EVENT_PAGE_SIZE {
+            fx.state.emit(EventKind::Deposited, &user_id, &[&user_id], Some(1));
+        }
+        let count = fx.state.user_event_counts.get(&user_id).unwrap();
+        assert!(count > 2 * USER_EVENT_PAGE_SIZE);
+        let first_page = fx.state.user_event_pages.get(&format!("{}:0", user_id)).unwrap();
+        assert_eq!(first_page.len() as u64, USER_EVENT_PAGE_SIZE);
+
+        let seqs = fx.state.user_event_seqs(&user_id, 0, u64::MAX);
+        assert_eq!(seqs.len() as u64, count);
+        assert!(seqs.windows(2).all(|w| w[0] < w[1]));
+        // a page of activity straddling two storage pages
+        MockEnv::set_sender("user-0");
+        let offset = USER_EVENT_PAGE_SIZE - 5;
+        let page = block_on(fx.state.get_my_activity(offset, 10));
+        assert_eq!(page.total_count, count);
+        let page_seqs: Vec<u64> = page.items.iter().map(|a| a.seq).collect();
+        assert_eq!(page_seqs, seqs[offset as usize..offset as usize + 10]);
+        let since = block_on(fx.state.get_user_events_since(user_id.clone(), seqs[150]));
+        assert_eq!(since.first().map(|e| e.seq), Some(seqs[151]));
+        assert_eq!(since.len(), seqs.len() - 151);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn accounts_with_open_positions_wait_to_be_recovered() {
+        let mut fx = fixture(3, 0, 1);
+        for owner in ["user-0", "user-1"] {
+            MockEnv::set_sender(owner);
+            block_on(fx.state.set_recovery_guardians(vec!["user-2".to_string()], 1)).unwrap();
+            MockEnv::set_sender("user-2");
+            block_on(fx.state.recover_account(owner.into(), format!("{}-new", owner))).unwrap();
+        }
+        MockEnv::set_block_height(RECOVERY_CHALLENGE_BLOCKS);
+        let complete = |fx: &mut Fixture, id: &str| {
+            block_on(fx.state.complete_recovery(id.to_string()))
+        };
+        // user-0 listed the offer, user-1 holds its bid
+        let err = complete(&mut fx, "user-0").unwrap_err();
+        assert_eq!(err, "settle the account's escrowed cards before recovering it");
+        let err = complete(&mut fx, "user-1").unwrap_err();
+        assert_eq!(err, "settle the account's escrowed chips before recovering it");
+
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.resolve(fx.offer_ids[0].clone())).unwrap();
+        complete(&mut fx, "user-0").unwrap();
+        complete(&mut fx, "user-1").unwrap();
+        assert!(fx.user("user-1-new").unwrap().cards.contains(&Card::KING));
+        assert!(fx.user("user-0").is_none() && fx.user("user-1").is_none());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn the_original_key_can_veto_a_recovery() {
+        let mut fx = fixture(3, 0, 0);
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.set_recovery_guardians(vec!["user-1".to_string()], 1)).unwrap();
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.recover_account("user-0".into(), "thief".into())).unwrap();
+
+        MockEnv::set_sender("user-0");
+        let vetoed = block_on(fx.state.veto_recovery()).unwrap();
+        assert_eq!(vetoed.status, RecoveryStatus::Vetoed);
+        MockEnv::set_block_height(RECOVERY_CHALLENGE_BLOCKS + 1);
+        assert!(block_on(fx.state.complete_recovery("user-0".to_string())).is_err());
+        assert!(fx.user("user-0").is_some());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn daily_cards_can_be_claimed_once_per_window() {
+        let mut fx = fixture(1, 0, 0);
+        let window = fx.state.params.daily_claim_blocks;
+        MockEnv::set_sender("user-0");
+        MockEnv::set_block_height(100);
+        let draw = block_on(fx.state.claim_daily_cards("a".repeat(64))).unwrap();
+        assert_eq!(draw.count, fx.state.params.daily_claim_cards);
+        assert!(block_on(fx.state.claim_daily_cards("b".repeat(64))).is_err());
+
+        MockEnv::set_block_height(100 + window);
+        block_on(fx.state.claim_daily_cards("b".repeat(64))).unwrap();
+        assert_eq!(fx.user("user-0").unwrap().last_claim_block, Some(100 + window));
+        assert_eq!(block_on(fx.state.get_my_pending_draws()).len(), 2);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn draws_are_seeded_by_the_block_after_their_commit_and_expire() {
+        let mut fx = fixture(2, 0, 0);
+        let price = fx.state.params.card_price;
+        let salt = "salt".to_string();
+        let seed_pool = |fx: &Fixture| {
+            fx.state.pending_draws.get(&"user-0".to_string()).unwrap()[0].seed_pool.clone()
+        };
+        MockEnv::set_sender("user-0");
+        MockEnv::set_block_height(10);
+        let commitment = randomness::commitment_for(&salt);
+        let draw = block_on(fx.state.buy_cards(3 * price, commitment.clone())).unwrap();
+        MockEnv::set_block_height(11);
+        assert!(block_on(fx.state.reveal_cards(draw.draw_id.clone(), salt.clone())).is_err());
+
+        // block 11 still feeds the seed, the first mix of block 12 freezes it
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.contribute_entropy("late".to_string())).unwrap();
+        assert!(seed_pool(&fx).is_none());
+        MockEnv::set_block_height(12);
+        block_on(fx.state.contribute_entropy("frozen".to_string())).unwrap();
+        let frozen = seed_pool(&fx);
+        assert!(frozen.is_some());
+        MockEnv::set_block_height(20);
+        block_on(fx.state.contribute_entropy("after".to_string())).unwrap();
+        assert_eq!(seed_pool(&fx), frozen);
+
+        MockEnv::set_sender("user-0");
+        let cards = block_on(fx.state.reveal_cards(draw.draw_id, salt.clone())).unwrap();
+        assert_eq!(cards.len(), 3);
+
+        // a draw held back past its deadline is forfeited without a refund
+        let draw = block_on(fx.state.buy_cards(price, commitment)).unwrap();
+        let (reserved, treasury) = (fx.state.deck.reserved, fx.state.treasury);
+        MockEnv::set_sender("user-1");
+        assert!(block_on(fx.state.forfeit_expired_draws("user-0".to_string())).is_err());
+        MockEnv::set_block_height(draw.reveal_by_block + 1);
+        MockEnv::set_sender("user-0");
+        let err = block_on(fx.state.reveal_cards(draw.draw_id, salt)).unwrap_err();
+        assert_eq!(err, "draw was not revealed in time and can only be forfeited");
+        MockEnv::set_sender("user-1");
+        assert_eq!(block_on(fx.state.forfeit_expired_draws("user-0".to_string())), Ok(1));
+        assert_eq!(fx.state.deck.reserved, reserved - 1);
+        assert_eq!(fx.state.treasury, treasury);
+        MockEnv::set_sender("user-0");
+        assert!(block_on(fx.state.get_my_pending_draws()).is_empty());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn sealed_bids_lock_a_deposit_that_hidden_bids_lose() {
+        let mut fx = fixture(3, 0, 0);
+        let (start, reserve) = (fx.state.params.starting_balance, 2 * CHIPS_PER_UNIT);
+        let deadline = 10 + SEALED_REVEAL_BLOCKS;
+        MockEnv::set_block_height(0);
+        MockEnv::set_sender("user-0");
+        let offer = block_on(fx.state.offer_sealed(vec![Card::KING], reserve, deadline));
+        let offer_id = offer.unwrap().offer_id;
+        let (won, unaffordable) = (3 * CHIPS_PER_UNIT, 2 * start);
+        for (user, amount) in [("user-1", won), ("user-2", unaffordable)] {
+            MockEnv::set_sender(user);
+            let commitment = SealedBid::commitment_for(amount, "salt");
+            let bid = block_on(fx.state.bid_sealed(offer_id.clone(), commitment)).unwrap();
+            assert_eq!(bid.deposit, reserve);
+            assert_eq!(fx.user(user).unwrap().locked_balance, reserve);
+        }
+
+        // user-2 committed more than it holds, the reveal is refused
+        MockEnv::set_block_height(10);
+        let reveal = |fx: &mut Fixture, amount| {
+            block_on(fx.state.reveal_bid(offer_id.clone(), amount, "salt".to_string()))
+        };
+        assert_eq!(reveal(&mut fx, unaffordable).unwrap_err(), "insufficient balance for bid");
+        MockEnv::set_sender("user-1");
+        assert_eq!(reveal(&mut fx, won).unwrap().deposit, 0);
+        assert_eq!(fx.user("user-1").unwrap().locked_balance, won);
+
+        MockEnv::set_block_height(deadline);
+        MockEnv::set_sender("user-0");
+        let (treasury, offer) = (fx.state.treasury, fx.offer(&offer_id).unwrap());
+        block_on(fx.state.resolve(offer_id.clone())).unwrap();
+        let hidden = fx.user("user-2").unwrap();
+        assert_eq!((hidden.balance, hidden.locked_balance), (start - reserve, 0));
+        let rake = won * fx.state.params.sale_rake_percent / 100;
+        assert_eq!(fx.state.treasury, treasury + reserve + rake - offer.listing_fee_refund());
+        let bids = block_on(fx.state.get_sealed_bids(offer_id));
+        assert!(bids.iter().all(|b| b.deposit == 0));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn newcomer_bracket_caps_stakes_and_subsidises_lost_checks() {
+        let mut fx = fixture(3, 1, 0);
+        let options = HandOptions {
+            newcomers_only: true,
+            ..fx.hand(&fx.hand_ids[0]).unwrap().options()
+        };
+        let held = |fx: &Fixture| -> u64 {
+            fx.user_ids.iter().map(|id| fx.user(id).unwrap().balance).sum::<u64>()
+                + fx.state.treasury
+        };
+        fx.state.treasury = 100 * CHIPS_PER_UNIT;
+        MockEnv::set_block_height(0);
+        MockEnv::set_sender("user-0");
+        let hand = block_on(fx.state.start_hand(Card::ACE, 1, vec![Card::ACE], options));
+        let hand_id = hand.unwrap().hand_id;
+
+        MockEnv::set_sender("user-2");
+        let capped = block_on(fx.state.stake(hand_id.clone(), vec![Card::ACE; 4], 4));
+        assert_eq!(capped.unwrap_err(), "newcomer bracket stakes are capped at 3 cards");
+        MockEnv::set_block_height(NEWCOMER_AGE_BLOCKS);
+        let veteran = block_on(fx.state.stake(hand_id.clone(), vec![Card::ACE; 2], 2));
+        assert_eq!(veteran.unwrap_err(), "only newcomers can play newcomer bracket hands");
+
+        // the creator staked an honest ACE, so the check loses and the
+        // treasury pays back part of the penalty
+        MockEnv::set_block_height(NEWCOMER_AGE_BLOCKS - 1);
+        MockEnv::set_sender("user-1");
+        let loss = payouts::checker_delta(&fx.hand(&hand_id).unwrap(), false).unsigned_abs();
+        let (balance, before) = (fx.user("user-1").unwrap().balance, held(&fx));
+        let outcome = block_on(fx.state.check(hand_id)).unwrap();
+        assert!(!outcome.bluff_detected);
+        let lines: Vec<&PayoutLine> =
+            outcome.payouts.iter().filter(|l| l.user_id == "user-1").collect();
+        let subsidy = lines.iter().find(|l| l.reason == PayoutReason::NewcomerSubsidy).unwrap();
+        assert_eq!(subsidy.amount as u64, loss * NEWCOMER_SUBSIDY_PERCENT / 100);
+        let net: i64 = lines.iter().map(|l| l.amount).sum();
+        assert_eq!(fx.user("user-1").unwrap().balance as i64, balance as i64 + net);
+        assert_eq!(held(&fx), before);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn withdrawals_wait_on_their_transfer_and_failed_ones_are_reclaimed() {
+        let mut fx = fixture(2, 0, 0);
+        let start = fx.state.params.starting_balance;
+        let supply = block_on(fx.state.get_total_supply());
+        fx.state.admin = "admin".to_string();
+        MockEnv::set_block_height(0);
+        MockEnv::set_sender("user-0");
+        assert!(block_on(fx.state.withdraw(2 * start)).is_err());
+        let [done, failed, stuck] =
+            [10, 20, 30].map(|amount| block_on(fx.state.withdraw(amount)).unwrap());
+        assert_eq!(fx.user("user-0").unwrap().balance, start - 60);
+
+        MockEnv::set_sender("admin");
+        block_on(fx.state.settle_transfer(done.intent_id.clone(), true)).unwrap();
+        block_on(fx.state.settle_transfer(failed.intent_id.clone(), false)).unwrap();
+        assert!(block_on(fx.state.settle_transfer(done.intent_id, false)).is_err());
+        assert_eq!(block_on(fx.state.get_total_supply()), supply - 10);
+
+        // only the requester gets a failed transfer back
+        MockEnv::set_sender("user-1");
+        let err = block_on(fx.state.reclaim_failed_transfer(failed.intent_id.clone()));
+        assert_eq!(err.unwrap_err(), "only the requester can reclaim a transfer");
+        MockEnv::set_sender("user-0");
+        let reclaimed = block_on(fx.state.reclaim_failed_transfer(failed.intent_id)).unwrap();
+        assert_eq!(reclaimed.status, TransferStatus::Reclaimed);
+
+        // a transfer nobody reported on comes back once it timed out
+        MockEnv::set_block_height(TRANSFER_TIMEOUT_BLOCKS - 1);
+        assert!(block_on(fx.state.reclaim_failed_transfer(stuck.intent_id.clone())).is_err());
+        MockEnv::set_block_height(TRANSFER_TIMEOUT_BLOCKS);
+        block_on(fx.state.reclaim_failed_transfer(stuck.intent_id)).unwrap();
+        assert_eq!(fx.user("user-0").unwrap().balance, start - 10);
+        assert_eq!(block_on(fx.state.get_my_transfers()).len(), 3);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn cold_storage_blocks_play_until_the_unlock_delay_passed() {
+        let mut fx = fixture(2, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        let locked = "account is in cold storage, request an unlock first";
+        let stake = |fx: &mut Fixture| {
+            block_on(fx.state.stake(hand_id.clone(), vec![Card::ACE; 2], 2))
+        };
+        MockEnv::set_block_height(10);
+        MockEnv::set_sender("user-1");
+        let err = block_on(fx.state.request_unlock()).unwrap_err();
+        assert_eq!(err, "account is not in cold storage");
+        block_on(fx.state.enable_cold_storage()).unwrap();
+        assert_eq!(stake(&mut fx).unwrap_err(), locked);
+        assert_eq!(block_on(fx.state.withdraw(10)).unwrap_err(), locked);
+
+        let unlock_at = 10 + COLD_STORAGE_UNLOCK_BLOCKS;
+        let user = block_on(fx.state.request_unlock()).unwrap();
+        assert_eq!(user.unlock_at_block, Some(unlock_at));
+        let err = block_on(fx.state.request_unlock()).unwrap_err();
+        assert_eq!(err, "unlock already requested");
+        MockEnv::set_block_height(unlock_at - 1);
+        assert_eq!(stake(&mut fx).unwrap_err(), locked);
+        MockEnv::set_block_height(unlock_at);
+        stake(&mut fx).unwrap();
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn packs_are_gifted_sold_and_opened_into_a_draw() {
+        let mut fx = fixture(3, 0, 0);
+        let (start, treasury) = (fx.state.params.starting_balance, fx.state.treasury);
+        MockEnv::set_block_height(0);
+        MockEnv::set_sender("user-0");
+        let booster = block_on(fx.state.buy_sealed_pack(PackKind::Booster)).unwrap();
+        let boxed = block_on(fx.state.buy_sealed_pack(PackKind::Box)).unwrap();
+        assert_eq!(fx.user("user-0").unwrap().balance, start - 23 * CHIPS_PER_UNIT);
+        assert_eq!(fx.state.treasury, treasury + 23 * CHIPS_PER_UNIT);
+
+        let gift = |fx: &mut Fixture, to: &str| {
+            block_on(fx.state.gift_pack(booster.pack_id.clone(), to.to_string()))
+        };
+        assert_eq!(gift(&mut fx, "user-0").unwrap_err(), "cannot gift a pack to yourself");
+        assert_eq!(gift(&mut fx, "user-1").unwrap().owner_id, "user-1");
+        assert_eq!(gift(&mut fx, "user-2").unwrap_err(), "pack belongs to another user");
+
+        // a listed pack stays sealed until its offer settles
+        let listed = fx.state.offer_pack(boxed.pack_id.clone(), 20 * CHIPS_PER_UNIT, 10);
+        let offer_id = block_on(listed).unwrap().offer_id;
+        let commitment = randomness::commitment_for("salt");
+        let opened = block_on(fx.state.open_pack(boxed.pack_id.clone(), commitment.clone()));
+        assert_eq!(opened.unwrap_err(), "pack is listed on an offer");
+        MockEnv::set_sender("user-2");
+        block_on(fx.state.bid(offer_id.clone(), 21 * CHIPS_PER_UNIT)).unwrap();
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.resolve(offer_id)).unwrap();
+        let sold = block_on(fx.state.get_pack(boxed.pack_id)).unwrap();
+        assert_eq!((sold.owner_id.as_str(), sold.is_listed), ("user-2", false));
+        assert!(block_on(fx.state.get_my_packs()).is_empty());
+
+        MockEnv::set_sender("user-1");
+        let open = |fx: &mut Fixture| {
+            block_on(fx.state.open_pack(booster.pack_id.clone(), commitment.clone()))
+        };
+        assert_eq!(open(&mut fx).unwrap().count, PackKind::Booster.card_count());
+        assert_eq!(open(&mut fx).unwrap_err(), "pack already opened");
+        assert!(block_on(fx.state.get_my_packs()).is_empty());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn team_checks_need_a_majority_within_the_window() {
+        let mut fx = fixture(4, 1, 0);
+        let options = fx.hand(&fx.hand_ids[0]).unwrap().options();
+        let teams = TeamOptions {
+            teammate: "user-1".to_string(),
+            opponents: vec!["user-2".to_string(), "user-3".to_string()],
+            confirm_window_blocks: 5,
+        };
+        let start = |fx: &mut Fixture, teams| {
+            let cards = vec![Card::ACE];
+            block_on(fx.state.start_team_hand(Card::ACE, 1, cards, options.clone(), teams))
+        };
+        MockEnv::set_block_height(10);
+        MockEnv::set_sender("user-0");
+        let short = TeamOptions { opponents: vec!["user-2".to_string()], ..teams.clone() };
+        let err = start(&mut fx, short).unwrap_err();
+        assert_eq!(err, format!("a team hand needs {} opponents", team::TEAM_SIZE));
+        let hand_id = start(&mut fx, teams).unwrap().hand_id;
+
+        // stakes alternate between the teams
+        let stake = |fx: &mut Fixture, user: &str| {
+            MockEnv::set_sender(user);
+            block_on(fx.state.stake(hand_id.clone(), vec![Card::ACE; 2], 2))
+        };
+        let err = stake(&mut fx, "user-1").unwrap_err();
+        assert_eq!(err, "team hands alternate stakes, it is the other team's turn");
+        stake(&mut fx, "user-2").unwrap();
+
+        let confirm = |fx: &mut Fixture, user: &str| {
+            MockEnv::set_sender(user);
+            block_on(fx.state.confirm_team_check(hand_id.clone()))
+        };
+        assert!(confirm(&mut fx, "user-3").is_err());
+        assert!(confirm(&mut fx, "user-0").unwrap().is_none());
+        let err = block_on(fx.state.check(hand_id.clone())).unwrap_err();
+        assert_eq!(err, "team hands are checked through confirm_team_check");
+
+        // the first confirmation lapsed, so the teammate opens a new window
+        MockEnv::set_block_height(16);
+        assert!(confirm(&mut fx, "user-1").unwrap().is_none());
+        let team = block_on(fx.state.get_team_hand(hand_id.clone())).unwrap();
+        assert_eq!(team.pending_check.unwrap().confirmations, vec!["user-1".to_string()]);
+        let outcome = confirm(&mut fx, "user-0").unwrap().unwrap();
+        assert!(!outcome.bluff_detected);
+        assert!(fx.hand(&hand_id).unwrap().is_resolved());
+        let team = block_on(fx.state.get_team_hand(hand_id)).unwrap();
+        assert!(team.pending_check.is_none());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn a_stake_voids_the_team_check_confirmations_gathered_so_far() {
+        let mut fx = fixture(4, 1, 0);
+        let options = fx.hand(&fx.hand_ids[0]).unwrap().options();
+        let teams = TeamOptions {
+            teammate: "user-1".to_string(),
+            opponents: vec!["user-2".to_string(), "user-3".to_string()],
+            confirm_window_blocks: 5,
+        };
+        MockEnv::set_block_height(10);
+        MockEnv::set_sender("user-0");
+        let started = fx.state.start_team_hand(Card::ACE, 1, vec![Card::ACE], options, teams);
+        let hand_id = block_on(started).unwrap().hand_id;
+        let stake = |fx: &mut Fixture, user: &str, count: usize| {
+            MockEnv::set_sender(user);
+            let cards = vec![Card::ACE; count];
+            block_on(fx.state.stake(hand_id.clone(), cards, count as u32))
+        };
+        let confirm = |fx: &mut Fixture, user: &str| {
+            MockEnv::set_sender(user);
+            block_on(fx.state.confirm_team_check(hand_id.clone()))
+        };
+        stake(&mut fx, "user-2", 2).unwrap();
+
+        // user-0 confirms, then the teammate stakes instead of confirming
+        assert!(confirm(&mut fx, "user-0").unwrap().is_none());
+        stake(&mut fx, "user-1", 3).unwrap();
+        let team = block_on(fx.state.get_team_hand(hand_id.clone())).unwrap();
+        assert!(team.pending_check.is_none());
+
+        // one confirmation from the other side is still short of its majority
+        assert!(confirm(&mut fx, "user-2").unwrap().is_none());
+        let team = block_on(fx.state.get_team_hand(hand_id.clone())).unwrap();
+        assert_eq!(team.pending_check.unwrap().confirmations, vec!["user-2".to_string()]);
+        assert!(confirm(&mut fx, "user-3").unwrap().is_some());
+        assert!(fx.hand(&hand_id).unwrap().is_resolved());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn trades_swap_cards_or_hand_the_offered_ones_back() {
+        let mut fx = fixture(2, 0, 0);
+        let count = |fx: &Fixture, user: &str, card: Card| {
+            fx.user(user).unwrap().cards.iter().filter(|c| **c == card).count()
+        };
+        let propose = |fx: &mut Fixture, to: &str| {
+            let (offered, requested) = (vec![Card::ACE; 2], vec![Card::KING]);
+            block_on(fx.state.propose_trade(to.to_string(), offered, requested))
+        };
+        MockEnv::set_sender("user-0");
+        assert_eq!(propose(&mut fx, "user-0").unwrap_err(), "cannot trade with yourself");
+        let rejected = propose(&mut fx, "user-1").unwrap().trade_id;
+        let accepted = propose(&mut fx, "user-1").unwrap().trade_id;
+        assert_eq!(count(&fx, "user-0", Card::ACE), 0);
+        let err = block_on(fx.state.accept_trade(accepted.clone())).unwrap_err();
+        assert_eq!(err, "only the counterparty can accept a trade");
+
+        // rejecting hands the escrowed cards back to the proposer
+        MockEnv::set_sender("user-1");
+        let trade = block_on(fx.state.reject_trade(rejected.clone())).unwrap();
+        assert_eq!(trade.status, TradeStatus::Rejected);
+        assert_eq!(count(&fx, "user-0", Card::ACE), 2);
+        assert!(block_on(fx.state.accept_trade(rejected)).is_err());
+
+        let trade = block_on(fx.state.accept_trade(accepted)).unwrap();
+        assert_eq!(trade.status, TradeStatus::Accepted);
+        assert_eq!((count(&fx, "user-0", Card::ACE), count(&fx, "user-0", Card::KING)), (2, 5));
+        assert_eq!((count(&fx, "user-1", Card::ACE), count(&fx, "user-1", Card::KING)), (6, 3));
+        assert_eq!(block_on(fx.state.get_my_trades()).len(), 2);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn buyouts_sell_right_away_and_refund_the_highest_bid() {
+        let mut fx = fixture(3, 0, 0);
+        let start = fx.state.params.starting_balance;
+        let listed = |fx: &mut Fixture, buyout| {
+            let amount = 2 * CHIPS_PER_UNIT;
+            block_on(fx.state.offer(vec![Card::KING], amount, 10, None, buyout, None))
+        };
+        MockEnv::set_block_height(0);
+        MockEnv::set_sender("user-0");
+        let err = listed(&mut fx, Some(2 * CHIPS_PER_UNIT)).unwrap_err();
+        assert_eq!(err, "buyout price must be above the initial price");
+        let offer_id = listed(&mut fx, Some(5 * CHIPS_PER_UNIT)).unwrap().offer_id;
+        let err = block_on(fx.state.buy_now(offer_id.clone())).unwrap_err();
+        assert_eq!(err, "creator cannot buy own offer");
+
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.bid(offer_id.clone(), 3 * CHIPS_PER_UNIT)).unwrap();
+        let seller = fx.user("user-0").unwrap().balance;
+        MockEnv::set_sender("user-2");
+        let offer = block_on(fx.state.buy_now(offer_id.clone())).unwrap();
+        assert!(offer.is_resolved);
+        assert_eq!(offer.current_bidder_id.as_deref(), Some("user-2"));
+        let outbid = fx.user("user-1").unwrap();
+        assert_eq!((outbid.balance, outbid.locked_balance), (start, 0));
+        let buyer = fx.user("user-2").unwrap();
+        assert_eq!(buyer.balance, start - 5 * CHIPS_PER_UNIT);
+        assert_eq!(buyer.cards.iter().filter(|c| **c == Card::KING).count(), 5);
+        assert!(fx.user("user-0").unwrap().balance > seller);
+
+        MockEnv::set_sender("user-1");
+        let err = block_on(fx.state.buy_now(offer_id)).unwrap_err();
+        assert_eq!(err, "offer already resolved");
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn accepted_counters_sell_at_the_counter_price() {
+        let mut fx = fixture(3, 0, 1);
+        let offer_id = fx.offer_ids[0].clone();
+        let offer = fx.state.offers.get(&offer_id).unwrap();
+        let bid = offer.current_bid.unwrap();
+        let bidder_id = offer.current_bidder_id.clone().unwrap();
+        fx.state.offers.insert(offer_id.clone(), Offer { reserve_price: Some(bid * 10), ..offer });
+
+        MockEnv::set_sender(&bidder_id);
+        assert!(block_on(fx.state.counter(offer_id.clone(), bid + 5)).is_err());
+        MockEnv::set_sender("user-0");
+        assert!(block_on(fx.state.counter(offer_id.clone(), bid)).is_err());
+        block_on(fx.state.counter(offer_id.clone(), bid + 5)).unwrap();
+
+        MockEnv::set_sender(&bidder_id);
+        let balance = fx.user(&bidder_id).unwrap().balance;
+        let accepted = block_on(fx.state.accept_counter(offer_id.clone())).unwrap();
+        assert_eq!(accepted.negotiation_state, NegotiationState::Accepted);
+        assert_eq!(fx.user(&bidder_id).unwrap().balance, balance - 5);
+        assert!(block_on(fx.state.withdraw_bid(offer_id.clone())).is_err());
+
+        // the accepted counter sells even though it is below the reserve
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.resolve(offer_id.clone())).unwrap();
+        let offer = fx.state.offers.get(&offer_id).unwrap();
+        assert!(offer.is_resolved);
+        assert_eq!(offer.current_bid, Some(bid + 5));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn burned_cards_pay_for_crafted_ones() {
+        let mut fx = fixture(1, 0, 0);
+        MockEnv::set_sender("user-0");
+        let deck = fx.state.deck.size();
+        let twos = vec![Card::TWO; 4];
+        fx.state.users.insert("user-0".to_string(), User {
+            cards: twos.clone(),
+            ..fx.user("user-0").unwrap()
+        });
+
+        assert_eq!(block_on(fx.state.burn_cards(twos)).unwrap(), 4);
+        assert_eq!(fx.state.deck.size(), deck + 4);
+        let cost = CraftingRate::for_card(Card::ACE).craft_cost;
+        assert!(cost > 4 && block_on(fx.state.craft_card(Card::ACE, cost)).is_err());
+        assert!(block_on(fx.state.craft_card(Card::TWO, 3)).is_err());
+
+        let user = block_on(fx.state.craft_card(Card::TWO, 4)).unwrap();
+        assert_eq!((user.cards, user.crafting_points), (vec![Card::TWO], 0));
+        assert_eq!(fx.state.deck.size(), deck + 3);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn rarely_claimed_ranks_earn_the_creator_a_bonus() {
+        let mut fx = fixture(2, 1, 0);
+        let options = fx.state.hands.get(&fx.hand_ids[0]).unwrap().options();
+        let mut claims = vec![0; Card::ALL.len()];
+        claims[token::token_id(Card::ACE)] = CLAIM_DIVERSITY_MIN_HANDS;
+        let hands = CLAIM_DIVERSITY_MIN_HANDS;
+        fx.state.claim_totals.push(ClaimTotals { hands, claims });
+        fx.state.treasury = 100 * CHIPS_PER_UNIT;
+
+        let bonuses = block_on(fx.state.get_claim_bonuses());
+        let bonus_of = |card| bonuses.iter().find(|b| b.card == card).unwrap().bonus_percent;
+        assert_eq!(bonus_of(Card::ACE), 0);
+        assert_eq!(bonus_of(Card::TWO), MAX_CLAIM_DIVERSITY_BONUS_PERCENT);
+
+        MockEnv::set_sender("user-0");
+        let hand = block_on(fx.state.start_hand(Card::TWO, 1, vec![Card::TWO], options)).unwrap();
+        assert_eq!(hand.claim_bonus_percent, MAX_CLAIM_DIVERSITY_BONUS_PERCENT);
+        assert_eq!(fx.state.claim_totals.len(), 3);
+        MockEnv::set_sender("user-1");
+        let outcome = block_on(fx.state.check(hand.hand_id)).unwrap();
+        let bonus = outcome
+            .payouts
+            .iter()
+            .find(|l| l.reason == PayoutReason::ClaimDiversityBonus)
+            .unwrap();
+        assert_eq!(bonus.user_id, "user-0");
+        assert_eq!(bonus.amount as u64, hand.pot * MAX_CLAIM_DIVERSITY_BONUS_PERCENT / 100);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn describe_lists_every_entrypoint_with_its_role() {
+        let fx = fixture(0, 0, 0);
+        let catalog = block_on(fx.state.describe());
+        let entry = |name: &str| catalog.iter().find(|e| e.name == name).unwrap();
+
+        let bid = entry("bid");
+        assert!(bid.mutates && bid.role == EntrypointRole::Anyone);
+        assert_eq!(bid.params[1].name, "bid_amout");
+        assert_eq!(bid.returns, "result<(), string>");
+        let set_params = entry("set_params");
+        assert_eq!(set_params.role, EntrypointRole::Admin);
+        assert_eq!(set_params.params[0].ty, "GameParams");
+        assert!(!entry("describe").mutates);
+        // batch closes the interface without a semicolon
+        assert_eq!(entry("batch").returns, "result<list<BatchResult>, string>");
+        assert_eq!(entry("withdraw_treasury").role, EntrypointRole::Admin);
+    }
+
+    #[test]
+    fn sales_and_checks_are_raked_into_the_treasury() {
+        let mut fx = fixture(3, 1, 1);
+        let held = |fx: &Fixture| -> u64 {
+            fx.user_ids.iter().map(|id| fx.user(id).unwrap().balance).sum::<u64>()
+                + fx.state.treasury
+        };
+        let supply = block_on(fx.state.get_total_supply());
+
+        let treasury = block_on(fx.state.get_treasury_balance());
+        let seller = fx.user("user-0").unwrap().balance;
+        let offer = fx.state.offers.get(&fx.offer_ids[0]).unwrap();
+        let (bid, refund) = (offer.current_bid.unwrap(), payouts::listing_refund(&offer, treasury));
+        let rake = bid * fx.state.params.sale_rake_percent / 100;
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.resolve(fx.offer_ids[0].clone())).unwrap();
+        assert_eq!(fx.user("user-0").unwrap().balance, seller + bid - rake + refund);
+        assert_eq!(fx.state.treasury, treasury - refund + rake);
+        assert_eq!(held(&fx), supply);
+
+        // user-1 checks the creator's honest ACE and pays for it, the creator's
+        // reward is raked
+        MockEnv::set_sender("user-1");
+        let outcome = block_on(fx.state.check(fx.hand_ids[0].clone())).unwrap();
+        let raked: Vec<_> =
+            outcome.payouts.iter().filter(|l| l.reason == PayoutReason::Rake).collect();
+        assert_eq!(raked.len(), 1);
+        assert_eq!(raked[0].user_id, "user-0");
+        assert_eq!(held(&fx), supply);
+
+        assert!(block_on(fx.state.withdraw_treasury("user-2".to_string(), 1)).is_err());
+        MockEnv::set_sender("admin");
+        fx.state.admin = "admin".to_string();
+        let before = fx.user("user-2").unwrap().balance;
+        let left = block_on(fx.state.withdraw_treasury("user-2".to_string(), 10)).unwrap();
+        assert_eq!(left, fx.state.treasury);
+        assert_eq!(fx.user("user-2").unwrap().balance, before + 10);
+        assert_eq!(held(&fx), supply);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn credits_repay_debt_before_reaching_the_balance() {
+        let mut fx = fixture(2, 0, 1);
+        fx.state.debts.insert("user-0".to_string(), 5);
+        let (seller, treasury) = (fx.user("user-0").unwrap().balance, fx.state.treasury);
+        let offer = fx.state.offers.get(&fx.offer_ids[0]).unwrap();
+        let (bid, refund) = (offer.current_bid.unwrap(), payouts::listing_refund(&offer, treasury));
+        let rake = bid * fx.state.params.sale_rake_percent / 100;
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.resolve(fx.offer_ids[0].clone())).unwrap();
+        assert_eq!(fx.user("user-0").unwrap().balance, seller + bid - rake + refund - 5);
+        assert_eq!(fx.state.debts.get(&"user-0".to_string()), Some(0));
+        assert_eq!(fx.state.treasury, treasury - refund + rake + 5);
+
+        // withdrawals only move what is above the floor
+        fx.state.params.balance_floor = fx.user("user-1").unwrap().balance - 10;
+        MockEnv::set_sender("user-1");
+        let err = block_on(fx.state.withdraw(11)).unwrap_err();
+        assert_eq!(err, "insufficient unlocked balance for withdrawal");
+        block_on(fx.state.withdraw(10)).unwrap();
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn gifts_move_cards_and_chips_between_users() {
+        let mut fx = fixture(2, 0, 0);
+        MockEnv::set_sender("user-0");
+        let recipient = fx.user("user-1").unwrap();
+        assert!(block_on(fx.state.gift_cards("user-0".to_string(), vec![Card::ACE])).is_err());
+        assert!(block_on(fx.state.gift_balance("nobody".to_string(), 5)).is_err());
+        assert!(block_on(fx.state.gift_cards("user-1".into(), vec![Card::ACE; 5])).is_err());
+
+        block_on(fx.state.gift_cards("user-1".into(), vec![Card::ACE, Card::TWO])).unwrap();
+        fx.state.debts.insert("user-0".to_string(), 1);
+        let err = block_on(fx.state.gift_balance("user-1".to_string(), 10)).unwrap_err();
+        assert_eq!(err, "repay your debt before gifting");
+        fx.state.debts.insert("user-0".to_string(), 0);
+        fx.state.debts.insert("user-1".to_string(), 3);
+        block_on(fx.state.gift_balance("user-1".to_string(), 10)).unwrap();
+        let gifted = fx.user("user-1").unwrap();
+        assert_eq!(gifted.cards.len(), recipient.cards.len() + 2);
+        assert_eq!(gifted.balance, recipient.balance + 7);
+        assert_eq!(fx.state.debts.get(&"user-1".to_string()), Some(0));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn retried_idempotent_calls_are_not_applied_twice() {
+        let mut fx = fixture(2, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        let stake = || {
+            BatchAction::Stake(StakeArgs {
+                hand_id: hand_id.clone(),
+                cards: vec![Card::ACE; 2],
+                claimed_count: 2,
+            })
+        };
+        MockEnv::set_sender("user-1");
+        let cards = fx.user("user-1").unwrap().cards.len();
+        let first = block_on(fx.state.with_idempotency("r-1".to_string(), stake())).unwrap();
+        let retry = block_on(fx.state.with_idempotency("r-1".to_string(), stake())).unwrap();
+        let stakes = |r: BatchResult| match r {
+            BatchResult::Hand(hand) => hand.stakes.len(),
+            _ => panic!("staking answers with the hand"),
+        };
+        assert_eq!((stakes(first), stakes(retry)), (2, 2));
+        assert_eq!(fx.user("user-1").unwrap().cards.len(), cards - 2);
+
+        // the key is per caller, and a new key runs the action again
+        assert!(block_on(fx.state.with_idempotency("r-2".to_string(), stake())).is_ok());
+        assert_eq!(fx.user("user-1").unwrap().cards.len(), cards - 4);
+        assert!(block_on(fx.state.with_idempotency(String::new(), stake())).is_err());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn resolved_hands_can_be_replayed_unmasked() {
+        let mut fx = fixture(3, 1, 0);
+        let options = fx.state.hands.get(&fx.hand_ids[0]).unwrap().options();
+        MockEnv::set_sender("user-0");
+        MockEnv::set_block_height(5);
+        let hand = block_on(fx.state.start_hand(Card::KING, 1, vec![Card::TWO], options)).unwrap();
+        let hand_id = hand.hand_id;
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.stake(hand_id.clone(), vec![Card::KING; 2], 2)).unwrap();
+        assert!(block_on(fx.state.get_hand_replay(hand_id.clone())).is_err());
+
+        MockEnv::set_sender("user-2");
+        block_on(fx.state.check(hand_id.clone())).unwrap();
+        let replay = block_on(fx.state.get_hand_replay(hand_id)).unwrap();
+        let kinds: Vec<_> = replay.actions.iter().map(|a| a.kind).collect();
+        use HandActionKind::*;
+        assert_eq!(kinds, vec![Started, Staked, Checked]);
+        assert_eq!(replay.actions[0].cards, vec![Card::TWO]);
+        assert_eq!(replay.actions[0].at_block, 5);
+        assert_eq!(replay.actions[2].user_id, "user-2");
+        assert_eq!(replay.actions[2].bluff_detected, Some(false));
+        assert!(!replay.payouts.is_empty());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn bundles_sell_the_picked_cards_and_close_when_empty() {
+        let mut fx = fixture(2, 0, 0);
+        let start = fx.state.params.starting_balance;
+        MockEnv::set_sender("user-0");
+        let cards = vec![Card::ACE, Card::ACE, Card::KING];
+        let listing_fee = Offer::listing_fee_for(&cards);
+        let offer = block_on(fx.state.offer_bundle(cards, 100, 50)).unwrap();
+        let offer_id = offer.offer_id;
+
+        MockEnv::set_sender("user-1");
+        assert!(block_on(fx.state.bid(offer_id.clone(), 500)).is_err());
+        assert!(block_on(fx.state.buy_from_bundle(offer_id.clone(), vec![Card::QUEEN])).is_err());
+        let offer = block_on(fx.state.buy_from_bundle(offer_id.clone(), vec![Card::ACE])).unwrap();
+        assert_eq!(offer.cards, vec![Card::ACE, Card::KING]);
+        assert!(!offer.is_resolved);
+        assert_eq!(fx.user("user-1").unwrap().balance, start - 100);
+        assert_eq!(fx.user("user-0").unwrap().balance, start - listing_fee + 98);
+
+        let rest = vec![Card::KING, Card::ACE];
+        let offer = block_on(fx.state.buy_from_bundle(offer_id, rest)).unwrap();
+        assert!(offer.cards.is_empty());
+        assert!(offer.is_resolved);
+        assert_eq!(fx.user("user-1").unwrap().balance, start - 300);
+        assert_eq!(fx.state.treasury, listing_fee - offer.listing_fee_refund() + 6);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn queued_players_of_the_same_stake_size_are_matched() {
+        let mut fx = fixture(3, 0, 0);
+        MockEnv::set_sender("user-0");
+        assert!(block_on(fx.state.join_queue(2)).unwrap().is_none());
+        assert!(block_on(fx.state.join_queue(2)).is_err());
+        MockEnv::set_sender("user-1");
+        assert!(block_on(fx.state.join_queue(3)).unwrap().is_none());
+
+        MockEnv::set_sender("user-2");
+        let hand = block_on(fx.state.join_queue(2)).unwrap().unwrap();
+        assert_eq!(hand.creator, "user-0");
+        assert_eq!(hand.allowed_stakers, vec!["user-2".to_string()]);
+        assert_eq!(hand.claimed_card, Card::ACE);
+        let opening = hand.last_stake().unwrap();
+        assert_eq!(opening.cards, vec![Card::ACE, Card::ACE]);
+        assert_eq!(opening.claimed_count, 2);
+        assert_eq!(fx.user("user-0").unwrap().cards.len(), Card::ALL.len() * 4 - 2);
+
+        let queue = block_on(fx.state.get_match_queue());
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0].user_id, "user-1");
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.leave_queue()).unwrap();
+        assert!(block_on(fx.state.match_players()).unwrap().is_empty());
+        assert!(block_on(fx.state.get_match_queue()).is_empty());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn achievements_unlock_from_stats_and_pay_out_once() {
+        let mut fx = fixture(3, 1, 0);
+        let options = fx.state.hands.get(&fx.hand_ids[0]).unwrap().options();
+        // user-0 unlocked its first hand when the fixture opened one
+        MockEnv::set_sender("user-1");
+        assert!(block_on(fx.state.claim_achievement_rewards()).is_err());
+        let offer = block_on(fx.state.offer(vec![Card::JOKER], 10, 50, None, Some(20), None));
+        MockEnv::set_sender("user-2");
+        block_on(fx.state.buy_now(offer.unwrap().offer_id)).unwrap();
+
+        let badges = block_on(fx.state.get_user_badges("user-1".to_string()));
+        let kinds: Vec<_> = badges.iter().map(|b| b.kind).collect();
+        assert_eq!(kinds, vec![AchievementKind::JokerSold]);
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.start_hand(Card::KING, 1, vec![Card::KING], options)).unwrap();
+        let progress = block_on(fx.state.get_my_achievements());
+        let unlocked = progress.iter().filter(|p| p.unlocked.is_some()).count();
+        assert_eq!(unlocked, 2);
+        let checks = progress.iter().find(|p| p.achievement.kind == AchievementKind::TenChecksWon);
+        assert_eq!(checks.unwrap().progress, 0);
+
+        let aces = |user: &User| user.cards.iter().filter(|c| **c == Card::ACE).count();
+        let before = aces(&fx.user("user-1").unwrap());
+        let user = block_on(fx.state.claim_achievement_rewards()).unwrap();
+        assert_eq!(aces(&user), before + 1);
+        assert!(block_on(fx.state.claim_achievement_rewards()).is_err());
+        let badges = block_on(fx.state.get_user_badges("user-1".to_string()));
+        assert!(badges.iter().all(|b| b.rewards_claimed));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn bids_lock_balance_until_outbid_or_settled() {
+        let mut fx = fixture(3, 0, 1);
+        let offer_id = fx.offer_ids[0].clone();
+        let bid = 2 * CHIPS_PER_UNIT;
+        let start = fx.state.params.starting_balance;
+        MockEnv::set_sender("user-1");
+        let breakdown = block_on(fx.state.get_balance_breakdown(None)).unwrap();
+        assert_eq!((breakdown.available, breakdown.locked), (start - bid, bid));
+
+        MockEnv::set_sender("user-2");
+        block_on(fx.state.bid(offer_id.clone(), bid + 5)).unwrap();
+        assert_eq!(fx.user("user-1").unwrap().locked_balance, 0);
+        assert_eq!(fx.user("user-1").unwrap().balance, start);
+        assert_eq!(fx.user("user-2").unwrap().locked_balance, bid + 5);
+        block_on(fx.state.bid(offer_id.clone(), bid + 10)).unwrap();
+        let raised = fx.user("user-2").unwrap();
+        assert_eq!((raised.balance, raised.locked_balance), (start - bid - 10, bid + 10));
+
+        // a bid the caller cannot cover leaves the current bidder's escrow alone
+        MockEnv::set_sender("user-1");
+        assert!(block_on(fx.state.bid(offer_id.clone(), 2 * start)).is_err());
+        assert_eq!(fx.user("user-2").unwrap().locked_balance, bid + 10);
+
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.resolve(offer_id)).unwrap();
+        let buyer = fx.user("user-2").unwrap();
+        assert_eq!((buyer.balance, buyer.locked_balance), (start - bid - 10, 0));
+        MockEnv::set_sender("nobody");
+        assert!(block_on(fx.state.get_balance_breakdown(None)).is_err());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn referrers_earn_a_cut_of_card_purchases_until_the_referral_ends() {
+        let mut fx = fixture(1, 0, 0);
+        fx.state.treasury = 10 * CHIPS_PER_UNIT;
+        let mut refer = |sender: &str, referrer: &str| {
+            MockEnv::set_sender(sender);
+            block_on(fx.state.register_user_with_referrer(String::new(), referrer.to_string()))
+        };
+        assert!(refer("user-0", "user-0").is_err());
+        assert!(refer("newcomer", "nobody").is_err());
+        let user = refer("newcomer", "user-0");
+        assert_eq!(user.unwrap().referrer.as_deref(), Some("user-0"));
+
+        let before = fx.user("user-0").unwrap().balance;
+        let price = fx.state.params.card_price;
+        block_on(fx.state.buy_cards(10 * price, "a".repeat(64))).unwrap();
+        let cut = 10 * price * fx.state.params.referral_percent / 100;
+        assert_eq!(fx.user("user-0").unwrap().balance, before + cut);
+
+        MockEnv::set_block_height(fx.state.params.referral_blocks);
+        block_on(fx.state.buy_cards(price, "b".repeat(64))).unwrap();
+        assert_eq!(fx.user("user-0").unwrap().balance, before + cut);
+        MockEnv::set_sender("user-0");
+        let earnings = block_on(fx.state.get_referral_earnings());
+        assert_eq!(earnings.len(), 1);
+        assert_eq!((earnings[0].referee_id.as_str(), earnings[0].earned), ("newcomer", cut));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn seasons_count_stats_afresh_and_pay_the_best_players() {
+        let mut fx = fixture(3, 0, 0);
+        MockEnv::set_sender("admin");
+        fx.state.admin = "admin".to_string();
+        fx.state.treasury = 10 * CHIPS_PER_UNIT;
+        fx.state.update_stats(&"user-2".to_string(), |s| s.hands_started += 5);
+        let config = SeasonConfig {
+            name: "spring".to_string(),
+            metric: StatsMetric::HandsStarted,
+            prizes: vec![3 * CHIPS_PER_UNIT, 2 * CHIPS_PER_UNIT, CHIPS_PER_UNIT],
+        };
+        let season = block_on(fx.state.start_season(config.clone())).unwrap();
+        assert!(block_on(fx.state.start_season(config)).is_err());
+
+        fx.state.update_stats(&"user-1".to_string(), |s| s.hands_started += 2);
+        fx.state.update_stats(&"user-0".to_string(), |s| s.hands_started += 1);
+        fx.state.update_stats(&"user-2".to_string(), |s| s.bluffs_caught += 1);
+        let standings = block_on(fx.state.get_season_standings(season.season_id.clone(), 10));
+        let ranked: Vec<_> = standings.unwrap().iter().map(|s| s.user_id.clone()).collect();
+        assert_eq!(ranked, vec!["user-1", "user-0", "user-2"]);
+
+        let before = fx.user("user-1").unwrap().balance;
+        let ended = block_on(fx.state.end_season()).unwrap();
+        let paid: Vec<_> = ended.prizes_paid.iter().map(|p| (p.user_id.as_str(), p.rank)).collect();
+        assert_eq!(paid, vec![("user-1", 1), ("user-0", 2)]);
+        assert_eq!(fx.user("user-1").unwrap().balance, before + 3 * CHIPS_PER_UNIT);
+        assert_eq!(fx.state.treasury, 5 * CHIPS_PER_UNIT);
+        assert!(block_on(fx.state.get_current_season()).is_none());
+        assert_eq!(block_on(fx.state.get_seasons()).len(), 1);
+        assert_eq!(fx.state.user_stats.get(&"user-2".to_string()).unwrap().hands_started, 5);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn ended_seasons_keep_an_archive_after_their_stats_are_dropped() {
+        let mut fx = fixture(3, 1, 1);
+        let (hand_id, offer_id) = (fx.hand_ids[0].clone(), fx.offer_ids[0].clone());
+        let bid = fx.offer(&offer_id).unwrap().current_bid.unwrap();
+        MockEnv::set_sender("admin");
+        fx.state.admin = "admin".to_string();
+        let config = SeasonConfig {
+            name: "summer".to_string(),
+            metric: StatsMetric::TotalWinnings,
+            prizes: Vec::new(),
+        };
+        let season_id = block_on(fx.state.start_season(config)).unwrap().season_id;
+
+        MockEnv::set_sender("user-1");
+        let pot = block_on(fx.state.check(hand_id.clone())).unwrap().pot;
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.resolve(offer_id)).unwrap();
+        assert!(block_on(fx.state.get_season_archive(season_id.clone())).is_none());
+        let live = block_on(fx.state.get_season_standings(season_id.clone(), 10)).unwrap();
+
+        MockEnv::set_sender("admin");
+        block_on(fx.state.end_season()).unwrap();
+        let archive = block_on(fx.state.get_season_archive(season_id.clone())).unwrap();
+        assert_eq!(archive.name, "summer");
+        assert_eq!(archive.volume, pot + bid);
+        let notable: Vec<_> = archive.notable_hands.iter().map(|h| h.hand_id.clone()).collect();
+        assert_eq!(notable, vec![hand_id]);
+        let ranked = |standings: &[UserStats]| -> Vec<(String, i64)> {
+            standings.iter().map(|s| (s.user_id.clone(), s.total_winnings)).collect()
+        };
+        assert_eq!(ranked(&archive.standings), ranked(&live));
+
+        // the live stats are gone, the standings now come from the archive
+        let key = season::stats_key(&season_id, "user-1");
+        assert!(fx.state.season_stats.get(&key).is_none());
+        let archived = block_on(fx.state.get_season_standings(season_id, 10)).unwrap();
+        assert_eq!(ranked(&archived), ranked(&live));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn season_end_rewards_referrers_of_active_referees_within_the_budget() {
+        let mut fx = fixture(2, 0, 0);
+        MockEnv::set_sender("admin");
+        fx.state.admin = "admin".to_string();
+        let config = SeasonConfig {
+            name: "autumn".to_string(),
+            metric: StatsMetric::HandsStarted,
+            prizes: Vec::new(),
+        };
+        block_on(fx.state.start_season(config)).unwrap();
+
+        // "b" has not played enough hands to count for user-0 yet
+        let min = season::REFERRAL_MIN_HANDS;
+        for (referee, referrer, hands) in
+            [("a", "user-0", min), ("b", "user-0", min - 1), ("c", "user-1", min)]
+        {
+            MockEnv::set_sender(referee);
+            let referrer = referrer.to_string();
+            block_on(fx.state.register_user_with_referrer(String::new(), referrer)).unwrap();
+            for hand in 0..hands {
+                fx.state.record_participation(&referee.to_string(), &hand.to_string());
+            }
+        }
+        let standings = block_on(fx.state.get_referral_standings());
+        let rows: Vec<_> = standings
+            .iter()
+            .map(|s| (s.referrer_id.as_str(), s.qualified_referees, s.pending_referees))
+            .collect();
+        assert_eq!(rows, vec![("user-0", 1, 1), ("user-1", 1, 0)]);
+        let reward = season::referral_reward(1);
+        assert!(reward > 0 && standings.iter().all(|s| s.reward == reward));
+
+        // the treasury only covers half of the second reward
+        fx.state.treasury = reward + reward / 2;
+        let before = fx.user("user-1").unwrap().balance;
+        MockEnv::set_sender("admin");
+        let season = block_on(fx.state.end_season()).unwrap();
+        let paid: Vec<_> =
+            season.referral_rewards.iter().map(|p| (p.user_id.as_str(), p.amount)).collect();
+        assert_eq!(paid, vec![("user-0", reward), ("user-1", reward / 2)]);
+        assert_eq!(fx.user("user-1").unwrap().balance, before + reward / 2);
+        assert_eq!(fx.state.treasury, 0);
+        assert!(block_on(fx.state.get_referral_standings()).is_empty());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn escrow_lists_cards_held_by_open_offers_trades_and_hands() {
+        let mut fx = fixture(2, 1, 1);
+        MockEnv::set_sender("user-0");
+        let two = vec![Card::TWO];
+        let trade = block_on(fx.state.propose_trade("user-1".into(), two.clone(), two.clone()));
+        let trade_id = trade.unwrap().trade_id;
+
+        let escrow = block_on(fx.state.get_my_escrow());
+        let held: Vec<_> = escrow.iter().map(|e| (e.kind, e.cards.clone())).collect();
+        assert_eq!(
+            held,
+            vec![
+                (EscrowKind::Offer, vec![Card::KING]),
+                (EscrowKind::Trade, two),
+                (EscrowKind::Hand, vec![Card::ACE]),
+            ]
+        );
+        assert_eq!(escrow[1].subject_id, trade_id);
+
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.accept_trade(trade_id)).unwrap();
+        assert!(block_on(fx.state.get_my_escrow()).is_empty());
+        MockEnv::set_sender("user-0");
+        assert_eq!(block_on(fx.state.get_my_escrow()).len(), 2);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn full_inventories_refuse_cards_and_idle_ones_decay() {
+        let mut fx = fixture(2, 0, 0);
+        MockEnv::set_sender("user-0");
+        assert!(block_on(fx.state.decay_inventory("user-1".to_string())).is_err());
+        fx.state.params.decay_blocks = 100;
+        // a gift is the giver's activity, not the recipient's
+        MockEnv::set_block_height(50);
+        block_on(fx.state.gift_cards("user-1".into(), vec![Card::JOKER])).unwrap();
+        MockEnv::set_block_height(99);
+        assert!(block_on(fx.state.decay_inventory("user-1".to_string())).is_err());
+        MockEnv::set_block_height(100);
+        let decayed = block_on(fx.state.decay_inventory("user-1".to_string())).unwrap();
+        assert_eq!(decayed, Card::ALL[..5].to_vec());
+        let cards = fx.user("user-1").unwrap().cards;
+        assert_eq!((cards.len(), cards.last()), (52, Some(&Card::JOKER)));
+        // the decay itself restarts the clock
+        assert!(block_on(fx.state.decay_inventory("user-1".to_string())).is_err());
+
+        fx.state.params.max_inventory_cards = 53;
+        assert!(block_on(fx.state.gift_cards("user-1".into(), vec![Card::ACE; 2])).is_err());
+        block_on(fx.state.gift_cards("user-1".into(), vec![Card::ACE])).unwrap();
+        MockEnv::set_sender("user-1");
+        let commitment = "a".repeat(64);
+        let price = fx.state.params.card_price;
+        assert!(block_on(fx.state.buy_cards(price, commitment)).is_err());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn bids_need_room_and_full_buyers_are_refunded_at_settlement() {
+        let mut fx = fixture(2, 0, 1);
+        let offer_id = fx.offer_ids[0].clone();
+        let buyer = fx.user("user-1").unwrap();
+        let cards = buyer.cards.len();
+        fx.state.params.max_inventory_cards = cards as u32;
+        MockEnv::set_sender("user-1");
+        let err = block_on(fx.state.bid(offer_id.clone(), 3 * CHIPS_PER_UNIT)).unwrap_err();
+        assert_eq!(err, format!("user-1 can hold at most {} cards", cards));
+
+        // cards received after bidding fill the inventory, the sale falls through
+        fx.state.params.max_inventory_cards = cards as u32 + 1;
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.gift_cards("user-1".into(), vec![Card::TWO])).unwrap();
+        let kings = |fx: &Fixture| {
+            fx.user("user-0").unwrap().cards.iter().filter(|c| **c == Card::KING).count()
+        };
+        let seller_kings = kings(&fx);
+        block_on(fx.state.resolve(offer_id.clone())).unwrap();
+        let offer = fx.offer(&offer_id).unwrap();
+        assert!(offer.is_resolved && offer.current_bidder_id.is_none());
+        let refunded = fx.user("user-1").unwrap();
+        assert_eq!(refunded.balance, buyer.balance + buyer.locked_balance);
+        assert_eq!((refunded.locked_balance, refunded.cards.len()), (0, cards + 1));
+        assert_eq!(kings(&fx), seller_kings + 1);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn full_buyers_are_refunded_instead_of_paying_off_an_installment_plan() {
+        let mut fx = fixture(2, 0, 0);
+        let terms = InstallmentTerms {
+            count: 2,
+            interval_blocks: 10,
+            forfeit_percent: 50,
+        };
+        MockEnv::set_block_height(0);
+        MockEnv::set_sender("user-0");
+        let price = MIN_INSTALLMENT_PRICE;
+        let list = fx.state.offer(vec![Card::KING], price, 10, Some(terms), None, None);
+        let offer_id = block_on(list).unwrap().offer_id;
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.bid(offer_id.clone(), price + 1)).unwrap();
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.resolve(offer_id.clone())).unwrap();
+        let kings = |fx: &Fixture| {
+            fx.user("user-0").unwrap().cards.iter().filter(|c| **c == Card::KING).count()
+        };
+        let seller_kings = kings(&fx);
+
+        let buyer = fx.user("user-1").unwrap();
+        fx.state.params.max_inventory_cards = buyer.cards.len() as u32;
+        MockEnv::set_sender("user-1");
+        let plan = block_on(fx.state.pay_installment(offer_id.clone())).unwrap();
+        assert!(plan.is_settled && plan.installments_paid == 1);
+        let refunded = fx.user("user-1").unwrap();
+        assert_eq!(refunded.balance, buyer.balance + plan.paid);
+        assert_eq!(refunded.cards.len(), buyer.cards.len());
+        assert_eq!(kings(&fx), seller_kings + 1);
+        assert!(block_on(fx.state.pay_installment(offer_id)).is_err());
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn hands_can_limit_how_often_each_player_stakes() {
+        let mut fx = fixture(2, 1, 0);
+        MockEnv::set_sender("user-0");
+        let mut options = fx.state.hands.get(&fx.hand_ids[0]).unwrap().options();
+        options.max_stakes_per_player = Some(0);
+        let ace = vec![Card::ACE];
+        assert!(block_on(fx.state.start_hand(Card::ACE, 1, ace.clone(), options.clone())).is_err());
+        options.alternate_stakers = true;
+        options.max_stakes_per_player = Some(2);
+        let hand = block_on(fx.state.start_hand(Card::ACE, 1, ace.clone(), options)).unwrap();
+        let stake = |fx: &mut Fixture, user: &str| {
+            MockEnv::set_sender(user);
+            block_on(fx.state.stake(hand.hand_id.clone(), vec![Card::ACE], 1))
+        };
+
+        assert!(stake(&mut fx, "user-0").is_err());
+        stake(&mut fx, "user-1").unwrap();
+        stake(&mut fx, "user-0").unwrap();
+        stake(&mut fx, "user-1").unwrap();
+        let err = stake(&mut fx, "user-0").unwrap_err();
+        assert_eq!(err, "each player may stake at most 2 times on this hand");
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn card_supply_and_sale_prices_are_reported_per_rank() {
+        let mut fx = fixture(2, 0, 1);
+        let supply = block_on(fx.state.get_card_supply());
+        assert_eq!(supply.len(), Card::ALL.len());
+        assert!(supply.iter().all(|s| s.supply == 8));
+        assert_eq!(block_on(fx.state.get_card_price_stats(Card::KING)).sales, 0);
+
+        MockEnv::set_sender("user-0");
+        MockEnv::set_block_height(3);
+        block_on(fx.state.resolve(fx.offer_ids[0].clone())).unwrap();
+        let king = block_on(fx.state.get_card_price_stats(Card::KING));
+        let sold_for = 2 * CHIPS_PER_UNIT;
+        assert_eq!((king.sales, king.median, king.average), (1, sold_for, sold_for));
+        assert_eq!(king.last.unwrap().sold_at_block, 3);
+
+        let lot = vec![Card::TWO, Card::TWO, Card::THREE];
+        let bundle_id = block_on(fx.state.offer_bundle(lot, 5, 10)).unwrap().offer_id;
+        MockEnv::set_sender("user-1");
+        let picked = vec![Card::TWO, Card::THREE];
+        block_on(fx.state.buy_from_bundle(bundle_id.clone(), picked)).unwrap();
+        block_on(fx.state.buy_from_bundle(bundle_id, vec![Card::TWO])).unwrap();
+        let two = block_on(fx.state.get_card_price_stats(Card::TWO));
+        assert_eq!((two.sales, two.min, two.max), (2, 5, 5));
+        assert_eq!(block_on(fx.state.get_card_price_stats(Card::THREE)).sales, 1);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn offers_requiring_confirmation_sell_only_once_the_bidder_confirms() {
+        let mut fx = fixture(3, 0, 2);
+        let (sold, lapsed) = (fx.offer_ids[0].clone(), fx.offer_ids[1].clone());
+        MockEnv::set_sender("user-0");
+        assert!(block_on(fx.state.require_bidder_confirmation(sold.clone())).is_err());
+        for id in [&sold, &lapsed] {
+            let mut offer = fx.state.offers.get(id).unwrap();
+            offer.requires_confirmation = true;
+            fx.state.offers.insert(id.clone(), offer);
+        }
+
+        let kings = |fx: &Fixture, id: &str| {
+            token::balance_of(&fx.user(id).unwrap().cards, Card::KING)
+        };
+        let buyer_kings = kings(&fx, "user-1");
+        block_on(fx.state.resolve(sold.clone())).unwrap();
+        assert!(fx.state.offers.get(&sold).unwrap().awaiting_confirmation());
+        assert_eq!(kings(&fx, "user-1"), buyer_kings);
+        assert!(block_on(fx.state.resolve(sold.clone())).is_err());
+
+        // nobody can buy the offer out from under the confirming bidder
+        let offer = fx.state.offers.get(&sold).unwrap();
+        let buyout_price = Some(offer.current_bid.unwrap() * 10);
+        fx.state.offers.insert(sold.clone(), Offer { buyout_price, ..offer });
+        MockEnv::set_sender("user-2");
+        let err = block_on(fx.state.buy_now(sold.clone())).unwrap_err();
+        assert_eq!(err, "offer is waiting for the bidder to confirm the purchase");
+        MockEnv::set_sender("user-1");
+        assert!(block_on(fx.state.withdraw_bid(sold.clone())).is_err());
+        block_on(fx.state.confirm_purchase(sold.clone())).unwrap();
+        assert!(fx.state.offers.get(&sold).unwrap().is_resolved);
+        assert_eq!(kings(&fx, "user-1"), buyer_kings + 1);
+
+        block_on(fx.state.resolve(lapsed.clone())).unwrap();
+        let bidder = fx.user("user-2").unwrap();
+        MockEnv::set_block_height(CONFIRM_PURCHASE_BLOCKS);
+        MockEnv::set_sender("user-2");
+        assert!(block_on(fx.state.confirm_purchase(lapsed.clone())).is_err());
+        MockEnv::set_sender("user-1");
+        let seller_kings = kings(&fx, "user-1");
+        block_on(fx.state.resolve(lapsed.clone())).unwrap();
+        let refunded = fx.user("user-2").unwrap();
+        assert_eq!(refunded.balance, bidder.balance + 2 * CHIPS_PER_UNIT);
+        assert_eq!(refunded.locked_balance, 0);
+        assert_eq!(kings(&fx, "user-1"), seller_kings + 1);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn seller_reputation_scales_the_listing_fee_and_the_escrow() {
+        let mut fx = fixture(3, 0, 0);
+        let fee = Offer::listing_fee_for(&[Card::KING]);
+        let set_reputation = |fx: &mut Fixture, reputation| {
+            let user = User { reputation, ..fx.user("user-0").unwrap() };
+            fx.state.users.insert("user-0".to_string(), user);
+        };
+        let list = |fx: &mut Fixture, amount, installments| {
+            block_on(fx.state.offer(vec![Card::KING], amount, 10, installments, None, None))
+        };
+        MockEnv::set_block_height(0);
+        MockEnv::set_sender("user-0");
+        let tiers = [
+            (0, fee),
+            (escalate_rules::TRUSTED_SELLER_REPUTATION, fee / 2),
+            (escalate_rules::VETERAN_SELLER_REPUTATION, 0),
+        ];
+        for (reputation, charged) in tiers {
+            set_reputation(&mut fx, reputation);
+            let offer = list(&mut fx, CHIPS_PER_UNIT, None).unwrap();
+            assert_eq!(offer.listing_fee, charged);
+            assert!(!offer.requires_confirmation);
+        }
+
+        // below zero the buyer pays in full and gets twice as long to confirm
+        set_reputation(&mut fx, -1);
+        let terms = InstallmentTerms {
+            count: 2,
+            interval_blocks: 10,
+            forfeit_percent: 50,
+        };
+        let err = list(&mut fx, MIN_INSTALLMENT_PRICE, Some(terms)).unwrap_err();
+        assert_eq!(err, "sellers with negative reputation must be paid in full");
+        let offer = list(&mut fx, CHIPS_PER_UNIT, None).unwrap();
+        assert_eq!(offer.listing_fee, fee);
+        assert!(offer.requires_confirmation);
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.bid(offer.offer_id.clone(), 2 * CHIPS_PER_UNIT)).unwrap();
+        MockEnv::set_block_height(5);
+        MockEnv::set_sender("user-0");
+        block_on(fx.state.resolve(offer.offer_id.clone())).unwrap();
+        let confirm_by = fx.offer(&offer.offer_id).unwrap().confirm_by;
+        assert_eq!(confirm_by, Some(5 + 2 * CONFIRM_PURCHASE_BLOCKS));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn side_bets_lock_both_stakes_until_the_hand_is_checked() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        let start = fx.state.params.starting_balance;
+        MockEnv::set_sender("user-1");
+        let propose = fx.state.propose_side_bet("user-2".into(), hand_id.clone(), true, 10);
+        let bet = block_on(propose).unwrap();
+        assert_eq!(fx.user("user-1").unwrap().locked_balance, 10);
+
+        MockEnv::set_sender("user-2");
+        block_on(fx.state.accept_side_bet(bet.bet_id)).unwrap();
+        let opponent = fx.user("user-2").unwrap();
+        assert_eq!((opponent.balance, opponent.locked_balance), (start - 10, 10));
+
+        MockEnv::set_sender("user-1");
+        block_on(fx.state.check(hand_id)).unwrap();
+        assert_eq!(fx.user("user-1").unwrap().locked_balance, 0);
+        let winner = fx.user("user-2").unwrap();
+        assert_eq!((winner.balance, winner.locked_balance), (start + 10, 0));
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn achievement_rewards_wait_for_a_treasury_that_covers_them() {
+        let mut fx = fixture(1, 0, 0);
+        let unlocked = UnlockedAchievement {
+            kind: AchievementKind::FirstBluffCaught,
+            unlocked_at_block: 0,
+            rewards_claimed: false,
+        };
+        fx.state.user_achievements.insert("user-0".to_string(), vec![unlocked]);
+        fx.state.treasury = CHIPS_PER_UNIT - 1;
+        MockEnv::set_sender("user-0");
+        let before = fx.user("user-0").unwrap().balance;
+        assert!(block_on(fx.state.claim_achievement_rewards()).is_err());
+        let badges = block_on(fx.state.get_user_badges("user-0".to_string()));
+        assert!(!badges[0].rewards_claimed);
+
+        fx.state.treasury = CHIPS_PER_UNIT;
+        let user = block_on(fx.state.claim_achievement_rewards()).unwrap();
+        assert_eq!(user.balance, before + CHIPS_PER_UNIT);
+        assert_eq!(fx.state.treasury, 0);
+        MockEnv::reset();
+    }
+
+    #[test]
+    fn preview_after_a_check_reports_the_winner_in_the_requested_locale() {
+        let mut fx = fixture(3, 1, 0);
+        let hand_id = fx.hand_ids[0].clone();
+        fx.state.message_templates.insert(
+            MessageCode::AlreadyResolvedBy.catalog_key("pt"),
+            "mão já verificada por {0}, blefe: {1}".to_string(),
+        );
+
+        MockEnv::set_sender("user-1");
+        let outcome = block_on(fx.state.check(hand_id.clone())).unwrap();
+
+        MockEnv::set_sender("user-2");
+        let err = block_on(fx.state.preview_check(hand_id, Some("pt-BR".to_string())))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            format!("mão já verificada por user-1, blefe: {}", outcome.bluff_detected)
+        );
+        MockEnv::reset();
+    }
+}
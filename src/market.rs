@@ -0,0 +1,34 @@
+use crate::elements::{Card, CardPriceStats, SalePrice};
+
+// sales remembered per rank, older ones make way for new ones
+pub const MAX_PRICE_SAMPLES: usize = 50;
+
+// what each card of a lot sold for, when the lot holds a single rank; a
+// mixed lot sold at one price says nothing about any one of its ranks
+pub fn lot_price_per_card(cards: &[Card], price: u64) -> Option<u64> {
+    let first = *cards.first()?;
+    cards.iter().all(|c| *c == first).then(|| price / cards.len() as u64)
+}
+
+pub fn record_sale(samples: &mut Vec<SalePrice>, sample: SalePrice) {
+    samples.push(sample);
+    if samples.len() > MAX_PRICE_SAMPLES {
+        samples.remove(0);
+    }
+}
+
+// all zero while the rank has no recorded sales
+pub fn price_stats(card: Card, samples: &[SalePrice]) -> CardPriceStats {
+    let mut prices: Vec<u64> = samples.iter().map(|s| s.price_per_card).collect();
+    prices.sort_unstable();
+    let sales = prices.len() as u64;
+    CardPriceStats {
+        card,
+        sales,
+        min: prices.first().copied().unwrap_or_default(),
+        max: prices.last().copied().unwrap_or_default(),
+        average: prices.iter().sum::<u64>().checked_div(sales).unwrap_or_default(),
+        median: prices.get(prices.len().saturating_sub(1) / 2).copied().unwrap_or_default(),
+        last: samples.last().cloned(),
+    }
+}
@@ -0,0 +1,54 @@
+use crate::elements::{Card, HandOptions, QueueEntry};
+
+impl QueueEntry {
+    pub fn new(user_id: String, stake_size: u32, joined_at_block: u64) -> Self {
+        QueueEntry {
+            user_id,
+            stake_size,
+            joined_at_block,
+        }
+    }
+
+    // a hand only this player and the opponent they were matched with can play
+    pub fn hand_options(&self, opponent_id: &str, duration_blocks: u64) -> HandOptions {
+        HandOptions {
+            region_tag: None,
+            duration_blocks,
+            min_raise: 0,
+            max_stakes: u32::MAX,
+            alternate_stakers: false,
+            max_stakes_per_player: None,
+            newcomers_only: false,
+            participants_only: false,
+            turn_based: false,
+            allowed_stakers: vec![opponent_id.to_string()],
+            min_reputation: None,
+            tournament_id: None,
+        }
+    }
+}
+
+// the opening stake drawn for a matched player: `size` cards led by the
+// rank they hold most of (the earlier rank on ties), filled up with their
+// other cards in inventory order and claiming every copy of the lead rank.
+// Jokers are never drawn, `None` when there are too few other cards
+pub fn opening_stake(inventory: &[Card], size: u32) -> Option<(Card, u32, Vec<Card>)> {
+    let size = size as usize;
+    let natural: Vec<Card> = inventory.iter().copied().filter(|c| *c != Card::JOKER).collect();
+    if size == 0 || natural.len() < size {
+        return None;
+    }
+
+    let held = |rank: Card| natural.iter().filter(|c| **c == rank).count();
+    let lead = Card::ALL
+        .iter()
+        .copied()
+        .filter(|rank| *rank != Card::JOKER)
+        .rev()
+        .max_by_key(|rank| held(*rank))?;
+    let copies = held(lead).min(size);
+
+    let mut cards = vec![lead; copies];
+    cards.extend(natural.iter().copied().filter(|c| *c != lead).take(size - copies));
+    Some((lead, copies as u32, cards))
+}
@@ -0,0 +1,79 @@
+use crate::elements::MessageCode;
+
+// locale used when none is requested or no template exists for it
+pub const DEFAULT_LOCALE: &str = "en";
+
+// a validation error that can be rendered in any locale the catalog knows
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub code: MessageCode,
+    pub args: Vec<String>,
+}
+
+impl MessageCode {
+    pub fn default_template(&self) -> &'static str {
+        match self {
+            MessageCode::UserNotRegistered => "user not registered",
+            MessageCode::HandNotFound => "hand not found for check",
+            MessageCode::HandAlreadyResolved => "hand already resolved",
+            MessageCode::HandExpired => "hand expired, claim it instead of checking",
+            MessageCode::NoStakesToCheck => "no stakes to check",
+            MessageCode::NewcomersOnly => "only newcomers can play newcomer bracket hands",
+            MessageCode::NewcomerStakeCap => "newcomer bracket stakes are capped at {0} cards",
+            MessageCode::OwnStakeCheck => "the last staker cannot check their own stake",
+            MessageCode::ParticipantsOnly => "only users who staked on this hand can check it",
+            MessageCode::AlreadyResolvedBy => "hand already checked by {0}, bluff detected: {1}",
+            MessageCode::ReputationTooLow => "this hand requires a reputation of at least {0}",
+            MessageCode::MaintenanceWindow => "contract is under maintenance until block {0}",
+            MessageCode::TournamentPlayersOnly => "only tournament {0} players can play this hand",
+        }
+    }
+
+    // key of this code's template for `locale` in the catalog
+    pub fn catalog_key(&self, locale: &str) -> String {
+        format!("{:?}/{}", self, locale)
+    }
+}
+
+impl Message {
+    pub fn new(code: MessageCode) -> Self {
+        Message {
+            code,
+            args: Vec::new(),
+        }
+    }
+
+    pub fn with_arg(mut self, arg: impl ToString) -> Self {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn render(&self, template: &str) -> String {
+        self.args
+            .iter()
+            .enumerate()
+            .fold(template.to_string(), |text, (i, arg)| {
+                text.replace(&format!("{{{}}}", i), arg)
+            })
+    }
+}
+
+// entrypoints without a locale report errors in the default language
+impl From<Message> for String {
+    fn from(message: Message) -> Self {
+        message.render(message.code.default_template())
+    }
+}
+
+// `pt-BR` falls back to `pt`, then to the default locale's catalog entry
+// and only after that to the built in templates
+pub fn locale_fallbacks(locale: &str) -> Vec<&str> {
+    let mut locales = vec![locale];
+    if let Some((language, _)) = locale.split_once('-') {
+        locales.push(language);
+    }
+    if !locales.contains(&DEFAULT_LOCALE) {
+        locales.push(DEFAULT_LOCALE);
+    }
+    locales
+}
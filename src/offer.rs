@@ -1,7 +1,42 @@
-use crate::elements::{Card, Offer};
+use crate::elements::{Card, CardValue, DutchTerms, InstallmentTerms, NegotiationState, Offer};
+
+// how a new listing is auctioned, checked by the entrypoint creating it
+pub struct OfferTerms {
+    pub amount: u64,
+    pub expires_at: u64,
+    pub installments: Option<InstallmentTerms>,
+    pub reveal_deadline: Option<u64>,
+    pub buyout_price: Option<u64>,
+    pub card_prices: Option<Vec<u64>>,
+    pub dutch: Option<DutchTerms>,
+    pub price_per_card: Option<u64>,
+}
+
+impl OfferTerms {
+    pub fn auction(amount: u64, expires_at: u64) -> Self {
+        OfferTerms {
+            amount,
+            expires_at,
+            installments: None,
+            reveal_deadline: None,
+            buyout_price: None,
+            card_prices: None,
+            dutch: None,
+            price_per_card: None,
+        }
+    }
+}
 
 impl Offer {
-    pub fn new(offer_id: String, creator_id: String, cards: Vec<Card>, amount: f64) -> Self {
+    pub fn new(
+        offer_id: String,
+        creator_id: String,
+        cards: Vec<Card>,
+        amount: u64,
+        listing_fee: u64,
+        expires_at: u64,
+        installments: Option<InstallmentTerms>,
+    ) -> Self {
         Offer {
             offer_id,
             creator_id,
@@ -10,6 +45,120 @@ impl Offer {
             current_bid: None,
             current_bidder_id: None,
             is_resolved: false,
+            listing_fee,
+            expires_at,
+            installments,
+            reveal_deadline: None,
+            pack_id: None,
+            buyout_price: None,
+            boost_until: 0,
+            boost_fees_paid: 0,
+            card_prices: None,
+            dutch: None,
+            min_increment: 1,
+            reserve_price: None,
+            negotiation_state: NegotiationState::None,
+            counter_price: None,
+            price_per_card: None,
+            requires_confirmation: false,
+            confirm_by: None,
+        }
+    }
+
+    // resolved by the seller, waiting on the bidder's confirmation
+    pub fn awaiting_confirmation(&self) -> bool {
+        !self.is_resolved && self.confirm_by.is_some()
+    }
+
+    // a new or withdrawn bid voids any counter made to the previous one
+    pub fn clear_counter(&mut self) {
+        self.negotiation_state = NegotiationState::None;
+        self.counter_price = None;
+    }
+
+    // once the bidder took a counter, neither side can walk away from it
+    pub fn counter_accepted(&self) -> bool {
+        self.negotiation_state == NegotiationState::Accepted
+    }
+
+    // the lowest amount `bid` accepts next
+    pub fn min_next_bid(&self) -> u64 {
+        match self.current_bid {
+            Some(bid) => bid.saturating_add(self.min_increment),
+            None => self.initial_price.saturating_add(1),
+        }
+    }
+
+    pub fn meets_reserve(&self, amount: u64) -> bool {
+        self.reserve_price.is_none_or(|reserve| amount >= reserve)
+    }
+
+    pub fn is_boosted(&self, height: u64) -> bool {
+        !self.is_resolved && !self.is_expired(height) && height < self.boost_until
+    }
+
+    pub fn listing_fee_for(cards: &[Card]) -> u64 {
+        escalate_rules::listing_fee(cards)
+    }
+
+    pub fn listing_fee_refund(&self) -> u64 {
+        escalate_rules::listing_fee_refund(self.listing_fee)
+    }
+
+    // what a bid of `amount` locks up front: all of it, or only the
+    // first installment when the seller allows paying it off
+    pub fn bid_escrow(&self, amount: u64) -> u64 {
+        self.installments
+            .as_ref()
+            .map_or(amount, |terms| terms.installment_for(amount))
+    }
+
+    // the price a dutch auction sells at in block `height`, falling from
+    // the initial price by the decay every block until it hits the floor
+    pub fn dutch_price(&self, height: u64) -> Option<u64> {
+        self.dutch.as_ref().map(|terms| {
+            let blocks = height.saturating_sub(terms.started_at_block);
+            let decay = blocks.saturating_mul(terms.decay_per_block);
+            self.initial_price.saturating_sub(decay).max(terms.floor_price)
+        })
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.reveal_deadline.is_some()
+    }
+
+    // only open auctions paid in full can wait on the winner's confirmation
+    pub fn can_require_confirmation(&self) -> bool {
+        !self.is_sealed()
+            && self.dutch.is_none()
+            && self.price_per_card.is_none()
+            && self.installments.is_none()
+    }
+
+    pub fn is_expired(&self, height: u64) -> bool {
+        height >= self.expires_at
+    }
+
+    // `price` split across the cards in proportion to the seller's card
+    // prices, or evenly without them; the first cards take the chips
+    // lost to rounding
+    pub fn card_values(&self, price: u64) -> Vec<CardValue> {
+        let weights = self.card_prices.clone().unwrap_or_else(|| vec![1; self.cards.len()]);
+        let total: u64 = weights.iter().sum();
+        if total == 0 {
+            return Vec::new();
         }
+        let shares: Vec<u64> =
+            weights.iter().map(|w| (*w as u128 * price as u128 / total as u128) as u64).collect();
+        let leftover = price - shares.iter().sum::<u64>();
+        self.cards
+            .iter()
+            .zip(shares)
+            .enumerate()
+            .map(|(i, (card, share))| CardValue {
+                card: *card,
+                value: share + ((i as u64) < leftover) as u64,
+            })
+            .collect()
     }
-}
\ No newline at end of file
+}
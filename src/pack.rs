@@ -0,0 +1,36 @@
+use escalate_rules::CHIPS_PER_UNIT;
+
+use crate::elements::{Pack, PackKind};
+
+impl PackKind {
+    pub fn card_count(&self) -> u32 {
+        match self {
+            PackKind::Booster => 5,
+            PackKind::Box => 20,
+        }
+    }
+
+    // boxes come with a bulk discount over buying the cards one by one
+    pub fn price(&self) -> u64 {
+        match self {
+            PackKind::Booster => 5 * CHIPS_PER_UNIT,
+            PackKind::Box => 18 * CHIPS_PER_UNIT,
+        }
+    }
+}
+
+impl Pack {
+    pub fn new(pack_id: String, kind: PackKind, owner_id: String) -> Self {
+        Pack {
+            pack_id,
+            kind,
+            owner_id,
+            is_listed: false,
+            is_opened: false,
+        }
+    }
+
+    pub fn listing_fee(&self) -> u64 {
+        escalate_rules::listing_fee_on(self.kind.price())
+    }
+}
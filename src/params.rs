@@ -0,0 +1,123 @@
+use escalate_rules::{BLUFF_REWARD, CHIPS_PER_UNIT, EQUIVALENT_REWARD};
+
+use crate::elements::{Card, DropWeights, GameParams, MaintenanceWindow, Rarity, User};
+use crate::token;
+
+const MAX_STARTER_CARDS: usize = 52;
+const MAX_DAILY_CLAIM_CARDS: u32 = 10;
+const MAX_RAKE_PERCENT: u64 = 20;
+const MAX_REFERRAL_PERCENT: u64 = 50;
+const MAX_DECAY_PERCENT: u64 = 100;
+
+impl Default for GameParams {
+    fn default() -> Self {
+        GameParams {
+            starting_balance: 100 * CHIPS_PER_UNIT,
+            balance_floor: 0,
+            card_price: CHIPS_PER_UNIT,
+            bluff_reward_percent: BLUFF_REWARD * 100 / EQUIVALENT_REWARD,
+            max_stake_cards: 52,
+            max_hand_loss_percent: 50,
+            checker_reward_percent: Card::ALL
+                .iter()
+                .map(|c| 100 + 5 * (c.strength() - Card::TWO.strength()) as u64)
+                .collect(),
+            drop_weights: DropWeights {
+                common: 100,
+                uncommon: 60,
+                rare: 25,
+            },
+            starter_cards: Vec::new(),
+            daily_claim_cards: 2,
+            daily_claim_blocks: 14_400,
+            hand_rake_percent: 2,
+            sale_rake_percent: 2,
+            referral_percent: 10,
+            referral_blocks: 200_000,
+            max_inventory_cards: 1_000,
+            decay_blocks: 0,
+            decay_percent: 10,
+        }
+    }
+}
+
+impl GameParams {
+    // chips a single bluffed card earns its staker
+    pub fn bluff_reward(&self) -> u64 {
+        EQUIVALENT_REWARD * self.bluff_reward_percent / 100
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.card_price == 0 {
+            return Err("card price must be positive".to_string());
+        }
+        if self.balance_floor > self.starting_balance {
+            return Err("balance floor cannot exceed the starting balance".to_string());
+        }
+        if self.max_stake_cards == 0 {
+            return Err("max stake size must be at least one card".to_string());
+        }
+        if self.checker_reward_percent.len() != Card::ALL.len() {
+            return Err(format!(
+                "checker rewards must list a percent for each of the {} ranks",
+                Card::ALL.len()
+            ));
+        }
+        let DropWeights { common, uncommon, rare } = self.drop_weights;
+        if common == 0 || uncommon == 0 || rare == 0 {
+            return Err("every rarity needs a positive drop weight".to_string());
+        }
+        if self.max_hand_loss_percent == 0 || self.max_hand_loss_percent > 100 {
+            return Err("max hand loss must be between 1 and 100 percent".to_string());
+        }
+        if self.starter_cards.len() > MAX_STARTER_CARDS {
+            return Err(format!("starter bundle is capped at {} cards", MAX_STARTER_CARDS));
+        }
+        if self.daily_claim_cards > MAX_DAILY_CLAIM_CARDS {
+            return Err(format!("daily claims are capped at {} cards", MAX_DAILY_CLAIM_CARDS));
+        }
+        if self.daily_claim_cards > 0 && self.daily_claim_blocks == 0 {
+            return Err("daily claims need a cooldown of at least one block".to_string());
+        }
+        if self.hand_rake_percent > MAX_RAKE_PERCENT || self.sale_rake_percent > MAX_RAKE_PERCENT {
+            return Err(format!("rakes are capped at {} percent", MAX_RAKE_PERCENT));
+        }
+        if self.referral_percent > MAX_REFERRAL_PERCENT {
+            return Err(format!("referrals are capped at {} percent", MAX_REFERRAL_PERCENT));
+        }
+        let decay_in_range = (1..=MAX_DECAY_PERCENT).contains(&self.decay_percent);
+        if self.decay_blocks > 0 && !decay_in_range {
+            return Err(format!("decay must take between 1 and {} percent", MAX_DECAY_PERCENT));
+        }
+        Ok(())
+    }
+
+    // the drop weight of every rank, in Card order
+    pub fn rank_drop_weights(&self) -> Vec<u64> {
+        Card::ALL
+            .iter()
+            .map(|c| match c.rarity() {
+                Rarity::Common => self.drop_weights.common,
+                Rarity::Uncommon => self.drop_weights.uncommon,
+                Rarity::Rare => self.drop_weights.rare,
+            })
+            .collect()
+    }
+
+    // stronger claims pay more for catching them bluffing
+    pub fn checker_reward_percent(&self, claimed: Card) -> u64 {
+        self.checker_reward_percent[token::token_id(claimed)]
+    }
+
+    // whether `user` may put `loss` chips at risk on one hand
+    pub fn allows_loss(&self, user: &User, loss: u64) -> bool {
+        user.high_roller
+            || loss as u128 * 100 <= user.balance as u128 * self.max_hand_loss_percent as u128
+    }
+}
+
+impl MaintenanceWindow {
+    pub fn is_active(&self, height: u64) -> bool {
+        self.start_block <= height && height < self.end_block
+    }
+}
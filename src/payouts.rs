@@ -0,0 +1,670 @@
+//! Pure payout math behind checks, side bets and offer settlement.
+//! Nothing in here touches the runtime; the invariant suite at the bottom
+//! drives it through the contract entrypoints under the mock environment.
+//! The per-card math itself comes from the `escalate-rules` crate.
+
+use escalate_rules::stake_reward_with;
+
+use crate::elements::{
+    Card, GameParams, Hand, InstallmentPlan, Offer, PayoutLine, PayoutReason, SideBet,
+    SpectatorPool, Stake, TeamHand,
+};
+use crate::token::token_id;
+
+// a balance movement for a single user, positive amounts are credits
+pub type Payout = (String, i64);
+
+impl PayoutLine {
+    pub fn new((user_id, amount): Payout, reason: PayoutReason) -> Self {
+        PayoutLine {
+            user_id,
+            amount,
+            reason,
+        }
+    }
+}
+
+impl PayoutReason {
+    // rewards and subsidies are paid by the treasury, everything else
+    // comes out of an escrow
+    pub fn is_treasury_funded(self) -> bool {
+        matches!(
+            self,
+            PayoutReason::CheckerReward
+                | PayoutReason::StakeReward
+                | PayoutReason::NewcomerSubsidy
+                | PayoutReason::ClaimDiversityBonus
+        )
+    }
+}
+
+impl From<PayoutLine> for Payout {
+    fn from(line: PayoutLine) -> Self {
+        (line.user_id, line.amount)
+    }
+}
+
+fn labelled(payouts: Vec<Payout>, reason: PayoutReason) -> Vec<PayoutLine> {
+    payouts.into_iter().map(|p| PayoutLine::new(p, reason)).collect()
+}
+
+// per-card rewards for every stake up to (and optionally including) the last one,
+// bluffed cards earn `bluff_reward` each
+pub fn staker_payouts(
+    stakes: &[Stake],
+    include_last: bool,
+    claimed: Card,
+    bluff_reward: u64,
+) -> Vec<Payout> {
+    let upto = if include_last {
+        stakes.len()
+    } else {
+        stakes.len().saturating_sub(1)
+    };
+
+    stakes
+        .iter()
+        .take(upto)
+        .map(|stake| {
+            let reward = stake_reward_with(&stake.cards, claimed, bluff_reward);
+            (stake.user_id.clone(), reward as i64)
+        })
+        .collect()
+}
+
+pub fn checker_delta(hand: &Hand, bluff_detected: bool) -> i64 {
+    let last_stake = hand.last_stake().map_or(&[][..], |s| &s.cards[..]);
+    escalate_rules::checker_delta(last_stake, bluff_detected)
+}
+
+// the checker delta with a caught bluff scaled by the claimed rank's
+// multiplier; penalties for wrong checks are left as they are
+pub fn weighted_checker_delta(hand: &Hand, bluff_detected: bool, params: &GameParams) -> i64 {
+    let delta = checker_delta(hand, bluff_detected);
+    if delta <= 0 {
+        return delta;
+    }
+    let percent = params.checker_reward_percent(hand.claimed_card);
+    (delta as u128 * percent as u128 / 100) as i64
+}
+
+fn checker_reason(delta: i64) -> PayoutReason {
+    if delta >= 0 {
+        PayoutReason::CheckerReward
+    } else {
+        PayoutReason::CheckerPenalty
+    }
+}
+
+// accepted side bets pay the winner both escrows, unaccepted ones
+// are refunded to the proposer
+pub fn side_bet_lines(bets: &[SideBet], bluff_detected: bool) -> Vec<PayoutLine> {
+    bets.iter()
+        .filter(|b| !b.is_settled)
+        .map(|b| {
+            if b.is_accepted {
+                let payout = (b.winner(bluff_detected).to_string(), b.amount as i64 * 2);
+                PayoutLine::new(payout, PayoutReason::SideBetWon)
+            } else {
+                let payout = (b.proposer_id.clone(), b.amount as i64);
+                PayoutLine::new(payout, PayoutReason::SideBetRefund)
+            }
+        })
+        .collect()
+}
+
+pub fn side_bet_payouts(bets: &[SideBet], bluff_detected: bool) -> Vec<Payout> {
+    side_bet_lines(bets, bluff_detected).into_iter().map(Payout::from).collect()
+}
+
+// both sides of every side bet get their escrow back, used when
+// a hand ends without being checked
+pub fn side_bet_refunds(bets: &[SideBet]) -> Vec<Payout> {
+    let mut refunds = Vec::new();
+    for bet in bets.iter().filter(|b| !b.is_settled) {
+        refunds.push((bet.proposer_id.clone(), bet.amount as i64));
+        if bet.is_accepted {
+            refunds.push((bet.opponent_id.clone(), bet.amount as i64));
+        }
+    }
+    refunds
+}
+
+// winners split the whole pool in proportion to their wagers, the chips
+// lost to rounding going one each to the earliest winners. When nobody
+// predicted the outcome every wager is refunded
+pub fn spectator_pool_lines(pool: &SpectatorPool, bluff_detected: bool) -> Vec<PayoutLine> {
+    if pool.is_settled {
+        return Vec::new();
+    }
+    let total = pool.total();
+    let winners: Vec<_> =
+        pool.wagers.iter().filter(|w| w.predicts_bluff == bluff_detected).collect();
+    let winning_total: u64 = winners.iter().map(|w| w.amount).sum();
+    if winning_total == 0 {
+        return labelled(spectator_pool_refunds(pool), PayoutReason::PoolRefund);
+    }
+
+    let shares: Vec<u64> = winners
+        .iter()
+        .map(|w| (w.amount as u128 * total as u128 / winning_total as u128) as u64)
+        .collect();
+    let leftover = total - shares.iter().sum::<u64>();
+    let payouts = winners
+        .iter()
+        .zip(shares)
+        .enumerate()
+        .map(|(i, (w, share))| {
+            let extra = ((i as u64) < leftover) as u64;
+            (w.user_id.clone(), (share + extra) as i64)
+        })
+        .collect();
+    labelled(payouts, PayoutReason::PoolWon)
+}
+
+pub fn spectator_pool_refunds(pool: &SpectatorPool) -> Vec<Payout> {
+    if pool.is_settled {
+        return Vec::new();
+    }
+    pool.wagers.iter().map(|w| (w.user_id.clone(), w.amount as i64)).collect()
+}
+
+// every balance movement caused by `checker_id` checking the hand,
+// shared by `check` and `preview_check` so the preview can never drift
+pub fn check_payouts(
+    hand: &Hand,
+    bets: &[SideBet],
+    checker_id: &str,
+    bluff_detected: bool,
+    params: &GameParams,
+) -> Vec<PayoutLine> {
+    let delta = weighted_checker_delta(hand, bluff_detected, params);
+    let mut payouts = vec![PayoutLine::new((checker_id.to_string(), delta), checker_reason(delta))];
+    let bluff_reward = params.bluff_reward();
+    payouts.extend(labelled(
+        staker_payouts(&hand.stakes, !bluff_detected, hand.claimed_card, bluff_reward),
+        PayoutReason::StakeReward,
+    ));
+    payouts.extend(side_bet_lines(bets, bluff_detected));
+    payouts
+}
+
+// caps the treasury funded credits to `budget` in payout order, so a
+// check can never pay out more than the treasury holds
+pub fn fund_from_treasury(lines: Vec<PayoutLine>, mut budget: u64) -> Vec<PayoutLine> {
+    lines
+        .into_iter()
+        .map(|mut line| {
+            if line.reason.is_treasury_funded() && line.amount > 0 {
+                let paid = line.amount.unsigned_abs().min(budget);
+                budget -= paid;
+                line.amount = paid as i64;
+            }
+            line
+        })
+        .collect()
+}
+
+// the treasury's cut of a checked hand: `percent` of its pot, charged to
+// the checker and stakers who won in proportion to their winnings and
+// never more than any of them won
+pub fn rake_lines(lines: &[PayoutLine], pot: u64, percent: u64) -> Vec<PayoutLine> {
+    let winnings: Vec<&PayoutLine> = lines
+        .iter()
+        .filter(|l| l.amount > 0)
+        .filter(|l| matches!(l.reason, PayoutReason::CheckerReward | PayoutReason::StakeReward))
+        .collect();
+    let won: u64 = winnings.iter().map(|l| l.amount.unsigned_abs()).sum();
+    let rake = (pot * percent / 100).min(won);
+    if rake == 0 {
+        return Vec::new();
+    }
+    winnings
+        .into_iter()
+        .map(|l| {
+            let share = (rake as u128 * l.amount.unsigned_abs() as u128 / won as u128) as i64;
+            PayoutLine::new((l.user_id.clone(), -share), PayoutReason::Rake)
+        })
+        .filter(|l| l.amount < 0)
+        .collect()
+}
+
+// the treasury's cut of a sale at `price`
+pub fn sale_rake(price: u64, percent: u64) -> u64 {
+    price * percent / 100
+}
+
+// total the treasury pays for `lines`
+pub fn treasury_funded(lines: &[PayoutLine]) -> u64 {
+    lines
+        .iter()
+        .filter(|l| l.reason.is_treasury_funded() && l.amount > 0)
+        .map(|l| l.amount.unsigned_abs())
+        .sum()
+}
+
+// `amount` shared as evenly as whole chips allow, the first members
+// take the remainder
+pub fn split_evenly(members: &[String], amount: i64) -> Vec<Payout> {
+    let n = members.len() as i64;
+    if n == 0 {
+        return Vec::new();
+    }
+    let (share, remainder) = (amount / n, amount % n);
+    members
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let extra = if (i as i64) < remainder.abs() { remainder.signum() } else { 0 };
+            (m.clone(), share + extra)
+        })
+        .collect()
+}
+
+// check payouts for a team hand: the checker delta is split across the
+// checking team and every stake reward across the staker's team
+pub fn team_check_payouts(
+    hand: &Hand,
+    team: &TeamHand,
+    bets: &[SideBet],
+    checking_team: usize,
+    bluff_detected: bool,
+    params: &GameParams,
+) -> Vec<PayoutLine> {
+    let delta = weighted_checker_delta(hand, bluff_detected, params);
+    let shares = split_evenly(team.members(checking_team), delta);
+    let mut payouts = labelled(shares, checker_reason(delta));
+    let bluff_reward = params.bluff_reward();
+    let rewards = staker_payouts(&hand.stakes, !bluff_detected, hand.claimed_card, bluff_reward);
+    for (user_id, amount) in rewards {
+        let shares = match team.team_of(&user_id) {
+            Some(side) => split_evenly(team.members(side), amount),
+            None => vec![(user_id, amount)],
+        };
+        payouts.extend(labelled(shares, PayoutReason::StakeReward));
+    }
+    payouts.extend(side_bet_lines(bets, bluff_detected));
+    payouts
+}
+
+// the part of a newcomer checker's loss the treasury covers, never more
+// than the treasury holds
+pub fn newcomer_subsidy(checker_delta: i64, subsidy_percent: u64, treasury: u64) -> u64 {
+    if checker_delta >= 0 {
+        return 0;
+    }
+    (checker_delta.unsigned_abs() * subsidy_percent / 100).min(treasury)
+}
+
+// bonus percent for a new hand claiming `claim`, given how often each rank
+// was claimed recently: `max_percent` for a rank nobody claimed, falling to
+// nothing as the rank reaches its fair share of the claims. Windows with
+// fewer than `min_hands` claims pay no bonus
+pub fn claim_diversity_bonus_percent(
+    recent_claims: &[u64],
+    claim: Card,
+    max_percent: u64,
+    min_hands: u64,
+) -> u64 {
+    let total: u64 = recent_claims.iter().sum();
+    if total < min_hands {
+        return 0;
+    }
+    let fair_share = recent_claims[token_id(claim)] * recent_claims.len() as u64;
+    max_percent * total.saturating_sub(fair_share) / total
+}
+
+// what the treasury adds to a checked hand's pot for its creator, never
+// more than it holds
+pub fn claim_diversity_bonus(hand: &Hand, treasury: u64) -> u64 {
+    (hand.pot * hand.claim_bonus_percent / 100).min(treasury)
+}
+
+// the escrowed part of the highest bid and its owner, returned when it
+// is outbid or withdrawn
+pub fn outbid_refund(offer: &Offer) -> Option<(String, u64)> {
+    match (offer.current_bid, &offer.current_bidder_id) {
+        (Some(amount), Some(bidder_id)) => Some((bidder_id.clone(), offer.bid_escrow(amount))),
+        _ => None,
+    }
+}
+
+// the refundable part of the listing fee, as far as the treasury still covers it
+pub fn listing_refund(offer: &Offer, treasury: u64) -> u64 {
+    offer.listing_fee_refund().min(treasury)
+}
+
+// what the seller receives when the offer sells: the winning bid plus
+// the listing fee `refund` paid by the treasury. Bids paid in
+// installments go to the plan escrow instead of the seller
+pub fn sale_proceeds(offer: &Offer, bid_amount: u64, refund: u64) -> u64 {
+    if offer.installments.is_some() {
+        refund
+    } else {
+        bid_amount + refund
+    }
+}
+
+// a defaulted plan keeps the forfeited share of what was paid for the
+// seller and refunds the rest to the buyer
+pub fn installment_forfeit(plan: &InstallmentPlan) -> Vec<Payout> {
+    let forfeited = plan.paid * plan.terms.forfeit_percent / 100;
+    vec![
+        (plan.seller_id.clone(), forfeited as i64),
+        (plan.buyer_id.clone(), (plan.paid - forfeited) as i64),
+    ]
+}
+
+#[cfg(all(test, feature = "invariant-tests"))]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::Escalate;
+    use crate::elements::{InstallmentTerms, TransferStatus};
+    use crate::testing::{Card, Fixture, HandOptions, MockEnv, block_on, fixture};
+
+    const USERS: usize = 4;
+
+    #[derive(Debug, Clone)]
+    enum Action {
+        Withdraw { user: usize, amount: u64 },
+        SettleTransfer { transfer: usize, succeeded: bool },
+        ReclaimTransfer { transfer: usize },
+        StartHand { user: usize, claim: Card, cards: Vec<Card> },
+        Stake { user: usize, hand: usize, cards: Vec<Card> },
+        ProposeBet { user: usize, opponent: usize, hand: usize, predicts_bluff: bool, amount: u64 },
+        AcceptBet { bet: usize },
+        SpectatorBet { user: usize, hand: usize, predicts_bluff: bool, amount: u64 },
+        Check { user: usize, hand: usize },
+        Offer { user: usize, cards: Vec<Card>, price: u64, installments: bool },
+        Bid { user: usize, offer: usize, amount: u64 },
+        WithdrawBid { offer: usize },
+        Resolve { offer: usize },
+        PayInstallment { offer: usize },
+        ForfeitPlan { offer: usize },
+        AdvanceBlocks { blocks: u64 },
+    }
+
+    // the contract built by `fixture` plus the ids its entrypoints handed
+    // out, so actions can refer to hands, offers, bets and transfers by index
+    struct Run {
+        fx: Fixture,
+        height: u64,
+        bets: Vec<(String, String)>,
+        transfers: Vec<(String, String)>,
+    }
+
+    fn user_id(idx: usize) -> String {
+        format!("user-{}", idx % USERS)
+    }
+
+    fn options() -> HandOptions {
+        HandOptions {
+            region_tag: None,
+            duration_blocks: 20,
+            min_raise: 0,
+            max_stakes: u32::MAX,
+            alternate_stakers: false,
+            max_stakes_per_player: None,
+            newcomers_only: false,
+            participants_only: false,
+            turn_based: false,
+            allowed_stakers: Vec::new(),
+            min_reputation: None,
+            tournament_id: None,
+        }
+    }
+
+    impl Run {
+        fn new() -> Self {
+            let mut fx = fixture(USERS, 0, 0);
+            fx.state.admin = "admin".to_string();
+            MockEnv::set_block_height(0);
+            Run {
+                fx,
+                height: 0,
+                bets: Vec::new(),
+                transfers: Vec::new(),
+            }
+        }
+
+        fn hand(&self, idx: usize) -> Option<String> {
+            self.fx.hand_ids.get(idx).cloned()
+        }
+
+        fn offer(&self, idx: usize) -> Option<String> {
+            self.fx.offer_ids.get(idx).cloned()
+        }
+
+        // every action goes through an entrypoint as its natural caller;
+        // rejected calls are part of the run and must leave value untouched
+        fn apply(&mut self, action: Action) {
+            match action {
+                Action::Withdraw { user, amount } => {
+                    MockEnv::set_sender(&user_id(user));
+                    if let Ok(intent) = block_on(self.fx.state.withdraw(amount)) {
+                        self.transfers.push((intent.intent_id, intent.user_id));
+                    }
+                }
+                Action::SettleTransfer { transfer, succeeded } => {
+                    let Some((intent_id, _)) = self.transfers.get(transfer).cloned() else {
+                        return;
+                    };
+                    MockEnv::set_sender("admin");
+                    let _ = block_on(self.fx.state.settle_transfer(intent_id, succeeded));
+                }
+                Action::ReclaimTransfer { transfer } => {
+                    let Some((intent_id, owner)) = self.transfers.get(transfer).cloned() else {
+                        return;
+                    };
+                    MockEnv::set_sender(&owner);
+                    let _ = block_on(self.fx.state.reclaim_failed_transfer(intent_id));
+                }
+                Action::StartHand { user, claim, cards } => {
+                    MockEnv::set_sender(&user_id(user));
+                    let count = cards.len() as u32;
+                    let started = self.fx.state.start_hand(claim, count, cards, options());
+                    if let Ok(hand) = block_on(started) {
+                        self.fx.hand_ids.push(hand.hand_id);
+                    }
+                }
+                Action::Stake { user, hand, cards } => {
+                    let Some(hand_id) = self.hand(hand) else { return };
+                    MockEnv::set_sender(&user_id(user));
+                    let count = cards.len() as u32;
+                    let _ = block_on(self.fx.state.stake(hand_id, cards, count));
+                }
+                Action::ProposeBet { user, opponent, hand, predicts_bluff, amount } => {
+                    let Some(hand_id) = self.hand(hand) else { return };
+                    MockEnv::set_sender(&user_id(user));
+                    let proposed = self.fx.state.propose_side_bet(
+                        user_id(opponent),
+                        hand_id,
+                        predicts_bluff,
+                        amount,
+                    );
+                    if let Ok(bet) = block_on(proposed) {
+                        self.bets.push((bet.bet_id, bet.opponent_id));
+                    }
+                }
+                Action::AcceptBet { bet } => {
+                    let Some((bet_id, opponent)) = self.bets.get(bet).cloned() else { return };
+                    MockEnv::set_sender(&opponent);
+                    let _ = block_on(self.fx.state.accept_side_bet(bet_id));
+                }
+                Action::SpectatorBet { user, hand, predicts_bluff, amount } => {
+                    let Some(hand_id) = self.hand(hand) else { return };
+                    MockEnv::set_sender(&user_id(user));
+                    let placed = self.fx.state.place_side_bet(hand_id, predicts_bluff, amount);
+                    let _ = block_on(placed);
+                }
+                Action::Check { user, hand } => {
+                    let Some(hand_id) = self.hand(hand) else { return };
+                    MockEnv::set_sender(&user_id(user));
+                    let _ = block_on(self.fx.state.check(hand_id));
+                }
+                Action::Offer { user, cards, price, installments } => {
+                    MockEnv::set_sender(&user_id(user));
+                    let terms = installments.then_some(InstallmentTerms {
+                        count: 3,
+                        interval_blocks: 1,
+                        forfeit_percent: 40,
+                    });
+                    let listed = self.fx.state.offer(cards, price, 20, terms, None, None);
+                    if let Ok(offer) = block_on(listed) {
+                        self.fx.offer_ids.push(offer.offer_id);
+                    }
+                }
+                Action::Bid { user, offer, amount } => {
+                    let Some(offer_id) = self.offer(offer) else { return };
+                    MockEnv::set_sender(&user_id(user));
+                    let _ = block_on(self.fx.state.bid(offer_id, amount));
+                }
+                Action::WithdrawBid { offer } => {
+                    let Some(offer_id) = self.offer(offer) else { return };
+                    let Some(bidder) = self.fx.offer(&offer_id).and_then(|o| o.current_bidder_id)
+                    else {
+                        return;
+                    };
+                    MockEnv::set_sender(&bidder);
+                    let _ = block_on(self.fx.state.withdraw_bid(offer_id));
+                }
+                Action::Resolve { offer } => {
+                    let Some(offer_id) = self.offer(offer) else { return };
+                    let creator = self.fx.offer(&offer_id).unwrap().creator_id;
+                    MockEnv::set_sender(&creator);
+                    let _ = block_on(self.fx.state.resolve(offer_id));
+                }
+                Action::PayInstallment { offer } => {
+                    let Some(offer_id) = self.offer(offer) else { return };
+                    let Some(plan) = self.fx.state.installment_plans.get(&offer_id) else {
+                        return;
+                    };
+                    MockEnv::set_sender(&plan.buyer_id);
+                    let _ = block_on(self.fx.state.pay_installment(offer_id));
+                }
+                Action::ForfeitPlan { offer } => {
+                    let Some(offer_id) = self.offer(offer) else { return };
+                    let Some(plan) = self.fx.state.installment_plans.get(&offer_id) else {
+                        return;
+                    };
+                    MockEnv::set_sender(&plan.seller_id);
+                    let _ = block_on(self.fx.state.forfeit_installment_plan(offer_id));
+                }
+                Action::AdvanceBlocks { blocks } => {
+                    self.height += blocks;
+                    MockEnv::set_block_height(self.height);
+                }
+            }
+        }
+
+        fn assert_invariants(&self) {
+            // amounts are exact integers, so conservation holds to the chip.
+            // Installments paid into an open plan and withdrawals that have
+            // not completed are escrowed off the accounts. Debts are claims
+            // of the treasury rather than value, so they are left out
+            let state = &self.fx.state;
+            let held: u128 = self
+                .fx
+                .user_ids
+                .iter()
+                .map(|id| self.fx.user(id).unwrap())
+                .map(|u| u.balance as u128 + u.locked_balance as u128)
+                .sum();
+            let plans: u128 = self
+                .fx
+                .offer_ids
+                .iter()
+                .filter_map(|id| state.installment_plans.get(id))
+                .filter(|plan| !plan.is_settled)
+                .map(|plan| plan.paid as u128)
+                .sum();
+            let transfers: u128 = self
+                .transfers
+                .iter()
+                .filter_map(|(id, _)| state.transfer_intents.get(id))
+                .filter(|t| matches!(t.status, TransferStatus::Pending | TransferStatus::Failed))
+                .map(|t| t.amount as u128)
+                .sum();
+            let total = held + state.treasury as u128 + plans + transfers;
+            let supply = block_on(state.get_total_supply()) as u128;
+            assert_eq!(total, supply, "value not conserved: {} held vs {} issued", total, supply);
+        }
+    }
+
+    fn card() -> impl Strategy<Value = Card> {
+        prop::sample::select(Card::ALL.to_vec())
+    }
+
+    fn cards() -> impl Strategy<Value = Vec<Card>> {
+        prop::collection::vec(card(), 1..4)
+    }
+
+    fn amount() -> impl Strategy<Value = u64> {
+        1u64..5000
+    }
+
+    fn action() -> impl Strategy<Value = Action> {
+        let idx = 0usize..6;
+        prop_oneof![
+            (0..USERS, amount()).prop_map(|(user, amount)| Action::Withdraw { user, amount }),
+            (idx.clone(), any::<bool>()).prop_map(|(transfer, succeeded)| {
+                Action::SettleTransfer { transfer, succeeded }
+            }),
+            idx.clone().prop_map(|transfer| Action::ReclaimTransfer { transfer }),
+            (0..USERS, card(), cards())
+                .prop_map(|(user, claim, cards)| Action::StartHand { user, claim, cards }),
+            (0..USERS, idx.clone(), cards())
+                .prop_map(|(user, hand, cards)| Action::Stake { user, hand, cards }),
+            (0..USERS, 0..USERS, idx.clone(), any::<bool>(), amount()).prop_map(
+                |(user, opponent, hand, predicts_bluff, amount)| Action::ProposeBet {
+                    user,
+                    opponent,
+                    hand,
+                    predicts_bluff,
+                    amount
+                }
+            ),
+            idx.clone().prop_map(|bet| Action::AcceptBet { bet }),
+            (0..USERS, idx.clone(), any::<bool>(), amount()).prop_map(
+                |(user, hand, predicts_bluff, amount)| Action::SpectatorBet {
+                    user,
+                    hand,
+                    predicts_bluff,
+                    amount
+                }
+            ),
+            (0..USERS, idx.clone()).prop_map(|(user, hand)| Action::Check { user, hand }),
+            (0..USERS, cards(), amount(), any::<bool>()).prop_map(
+                |(user, cards, price, installments)| Action::Offer {
+                    user,
+                    cards,
+                    price,
+                    installments
+                }
+            ),
+            (0..USERS, idx.clone(), amount())
+                .prop_map(|(user, offer, amount)| Action::Bid { user, offer, amount }),
+            idx.clone().prop_map(|offer| Action::WithdrawBid { offer }),
+            idx.clone().prop_map(|offer| Action::Resolve { offer }),
+            idx.clone().prop_map(|offer| Action::PayInstallment { offer }),
+            idx.prop_map(|offer| Action::ForfeitPlan { offer }),
+            (1u64..8).prop_map(|blocks| Action::AdvanceBlocks { blocks }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn value_is_conserved_after_every_step(
+            actions in prop::collection::vec(action(), 1..64)
+        ) {
+            let mut run = Run::new();
+            run.assert_invariants();
+            for action in actions {
+                run.apply(action);
+                run.assert_invariants();
+            }
+            MockEnv::reset();
+        }
+    }
+}
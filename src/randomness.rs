@@ -0,0 +1,72 @@
+//! Commit-reveal randomness for card draws.
+//!
+//! A draw is paid for together with a commitment `sha256(salt)` in block
+//! `n`. Its seed pool is the entropy pool as block `n + 1` left it, frozen
+//! by the first mix of any later block, and its seed is
+//! `sha256(salt || seed pool)`. Other players' commits, reveals and
+//! contributions up to the end of block `n + 1` are unknown when the salt
+//! is committed, so the committer cannot grind a favourable salt, and once
+//! the seed pool is frozen the reveal block no longer changes the cards.
+//! Draws left unrevealed past their deadline are forfeited, so nobody can
+//! hold one back to reveal it only when it suits them.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use weil_macros::WeilType;
+
+#[cfg(feature = "testing")]
+use crate::testing::MockEnv;
+use crate::utils::to_hex;
+
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Default)]
+pub struct EntropyPool {
+    state: Vec<u8>,
+    // block of the latest mix
+    mixed_at_block: u64,
+}
+
+impl EntropyPool {
+    // true before the first mix of a block after the latest mixed one, when
+    // the pool still holds what every earlier block left in it
+    pub fn is_new_block(&self, height: u64) -> bool {
+        height > self.mixed_at_block
+    }
+
+    pub fn mix(&mut self, height: u64, parts: &[&[u8]]) {
+        self.mixed_at_block = self.mixed_at_block.max(height);
+        let mut hasher = Sha256::new();
+        hasher.update(&self.state);
+        for part in parts {
+            hasher.update(part);
+        }
+        self.state = hasher.finalize().to_vec();
+    }
+
+    pub fn snapshot(&self) -> String {
+        to_hex(&self.state)
+    }
+}
+
+pub fn commitment_for(salt: &str) -> String {
+    to_hex(&Sha256::digest(salt.as_bytes()))
+}
+
+pub fn draw_seed(salt: &str, seed_pool: &str) -> Vec<u8> {
+    #[cfg(feature = "testing")]
+    if let Some(seed) = MockEnv::random_seed() {
+        return seed.to_le_bytes().to_vec();
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(seed_pool.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+// every card of a draw gets its own hash of the seed so draws stay uniform
+pub fn draw_word(seed: &[u8], i: u32) -> u64 {
+    let digest = Sha256::new().chain_update(seed).chain_update(i.to_le_bytes()).finalize();
+    let mut word = [0u8; 8];
+    word.copy_from_slice(&digest[..8]);
+    u64::from_le_bytes(word)
+}
@@ -0,0 +1,59 @@
+//! Guardian based account recovery. An account names a few guardians and
+//! how many of them must agree. If its key is lost, the guardians start a
+//! recovery towards a fresh key; once enough of them approved, a challenge
+//! window opens during which the original key can still veto, and after it
+//! anyone can complete the recovery, moving the account to the new key.
+
+use crate::elements::{RecoveryGuardians, RecoveryRequest, RecoveryStatus};
+
+impl RecoveryGuardians {
+    pub fn validate(&self, owner_id: &str, max_guardians: usize) -> Result<(), String> {
+        if self.guardians.is_empty() || self.guardians.len() > max_guardians {
+            return Err(format!("name between 1 and {} guardians", max_guardians));
+        }
+        if self.guardians.iter().any(|g| g == owner_id) {
+            return Err("an account cannot guard itself".to_string());
+        }
+        let mut unique = self.guardians.clone();
+        unique.sort();
+        unique.dedup();
+        if unique.len() != self.guardians.len() {
+            return Err("guardians must be distinct".to_string());
+        }
+        if self.threshold == 0 || self.threshold as usize > self.guardians.len() {
+            return Err("threshold must be between 1 and the number of guardians".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn is_guardian(&self, user_id: &str) -> bool {
+        self.guardians.iter().any(|g| g == user_id)
+    }
+}
+
+impl RecoveryRequest {
+    pub fn new(old_id: String, new_id: String, height: u64) -> Self {
+        RecoveryRequest {
+            old_id,
+            new_id,
+            approvals: Vec::new(),
+            started_at_block: height,
+            executable_at_block: None,
+            status: RecoveryStatus::Pending,
+        }
+    }
+
+    // records a guardian's approval; the challenge window starts with the
+    // approval that reaches the threshold
+    pub fn approve(&mut self, guardian_id: String, threshold: u32, height: u64, window: u64) {
+        self.approvals.push(guardian_id);
+        if self.executable_at_block.is_none() && self.approvals.len() >= threshold as usize {
+            self.executable_at_block = Some(height.saturating_add(window));
+        }
+    }
+
+    pub fn is_executable(&self, height: u64) -> bool {
+        self.status == RecoveryStatus::Pending
+            && self.executable_at_block.is_some_and(|at| height >= at)
+    }
+}
@@ -0,0 +1,28 @@
+use crate::elements::SealedBid;
+use crate::randomness;
+
+impl SealedBid {
+    pub fn new(
+        bidder_id: String,
+        commitment: String,
+        committed_at_block: u64,
+        deposit: u64,
+    ) -> Self {
+        SealedBid {
+            bidder_id,
+            commitment,
+            committed_at_block,
+            revealed_amount: None,
+            deposit,
+        }
+    }
+
+    // bids are committed as the hex encoded sha256 of "<amount>:<salt>"
+    pub fn commitment_for(amount: u64, salt: &str) -> String {
+        randomness::commitment_for(&format!("{}:{}", amount, salt))
+    }
+
+    pub fn matches(&self, amount: u64, salt: &str) -> bool {
+        SealedBid::commitment_for(amount, salt) == self.commitment
+    }
+}
@@ -0,0 +1,147 @@
+use escalate_rules::CHIPS_PER_UNIT;
+
+use crate::elements::{
+    NotableHand, ReferralStanding, Season, SeasonArchive, SeasonConfig, UserStats,
+};
+
+const MAX_SEASON_PRIZES: usize = 10;
+// referees count for their referrer once they played this many hands
+pub const REFERRAL_MIN_HANDS: usize = 3;
+// season-end referral rewards by qualified referees, best tier first
+const REFERRAL_TIERS: [(u32, u64); 3] =
+    [(10, 20 * CHIPS_PER_UNIT), (5, 8 * CHIPS_PER_UNIT), (1, 2 * CHIPS_PER_UNIT)];
+// the most one season pays out in referral rewards
+pub const REFERRAL_BUDGET: u64 = 50 * CHIPS_PER_UNIT;
+// players and hands an ended season keeps in its archive
+const ARCHIVED_STANDINGS: usize = 25;
+const NOTABLE_HANDS: usize = 5;
+
+impl Season {
+    pub fn new(season_id: String, config: SeasonConfig, height: u64) -> Self {
+        Season {
+            season_id,
+            config,
+            started_at_block: height,
+            ended_at_block: None,
+            players: Vec::new(),
+            prizes_paid: Vec::new(),
+            volume: 0,
+            notable_hands: Vec::new(),
+            referees: Vec::new(),
+            referral_rewards: Vec::new(),
+        }
+    }
+
+    pub fn has_player(&self, user_id: &str) -> bool {
+        self.players.iter().any(|p| p == user_id)
+    }
+
+    // referred or rewarded as a referrer during the season
+    pub fn has_referral(&self, user_id: &str) -> bool {
+        self.referees.iter().any(|r| r == user_id)
+            || self.referral_rewards.iter().any(|r| r.user_id == user_id)
+    }
+
+    // adds the pot to the volume and keeps the hand if its pot is among the
+    // biggest; an equal pot does not push out an earlier hand
+    pub fn record_checked_hand(&mut self, hand: NotableHand) {
+        self.volume += hand.pot;
+        let at = self.notable_hands.partition_point(|h| h.pot >= hand.pot);
+        if at < NOTABLE_HANDS {
+            self.notable_hands.insert(at, hand);
+            self.notable_hands.truncate(NOTABLE_HANDS);
+        }
+    }
+}
+
+impl SeasonArchive {
+    // `standings` are the season's final standings, best first
+    pub fn new(season: &Season, mut standings: Vec<UserStats>) -> Self {
+        standings.truncate(ARCHIVED_STANDINGS);
+        SeasonArchive {
+            season_id: season.season_id.clone(),
+            name: season.config.name.clone(),
+            metric: season.config.metric,
+            started_at_block: season.started_at_block,
+            ended_at_block: season.ended_at_block.unwrap_or_default(),
+            standings,
+            prizes_paid: season.prizes_paid.clone(),
+            volume: season.volume,
+            notable_hands: season.notable_hands.clone(),
+        }
+    }
+
+    pub fn rename_player(&mut self, old_id: &str, new_id: &str) {
+        for stats in self.standings.iter_mut().filter(|s| s.user_id == old_id) {
+            stats.user_id = new_id.to_string();
+        }
+        for prize in self.prizes_paid.iter_mut().filter(|p| p.user_id == old_id) {
+            prize.user_id = new_id.to_string();
+        }
+        for hand in self.notable_hands.iter_mut().filter(|h| h.checker_id == old_id) {
+            hand.checker_id = new_id.to_string();
+        }
+    }
+}
+
+impl SeasonConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("season name cannot be empty".to_string());
+        }
+        if self.prizes.len() > MAX_SEASON_PRIZES {
+            return Err(format!("a season pays at most {} prizes", MAX_SEASON_PRIZES));
+        }
+        Ok(())
+    }
+}
+
+// season stats are kept per player under the season they were earned in
+pub fn stats_key(season_id: &str, user_id: &str) -> String {
+    format!("{}/{}", season_id, user_id)
+}
+
+// the season's players ranked by its metric, best first; ties keep the
+// order players first scored in
+pub fn standings(season: &Season, mut stats: Vec<UserStats>) -> Vec<UserStats> {
+    stats.sort_by_key(|s| std::cmp::Reverse(s.metric(season.config.metric)));
+    stats
+}
+
+pub fn referral_reward(qualified_referees: u32) -> u64 {
+    REFERRAL_TIERS
+        .iter()
+        .find(|(min, _)| qualified_referees >= *min)
+        .map_or(0, |(_, reward)| *reward)
+}
+
+// one standing per referrer from `(referrer, qualified)` pairs in the order
+// the referees registered, most qualified referees first; ties keep the
+// order of the referrers' first referral
+pub fn referral_standings(referrals: Vec<(String, bool)>) -> Vec<ReferralStanding> {
+    let mut standings: Vec<ReferralStanding> = Vec::new();
+    for (referrer_id, qualified) in referrals {
+        let at = match standings.iter().position(|s| s.referrer_id == referrer_id) {
+            Some(at) => at,
+            None => {
+                standings.push(ReferralStanding {
+                    referrer_id,
+                    qualified_referees: 0,
+                    pending_referees: 0,
+                    reward: 0,
+                });
+                standings.len() - 1
+            }
+        };
+        if qualified {
+            standings[at].qualified_referees += 1;
+        } else {
+            standings[at].pending_referees += 1;
+        }
+    }
+    for standing in &mut standings {
+        standing.reward = referral_reward(standing.qualified_referees);
+    }
+    standings.sort_by_key(|s| std::cmp::Reverse(s.qualified_referees));
+    standings
+}
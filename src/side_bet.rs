@@ -0,0 +1,59 @@
+use crate::elements::{PoolWager, SideBet, SpectatorPool};
+
+impl SideBet {
+    pub fn new(
+        bet_id: String,
+        hand_id: String,
+        proposer_id: String,
+        opponent_id: String,
+        predicts_bluff: bool,
+        amount: u64,
+    ) -> Self {
+        SideBet {
+            bet_id,
+            hand_id,
+            proposer_id,
+            opponent_id,
+            predicts_bluff,
+            amount,
+            is_accepted: false,
+            is_settled: false,
+        }
+    }
+
+    // the user who takes the whole escrow once the hand is checked
+    pub fn winner(&self, bluff_detected: bool) -> &str {
+        if self.predicts_bluff == bluff_detected {
+            &self.proposer_id
+        } else {
+            &self.opponent_id
+        }
+    }
+}
+
+impl SpectatorPool {
+    pub fn new(hand_id: String) -> Self {
+        SpectatorPool {
+            hand_id,
+            wagers: Vec::new(),
+            is_settled: false,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.wagers.iter().map(|w| w.amount).sum()
+    }
+
+    pub fn has_wager_from(&self, user_id: &str) -> bool {
+        self.wagers.iter().any(|w| w.user_id == user_id)
+    }
+
+    pub fn place(&mut self, user_id: String, predicts_bluff: bool, amount: u64, height: u64) {
+        self.wagers.push(PoolWager {
+            user_id,
+            predicts_bluff,
+            amount,
+            placed_at_block: height,
+        });
+    }
+}
@@ -0,0 +1,49 @@
+use crate::elements::{Card, Challenge, ChallengeStatus, HandOptions};
+
+impl Challenge {
+    pub fn new(
+        challenge_id: String,
+        challenger_id: String,
+        friend_id: String,
+        claim: Card,
+        cards: Vec<Card>,
+        expires_at_block: u64,
+    ) -> Self {
+        Challenge {
+            challenge_id,
+            challenger_id,
+            friend_id,
+            claim,
+            cards,
+            status: ChallengeStatus::Pending,
+            hand_id: None,
+            expires_at_block,
+        }
+    }
+
+    pub fn is_expired(&self, height: u64) -> bool {
+        height >= self.expires_at_block
+    }
+
+    pub fn involves(&self, user_id: &str) -> bool {
+        self.challenger_id == user_id || self.friend_id == user_id
+    }
+
+    // a hand only the challenger and their friend can play
+    pub fn hand_options(&self, duration_blocks: u64) -> HandOptions {
+        HandOptions {
+            region_tag: None,
+            duration_blocks,
+            min_raise: 0,
+            max_stakes: u32::MAX,
+            alternate_stakers: false,
+            max_stakes_per_player: None,
+            newcomers_only: false,
+            participants_only: false,
+            turn_based: false,
+            allowed_stakers: vec![self.friend_id.clone()],
+            min_reputation: None,
+            tournament_id: None,
+        }
+    }
+}
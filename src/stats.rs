@@ -0,0 +1,88 @@
+use crate::elements::{
+    Card, ClaimTotals, Hand, ResolutionStats, ResolutionTotals, StatsMetric, UserStats,
+};
+use crate::token;
+
+impl ResolutionTotals {
+    // running totals after one more checked hand
+    pub fn record(&self, hand: &Hand, bluff_detected: bool) -> Self {
+        ResolutionTotals {
+            resolutions: self.resolutions + 1,
+            bluffs: self.bluffs + bluff_detected as u64,
+            stakes: self.stakes + hand.stakes.len() as u64,
+            staked_cards: self.staked_cards + hand.staked_card_count() as u64,
+            // the checker takes the last stake exactly when it was a bluff
+            checker_wins: self.checker_wins + bluff_detected as u64,
+        }
+    }
+}
+
+impl ClaimTotals {
+    // running totals after one more hand claiming `claim`
+    pub fn record(&self, claim: Card) -> Self {
+        let mut claims = self.claims.clone();
+        claims.resize(Card::ALL.len(), 0);
+        claims[token::token_id(claim)] += 1;
+        ClaimTotals { hands: self.hands + 1, claims }
+    }
+
+    // claims of each rank made after `older` up to and including `self`
+    pub fn since(&self, older: &ClaimTotals) -> Vec<u64> {
+        (0..Card::ALL.len())
+            .map(|i| {
+                let count = |t: &ClaimTotals| t.claims.get(i).copied().unwrap_or_default();
+                count(self) - count(older)
+            })
+            .collect()
+    }
+}
+
+impl ResolutionStats {
+    // stats for the resolutions recorded after `older` up to and including `newer`
+    pub fn between(older: &ResolutionTotals, newer: &ResolutionTotals) -> Self {
+        let resolutions = newer.resolutions - older.resolutions;
+        let stakes = newer.stakes - older.stakes;
+        let staked_cards = newer.staked_cards - older.staked_cards;
+        let ratio = |part: u64, whole: u64| {
+            if whole == 0 { 0.0 } else { part as f64 / whole as f64 }
+        };
+
+        ResolutionStats {
+            resolutions,
+            bluff_rate: ratio(newer.bluffs - older.bluffs, resolutions),
+            average_stake_size: ratio(staked_cards, stakes),
+            average_pot: ratio(staked_cards, resolutions),
+            checker_win_rate: ratio(newer.checker_wins - older.checker_wins, resolutions),
+        }
+    }
+}
+
+impl UserStats {
+    pub fn new(user_id: String) -> Self {
+        UserStats {
+            user_id,
+            hands_started: 0,
+            bluffs_caught: 0,
+            bluffs_gotten_away_with: 0,
+            successful_checks: 0,
+            total_winnings: 0,
+            checked_stakes: 0,
+            honest_stakes: 0,
+            jokers_sold: 0,
+        }
+    }
+
+    pub fn honesty_index(&self) -> Option<u64> {
+        (self.checked_stakes > 0).then(|| self.honest_stakes * 100 / self.checked_stakes)
+    }
+
+    pub fn metric(&self, metric: StatsMetric) -> i64 {
+        match metric {
+            StatsMetric::HandsStarted => self.hands_started as i64,
+            StatsMetric::BluffsCaught => self.bluffs_caught as i64,
+            StatsMetric::BluffsGottenAwayWith => self.bluffs_gotten_away_with as i64,
+            StatsMetric::SuccessfulChecks => self.successful_checks as i64,
+            StatsMetric::TotalWinnings => self.total_winnings,
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::elements::{TeamCheck, TeamHand, TeamOptions};
+
+// players on each side of a team hand
+pub const TEAM_SIZE: usize = 2;
+
+impl TeamHand {
+    pub fn new(hand_id: String, creator: String, options: TeamOptions) -> Self {
+        TeamHand {
+            hand_id,
+            team_a: vec![creator, options.teammate],
+            team_b: options.opponents,
+            confirm_window_blocks: options.confirm_window_blocks,
+            pending_check: None,
+        }
+    }
+
+    // 0 for `team_a`, 1 for `team_b`
+    pub fn team_of(&self, user_id: &str) -> Option<usize> {
+        if self.team_a.iter().any(|m| m == user_id) {
+            Some(0)
+        } else if self.team_b.iter().any(|m| m == user_id) {
+            Some(1)
+        } else {
+            None
+        }
+    }
+
+    pub fn members(&self, team: usize) -> &[String] {
+        if team == 0 { &self.team_a } else { &self.team_b }
+    }
+
+    pub fn majority(&self, team: usize) -> usize {
+        self.members(team).len() / 2 + 1
+    }
+
+    // records `user_id` confirming the check, starting a fresh window if
+    // none is open or the last one lapsed. True once the majority of `team`
+    // is in, confirmations from the other team never count
+    pub fn confirm(&mut self, user_id: &str, team: usize, height: u64) -> bool {
+        let window = self.confirm_window_blocks;
+        let mut check = match self.pending_check.take() {
+            Some(check) if height <= check.opened_at_block.saturating_add(window) => check,
+            _ => TeamCheck {
+                confirmations: Vec::new(),
+                opened_at_block: height,
+            },
+        };
+        if !check.confirmations.iter().any(|c| c == user_id) {
+            check.confirmations.push(user_id.to_string());
+        }
+        let members = self.members(team);
+        let confirmed = check.confirmations.iter().filter(|c| members.contains(c)).count();
+        let reached = confirmed >= self.majority(team);
+        self.pending_check = Some(check);
+        reached
+    }
+}
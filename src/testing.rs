@@ -0,0 +1,161 @@
+//! Fixtures and a mock environment for exercising the contract
+//! outside the live Weil runtime. Only built with the `testing` feature.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+
+use escalate_rules::CHIPS_PER_UNIT;
+
+pub use crate::elements::{Card, Hand, HandOptions, HandStatus, Offer, Stake, User};
+use crate::{Escalate, EscalateContractState};
+
+#[derive(Default)]
+struct MockState {
+    sender: Option<String>,
+    block_height: Option<u64>,
+    random_seed: Option<u64>,
+}
+
+thread_local! {
+    static MOCK: RefCell<MockState> = RefCell::new(MockState::default());
+}
+
+// overrides for what the contract reads from the runtime, unset
+// values fall through to the real runtime
+pub struct MockEnv;
+
+impl MockEnv {
+    pub fn set_sender(sender: &str) {
+        MOCK.with(|m| m.borrow_mut().sender = Some(sender.to_string()));
+    }
+
+    pub fn set_block_height(height: u64) {
+        MOCK.with(|m| m.borrow_mut().block_height = Some(height));
+    }
+
+    // replaces the commit-reveal seed of every draw
+    pub fn set_random_seed(seed: u64) {
+        MOCK.with(|m| m.borrow_mut().random_seed = Some(seed));
+    }
+
+    pub fn reset() {
+        MOCK.with(|m| *m.borrow_mut() = MockState::default());
+    }
+
+    pub(crate) fn sender() -> Option<String> {
+        MOCK.with(|m| m.borrow().sender.clone())
+    }
+
+    pub(crate) fn block_height() -> Option<u64> {
+        MOCK.with(|m| m.borrow().block_height)
+    }
+
+    pub(crate) fn random_seed() -> Option<u64> {
+        MOCK.with(|m| m.borrow().random_seed)
+    }
+}
+
+// runs a contract call to completion; entrypoints never actually await,
+// so a single poll always finishes them
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut cx = Context::from_waker(Waker::noop());
+    match pin!(future).poll(&mut cx) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("contract calls are expected to complete without waiting"),
+    }
+}
+
+pub struct Fixture {
+    pub state: EscalateContractState,
+    pub user_ids: Vec<String>,
+    pub hand_ids: Vec<String>,
+    pub offer_ids: Vec<String>,
+}
+
+impl Fixture {
+    pub fn user(&self, id: &str) -> Option<User> {
+        self.state.users.get(&id.to_string())
+    }
+
+    pub fn hand(&self, id: &str) -> Option<Hand> {
+        self.state.hands.get(&id.to_string())
+    }
+
+    pub fn offer(&self, id: &str) -> Option<Offer> {
+        self.state.offers.get(&id.to_string())
+    }
+}
+
+// builds a state with `users` registered players holding a full deck each,
+// `open_hands` unresolved hands claiming ACE and `live_offers` auctions of
+// a KING that already carry a bid from the next user. Everything goes
+// through the entrypoints at block 0, the mock sender and height are put
+// back afterwards
+pub fn fixture(users: usize, open_hands: usize, live_offers: usize) -> Fixture {
+    assert!(
+        users > 0 || (open_hands == 0 && live_offers == 0),
+        "hands and offers need at least one user"
+    );
+
+    let mut state = EscalateContractState::new().expect("constructor never fails");
+    let (sender, block_height) = (MockEnv::sender(), MockEnv::block_height());
+    MockEnv::set_block_height(0);
+
+    let user_ids: Vec<String> = (0..users).map(|i| format!("user-{}", i)).collect();
+    let starter_cards = std::mem::replace(&mut state.params.starter_cards, Card::ALL.repeat(4));
+    for id in &user_ids {
+        MockEnv::set_sender(id);
+        block_on(state.register_user(format!("fixture {}", id))).expect("fixture users register");
+    }
+    state.params.starter_cards = starter_cards;
+
+    let mut hand_ids = Vec::new();
+    for i in 0..open_hands {
+        MockEnv::set_sender(&user_ids[i % users]);
+        let options = HandOptions {
+            region_tag: None,
+            duration_blocks: u64::MAX,
+            min_raise: 0,
+            max_stakes: u32::MAX,
+            alternate_stakers: false,
+            max_stakes_per_player: None,
+            newcomers_only: false,
+            participants_only: false,
+            turn_based: false,
+            allowed_stakers: Vec::new(),
+            min_reputation: None,
+            tournament_id: None,
+        };
+        let hand = block_on(state.start_hand(Card::ACE, 1, vec![Card::ACE], options))
+            .expect("fixture users start with enough cards");
+        hand_ids.push(hand.hand_id);
+    }
+
+    let mut offer_ids = Vec::new();
+    for i in 0..live_offers {
+        MockEnv::set_sender(&user_ids[i % users]);
+        let listed = state.offer(vec![Card::KING], CHIPS_PER_UNIT, u64::MAX, None, None, None);
+        let offer = block_on(listed).expect("fixture users can pay the listing fee");
+        if users > 1 {
+            MockEnv::set_sender(&user_ids[(i + 1) % users]);
+            block_on(state.bid(offer.offer_id.clone(), 2 * CHIPS_PER_UNIT))
+                .expect("fixture users start with enough chips");
+        }
+        offer_ids.push(offer.offer_id);
+    }
+
+    MOCK.with(|m| {
+        let mut mock = m.borrow_mut();
+        mock.sender = sender;
+        mock.block_height = block_height;
+    });
+
+    Fixture {
+        state,
+        user_ids,
+        hand_ids,
+        offer_ids,
+    }
+}
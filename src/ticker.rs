@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use weil_macros::WeilType;
+
+use crate::elements::Event;
+
+// a fixed number of the latest events, kept next to the full log so a live
+// ticker can read them in a single call; once full, every push overwrites
+// the oldest slot
+#[derive(Debug, Serialize, Deserialize, WeilType, Clone, Default)]
+pub struct RecentEvents {
+    slots: Vec<Event>,
+    // slot the next event goes into once the buffer is full
+    head: usize,
+}
+
+impl RecentEvents {
+    pub fn push(&mut self, event: Event, capacity: usize) {
+        if self.slots.len() < capacity {
+            self.slots.push(event);
+        } else {
+            self.slots[self.head] = event;
+            self.head = (self.head + 1) % capacity;
+        }
+    }
+
+    pub fn newest_first(&self) -> Vec<Event> {
+        let (older, newer) = self.slots.split_at(self.head);
+        newer.iter().chain(older).rev().cloned().collect()
+    }
+}
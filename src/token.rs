@@ -0,0 +1,30 @@
+//! Card holdings seen through a standard multi-token interface: every rank
+//! is a token id and a user's balance of it is how many of those cards sit
+//! in their inventory. Cards escrowed in hands, offers, trades or plans are
+//! counted in the supply but not in anyone's balance until they come back.
+//! Stakes of a checked hand go back to the deck and leave the supply.
+
+use crate::elements::Card;
+
+// token id of a rank, its position in `Card::ALL`
+pub fn token_id(card: Card) -> usize {
+    Card::ALL.iter().position(|c| *c == card).expect("every rank is in Card::ALL")
+}
+
+pub fn balance_of(cards: &[Card], card: Card) -> u64 {
+    cards.iter().filter(|c| **c == card).count() as u64
+}
+
+// adds newly drawn cards to the per-rank supply counters
+pub fn record_minted(supply: &mut [u64], cards: &[Card]) {
+    for card in cards {
+        supply[token_id(*card)] += 1;
+    }
+}
+
+// removes cards returned to the deck from the per-rank supply counters
+pub fn record_burned(supply: &mut [u64], cards: &[Card]) {
+    for card in cards {
+        supply[token_id(*card)] = supply[token_id(*card)].saturating_sub(1);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::elements::{PayoutLine, Standing, Tournament, TournamentConfig, TournamentStatus};
+
+impl Tournament {
+    pub fn new(
+        tournament_id: String,
+        creator_id: String,
+        config: TournamentConfig,
+        height: u64,
+    ) -> Self {
+        let prize_pool = config.prize_escrow;
+        Tournament {
+            tournament_id,
+            creator_id,
+            config,
+            status: TournamentStatus::Open,
+            players: Vec::new(),
+            hand_ids: Vec::new(),
+            prize_pool,
+            created_at_block: height,
+        }
+    }
+
+    pub fn has_player(&self, user_id: &str) -> bool {
+        self.players.iter().any(|p| p == user_id)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.players.len() >= self.config.max_players as usize
+    }
+
+    // every entry fee paid by the players, each refunded when no prize is paid
+    pub fn entry_fee_refunds(&self) -> Vec<(String, u64)> {
+        self.players.iter().map(|p| (p.clone(), self.config.entry_fee)).collect()
+    }
+
+    pub fn has_started(&self, height: u64) -> bool {
+        height >= self.config.starts_at_block
+    }
+
+    pub fn has_ended(&self, height: u64) -> bool {
+        height >= self.config.ends_at_block
+    }
+
+    // tournament hands can only be started while the tournament runs
+    pub fn is_running(&self, height: u64) -> bool {
+        self.status == TournamentStatus::Open && self.has_started(height) && !self.has_ended(height)
+    }
+}
+
+impl TournamentConfig {
+    pub fn validate(&self, height: u64) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("tournament name cannot be empty".to_string());
+        }
+        if self.max_players < 2 {
+            return Err("a tournament needs room for at least two players".to_string());
+        }
+        if self.starts_at_block < height {
+            return Err("tournament cannot start in the past".to_string());
+        }
+        if self.ends_at_block <= self.starts_at_block {
+            return Err("tournament must end after it starts".to_string());
+        }
+        Ok(())
+    }
+}
+
+// every player's net chips over the payouts of the tournament's checked
+// hands, best first; ties keep the order players joined in
+pub fn standings(tournament: &Tournament, hand_payouts: &[Vec<PayoutLine>]) -> Vec<Standing> {
+    let mut standings: Vec<Standing> = tournament
+        .players
+        .iter()
+        .map(|p| Standing {
+            user_id: p.clone(),
+            net_winnings: 0,
+            hands_played: 0,
+        })
+        .collect();
+    for lines in hand_payouts {
+        for standing in standings.iter_mut() {
+            let mine: Vec<&PayoutLine> =
+                lines.iter().filter(|l| l.user_id == standing.user_id).collect();
+            if mine.is_empty() {
+                continue;
+            }
+            standing.net_winnings += mine.iter().map(|l| l.amount).sum::<i64>();
+            standing.hands_played += 1;
+        }
+    }
+    standings.sort_by_key(|s| std::cmp::Reverse(s.net_winnings));
+    standings
+}
+
+// splits `pool` between the top places by `split` percentages, scaled over
+// the places that have a player; rounding leftovers go to first place
+pub fn prize_shares(pool: u64, split: &[u64], players: usize) -> Vec<u64> {
+    let split = &split[..split.len().min(players)];
+    let weight: u64 = split.iter().sum();
+    if weight == 0 {
+        return Vec::new();
+    }
+    let mut shares: Vec<u64> = split.iter().map(|p| pool * p / weight).collect();
+    shares[0] += pool - shares.iter().sum::<u64>();
+    shares
+}
@@ -0,0 +1,26 @@
+use crate::elements::{Card, Trade, TradeStatus};
+
+impl Trade {
+    pub fn new(
+        trade_id: String,
+        proposer_id: String,
+        counterparty_id: String,
+        offered_cards: Vec<Card>,
+        requested_cards: Vec<Card>,
+        height: u64,
+    ) -> Self {
+        Trade {
+            trade_id,
+            proposer_id,
+            counterparty_id,
+            offered_cards,
+            requested_cards,
+            status: TradeStatus::Open,
+            proposed_at_block: height,
+        }
+    }
+
+    pub fn involves(&self, user_id: &str) -> bool {
+        self.proposer_id == user_id || self.counterparty_id == user_id
+    }
+}
@@ -0,0 +1,24 @@
+use crate::elements::{TransferIntent, TransferStatus};
+
+impl TransferIntent {
+    pub fn new(intent_id: String, user_id: String, amount: u64, height: u64, timeout: u64) -> Self {
+        TransferIntent {
+            intent_id,
+            user_id,
+            amount,
+            status: TransferStatus::Pending,
+            created_at_block: height,
+            expires_at_block: height.saturating_add(timeout),
+        }
+    }
+
+    // failed transfers and ones nobody reported on before the timeout
+    // return their escrow to the user
+    pub fn is_reclaimable(&self, height: u64) -> bool {
+        match self.status {
+            TransferStatus::Failed => true,
+            TransferStatus::Pending => height >= self.expires_at_block,
+            TransferStatus::Completed | TransferStatus::Reclaimed => false,
+        }
+    }
+}
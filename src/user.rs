@@ -1,20 +1,93 @@
 use crate::elements::{Card, User};
 
 impl User {
-    pub fn new(user_id: String, bio: String) -> Self {
+    pub fn new(user_id: String, bio: String, registered_at_block: u64, balance: u64) -> Self {
         User {
             user_id,
             bio,
-            balance: 100.0,
+            balance,
+            locked_balance: 0,
             cards: Vec::new(),
+            registered_at_block,
+            cold_storage: false,
+            unlock_at_block: None,
+            high_roller: false,
+            reputation: 0,
+            starter_granted: false,
+            last_claim_block: None,
+            crafting_points: 0,
+            referrer: None,
         }
     }
 
-    pub fn deposit(&mut self, amount: f64) {
+    pub fn is_locked(&self, height: u64) -> bool {
+        self.cold_storage && self.unlock_at_block.is_none_or(|at| height < at)
+    }
+
+    // accounts registered less than `age_blocks` ago play in the newcomer bracket
+    pub fn is_newcomer(&self, height: u64, age_blocks: u64) -> bool {
+        height < self.registered_at_block.saturating_add(age_blocks)
+    }
+
+    // whether a free daily draw is due, `window` blocks after the last one
+    pub fn can_claim_daily(&self, height: u64, window: u64) -> bool {
+        self.last_claim_block.is_none_or(|at| height >= at.saturating_add(window))
+    }
+
+    // how many of the oldest cards decay takes, `percent` of the inventory
+    // but always at least one card
+    pub fn decaying_cards(&self, percent: u64) -> usize {
+        let count = (self.cards.len() as u64 * percent / 100) as usize;
+        count.max(1).min(self.cards.len())
+    }
+
+    // moves `amount` out of the spendable balance into escrow
+    pub fn lock(&mut self, amount: u64) -> Result<(), String> {
+        if self.balance < amount {
+            return Err("insufficient balance to lock".to_string());
+        }
+        self.balance -= amount;
+        self.locked_balance += amount;
+        Ok(())
+    }
+
+    // hands escrowed chips back to the spendable balance. Escrow taken
+    // before the ledger existed was never recorded, so the ledger only
+    // gives up what it holds
+    pub fn unlock(&mut self, amount: u64) {
+        self.locked_balance = self.locked_balance.saturating_sub(amount);
         self.balance += amount;
     }
 
+    // drops escrow that was paid on to someone else
+    pub fn settle(&mut self, amount: u64) {
+        self.locked_balance = self.locked_balance.saturating_sub(amount);
+    }
+
+    // `cards` is kept in the order the user acquired them, oldest first:
+    // every card a user gains is appended here, and cards leave through
+    // `remove_cards_from_inventory`, which gives up the oldest copy of a rank
     pub fn add_cards(&mut self, new_cards: Vec<Card>) {
         self.cards.extend(new_cards);
     }
-}
\ No newline at end of file
+
+    pub fn take_oldest_cards(&mut self, count: usize) -> Vec<Card> {
+        self.cards.drain(..count.min(self.cards.len())).collect()
+    }
+
+    // applies a reward or penalty without pushing the balance below `floor`;
+    // any shortfall is tracked in `debt`, which credits pay down first
+    pub fn apply_delta(&mut self, delta: i64, debt: &mut u64, floor: u64) {
+        let amount = delta.unsigned_abs();
+        if delta >= 0 {
+            let repaid = amount.min(*debt);
+            *debt -= repaid;
+            self.balance += amount - repaid;
+        } else {
+            let available = self.balance.saturating_sub(floor);
+            let charged = amount.min(available);
+            self.balance -= charged;
+            *debt += amount - charged;
+        }
+    }
+}